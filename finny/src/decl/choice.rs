@@ -0,0 +1,74 @@
+use crate::{EventContext, FsmBackend, FsmCompletionEvent, lib::*};
+
+use super::FsmQueueMock;
+
+/// A transient choice pseudo-state. The outgoing transitions are evaluated in the declaration order
+/// right after entering it, the `otherwise` transition is taken if none of the guards pass.
+pub struct FsmChoiceBuilder<TFsm, TContext, TChoice> {
+	pub (crate) _fsm: PhantomData<TFsm>,
+	pub (crate) _context: PhantomData<TContext>,
+	pub (crate) _choice: PhantomData<TChoice>
+}
+
+impl<TFsm, TContext, TChoice> FsmChoiceBuilder<TFsm, TContext, TChoice>
+	where TFsm: FsmBackend<Context = TContext>
+{
+	/// A guarded branch of this choice.
+	pub fn transition_to<'b, TStateTo>(&'b self) -> FsmChoiceBuilderTransition<'b, TFsm, TContext, TChoice, TStateTo> {
+		FsmChoiceBuilderTransition {
+			_choice_builder: self,
+			_state_to: PhantomData::default()
+		}
+	}
+
+	/// The fallback branch of this choice, taken when none of the guards pass. Required!
+	pub fn otherwise<'b, TStateTo>(&'b self) -> FsmChoiceBuilderOtherwise<'b, TFsm, TContext, TChoice, TStateTo> {
+		FsmChoiceBuilderOtherwise {
+			_choice_builder: self,
+			_state_to: PhantomData::default()
+		}
+	}
+}
+
+pub struct FsmChoiceBuilderTransition<'a, TFsm, TContext, TChoice, TStateTo> {
+	_choice_builder: &'a FsmChoiceBuilder<TFsm, TContext, TChoice>,
+	_state_to: PhantomData<TStateTo>
+}
+
+impl<'a, TFsm, TContext, TChoice, TStateTo> FsmChoiceBuilderTransition<'a, TFsm, TContext, TChoice, TStateTo>
+	where TFsm: FsmBackend
+{
+	/// The guard for taking this branch.
+	pub fn guard<TGuard: Fn(&FsmCompletionEvent, &EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &<TFsm as FsmBackend>::States) -> bool>(&mut self, _guard: TGuard) -> &mut Self {
+		self
+	}
+
+	/// An action that happens between leaving the choice and entering the target state.
+	pub fn action<TAction: Fn(&FsmCompletionEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &mut TChoice, &mut TStateTo)>(&mut self, _action: TAction) -> &mut Self {
+		self
+	}
+
+	/// A type for this transition. The struct for the transition will be generated.
+	pub fn with_transition_ty<TTransition>(&mut self) -> &mut Self {
+		self
+	}
+}
+
+pub struct FsmChoiceBuilderOtherwise<'a, TFsm, TContext, TChoice, TStateTo> {
+	_choice_builder: &'a FsmChoiceBuilder<TFsm, TContext, TChoice>,
+	_state_to: PhantomData<TStateTo>
+}
+
+impl<'a, TFsm, TContext, TChoice, TStateTo> FsmChoiceBuilderOtherwise<'a, TFsm, TContext, TChoice, TStateTo>
+	where TFsm: FsmBackend
+{
+	/// An action that happens between leaving the choice and entering the target state.
+	pub fn action<TAction: Fn(&FsmCompletionEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &mut TChoice, &mut TStateTo)>(&mut self, _action: TAction) -> &mut Self {
+		self
+	}
+
+	/// A type for this transition. The struct for the transition will be generated.
+	pub fn with_transition_ty<TTransition>(&mut self) -> &mut Self {
+		self
+	}
+}
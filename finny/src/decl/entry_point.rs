@@ -0,0 +1,17 @@
+use crate::{FsmBackend, lib::*};
+
+/// The entry point of a machine, see `FsmBuilder::entry_point`.
+pub struct FsmEntryPointBuilder<TFsm, TContext, TEntryPoint> {
+	pub (crate) _fsm: PhantomData<TFsm>,
+	pub (crate) _context: PhantomData<TContext>,
+	pub (crate) _entry_point: PhantomData<TEntryPoint>
+}
+
+impl<TFsm, TContext, TEntryPoint> FsmEntryPointBuilder<TFsm, TContext, TEntryPoint>
+	where TFsm: FsmBackend<Context = TContext>
+{
+	/// The state that is entered through this entry point.
+	pub fn transition_to<TState>(&self) {
+
+	}
+}
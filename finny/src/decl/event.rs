@@ -1,6 +1,6 @@
 use crate::lib::*;
 
-use crate::{FsmBackend, fsm::EventContext};
+use crate::{FsmBackend, FsmEntryPoint, fsm::EventContext};
 use super::{FsmQueueMock, FsmStateBuilder};
 
 pub struct FsmEventBuilderState<'a, TFsm, TContext, TEvent, TState> {
@@ -70,11 +70,33 @@ impl<'a, TFsm, TContext, TEvent, TStateFrom, TStateTo> FsmEventBuilderTransition
         self
     }
 
+    /// An action that returns a future, which is driven by the `AsyncFsmFrontend`. The target state is entered
+    /// once the future completes, the events that arrive in the meantime are queued. The future has to be `Send`.
+    ///
+    /// The region is `FsmCurrentState::InTransition` until then. Only these actions can be async, the guards
+    /// and the entry and exit actions are rejected if they return a future. The machine has to be built with
+    /// `new_async_with`, can't be restored from a snapshot and can't be used as a submachine.
+    #[cfg(all(feature="async", feature="std"))]
+    pub fn action_async<TFuture, TAction>(&mut self, _action: TAction) -> &mut Self
+        where
+            TFuture: core::future::Future<Output = ()> + Send + 'static,
+            TAction: Fn(&TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &mut TStateFrom, &mut TStateTo) -> TFuture
+    {
+        self
+    }
+
     /// A guard for starting this transition from one state to another, including executing the action.
     pub fn guard<TGuard: Fn(&TEvent, &EventContext<'a, TFsm, FsmQueueMock<TFsm>>, &<TFsm as FsmBackend>::States) -> bool>(&mut self, _guard: TGuard) -> &mut Self {
         self
     }
 
+    /// Enter the target submachine through this entry point, instead of its initial state.
+    pub fn entry_point<TEntryPoint>(&mut self) -> &mut Self
+        where TStateTo: FsmEntryPoint<TEntryPoint>
+    {
+        self
+    }
+
     /// A type for this transition. The struct for the transition will be generated.
     pub fn with_transition_ty<TTransition>(&mut self) -> &mut Self {
         self
@@ -1,7 +1,7 @@
-use crate::{FsmBackendImpl, lib::*};
+use crate::{EventContext, FsmBackendImpl, lib::*};
 
 use crate::FsmBackend;
-use super::{FsmStateBuilder, FsmSubMachineBuilder};
+use super::{FsmChoiceBuilder, FsmEntryPointBuilder, FsmQueueMock, FsmStateBuilder, FsmSubMachineBuilder};
 
 /// The main builder-API for defining your Finny state machine.
 #[derive(Default)]
@@ -34,6 +34,46 @@ impl<TFsm, TContext> FsmBuilder<TFsm, TContext>
 		
 	}
 
+	/// Store only the active state of each region, in a generated enum, instead of keeping all the
+	/// states at the same time. The states are constructed on entry, before the previous state's exit,
+	/// and dropped after the transition's action. The submachines are still stored permanently. The
+	/// inactive states can't be accessed, use `try_get_state` instead of `get_state`.
+	pub fn enum_state_storage(&mut self) {
+
+	}
+
+	/// Derive the serialization of the machine's states and timers, for taking snapshots of the running
+	/// machine. The context, the states and the submachines have to implement `Serialize` and `Deserialize`,
+	/// the submachines have to opt in as well.
+	#[cfg(feature="serde")]
+	pub fn serde_snapshots(&mut self) {
+
+	}
+
+	/// Write the JSON description of this machine while compiling it, into the directory from the `FINNY_META_DIR`
+	/// environment variable or into `OUT_DIR` of the crate's build script. The name of the file is in the
+	/// generated `META_JSON_FILE` constant of the machine.
+	#[cfg(feature="generate_meta_json")]
+	pub fn meta_json(&mut self) {
+
+	}
+
+	/// Ignore this event if none of the regions can handle it, instead of returning an error.
+	pub fn ignore_event<TEvent>(&mut self) {
+
+	}
+
+	/// Ignore all the events that none of the regions can handle.
+	pub fn ignore_all_unhandled(&mut self) {
+
+	}
+
+	/// Execute this handler for the events that none of the regions can handle. The events that
+	/// were explicitly ignored won't be passed to it.
+	pub fn on_unhandled<'a, THandler: Fn(&<TFsm as FsmBackend>::Events, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)>(&mut self, _handler: THandler) {
+
+	}
+
 	/// Adds some information about a state.
 	pub fn state<TState>(&mut self) -> FsmStateBuilder<TFsm, TContext, TState> {
 		FsmStateBuilder {
@@ -43,6 +83,44 @@ impl<TFsm, TContext> FsmBuilder<TFsm, TContext>
 		}
 	}
 
+	/// Adds a final state. Once all the regions are in their final states, the machine is completed
+	/// and the parent machine gets notified with the `SubCompleted` event. Final states can't have
+	/// any outgoing transitions.
+	pub fn final_state<TState>(&mut self) -> FsmStateBuilder<TFsm, TContext, TState> {
+		FsmStateBuilder {
+			_state: PhantomData::default(),
+			_fsm: PhantomData::default(),
+			_context: PhantomData::default()
+		}
+	}
+
+	/// Adds a choice pseudo-state. The type has to implement `Default`, just like a state.
+	pub fn choice<TChoice>(&mut self) -> FsmChoiceBuilder<TFsm, TContext, TChoice> {
+		FsmChoiceBuilder {
+			_fsm: PhantomData::default(),
+			_context: PhantomData::default(),
+			_choice: PhantomData::default()
+		}
+	}
+
+	/// Declares an entry point of this machine. The parent machine can enter it directly into the
+	/// target state of the entry point, the other regions are started in their initial states.
+	/// The type has to implement `Default` and `Clone`, it is delivered to the machine as an event.
+	pub fn entry_point<TEntryPoint>(&mut self) -> FsmEntryPointBuilder<TFsm, TContext, TEntryPoint> {
+		FsmEntryPointBuilder {
+			_fsm: PhantomData::default(),
+			_context: PhantomData::default(),
+			_entry_point: PhantomData::default()
+		}
+	}
+
+	/// Declares an exit point of this machine, a pseudo-state that can be the target of the transitions.
+	/// When reached, the parent machine is notified through its queue and can leave the submachine.
+	/// The type has to implement `Default` and `Clone`.
+	pub fn exit_point<TExitPoint>(&mut self) {
+
+	}
+
 	/// Adds a sub machine
 	pub fn sub_machine<TSubFsm>(&mut self) -> FsmSubMachineBuilder<TFsm, TContext, TSubFsm>
 		where TSubFsm: FsmBackend
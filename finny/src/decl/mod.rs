@@ -5,11 +5,15 @@ mod fsm;
 mod state;
 mod event;
 mod sub;
+mod choice;
+mod entry_point;
 
 pub use self::fsm::*;
 pub use self::state::*;
 pub use self::event::*;
 pub use self::sub::*;
+pub use self::choice::*;
+pub use self::entry_point::*;
 
 #[cfg(feature = "std")]
 pub type FsmQueueMock<F> = crate::FsmEventQueueVec<F>;
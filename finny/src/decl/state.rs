@@ -1,6 +1,6 @@
 use crate::{TimerFsmSettings, lib::*};
 
-use crate::{EventContext, FsmBackend};
+use crate::{EventContext, FsmBackend, FsmCompletionEvent, FsmStateFactory, FsmStateFromEvent};
 use super::{FsmQueueMock, event::FsmEventBuilderState};
 
 pub struct FsmStateBuilder<TFsm, TContext, TState> {
@@ -22,6 +22,31 @@ impl<TFsm, TContext, TState> FsmStateBuilder<TFsm, TContext, TState>
 		self
 	}
 
+	/// Execute this action when entering the state by a transition that was triggered by the event
+	/// `TEvent`. Executed after the `on_entry` action, but not for the async transitions.
+	pub fn on_entry_with<'a, TEvent>(&self, _action: impl Fn(&mut TState, &TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)) -> &Self {
+		self
+	}
+
+	/// Execute this action when exiting the state by a transition that was triggered by the event
+	/// `TEvent`. Executed before the `on_exit` action.
+	pub fn on_exit_with<'a, TEvent>(&self, _action: impl Fn(&mut TState, &TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)) -> &Self {
+		self
+	}
+
+	/// Rebuild the state with its `FsmStateFactory` every time it is entered, so that nothing is kept
+	/// from the earlier visits. The state is rebuilt after the previous state's exit, before the
+	/// transition's action.
+	pub fn construct_on_entry(&self) -> &Self where TState: FsmStateFactory<TFsm> {
+		self
+	}
+
+	/// Build the state from the event `TEvent` when it is entered by a transition that was triggered by
+	/// it. For the other transitions, the state is rebuilt like with `construct_on_entry`.
+	pub fn construct_on_entry_from<TEvent>(&self) -> &Self where TState: FsmStateFactory<TFsm> + FsmStateFromEvent<TFsm, TEvent> {
+		self
+	}
+
 	/// What happens if we receive this event and we are in this state right now?
 	pub fn on_event<TEvent>(&self) -> FsmEventBuilderState<TFsm, TContext, TEvent, TState> {
 		FsmEventBuilderState {
@@ -30,6 +55,37 @@ impl<TFsm, TContext, TState> FsmStateBuilder<TFsm, TContext, TState>
 		}
 	}
 
+	/// Defer this event while in this state, if none of the transitions match it. The event is put
+	/// into the deferred queue and redelivered once the machine has left this state.
+	pub fn defer_event<TEvent>(&self) -> &Self {
+		self
+	}
+
+	/// Ignore this event while in this state, if none of the transitions match it.
+	pub fn ignore_event<TEvent>(&self) -> &Self {
+		self
+	}
+
+	/// Ignore all the events that don't match any of the transitions while in this state.
+	pub fn ignore_all_unhandled(&self) -> &Self {
+		self
+	}
+
+	/// Execute this handler for the events that don't match any of the transitions while in this state. The
+	/// events that were explicitly ignored won't be passed to it.
+	pub fn on_unhandled<'a, THandler: Fn(&<TFsm as FsmBackend>::Events, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)>(&self, _handler: THandler) -> &Self {
+		self
+	}
+
+	/// A completion transition, triggered automatically right after entering this state. Only
+	/// transitions to other states are supported. The guards are checked in the declaration order.
+	pub fn on_completion(&self) -> FsmEventBuilderState<TFsm, TContext, FsmCompletionEvent, TState> {
+		FsmEventBuilderState {
+			_state_builder: self,
+			_event: PhantomData::default()
+		}
+	}
+
 	/// Start a new timer when entering this state. The timer should be unit struct with a implemented
 	/// Default trait. The timer is setup within a closure and the trigger is another closure
 	/// that returns an event to be enqueued in the FSM.
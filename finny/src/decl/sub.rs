@@ -1,4 +1,4 @@
-use crate::{EventContext, FsmBackend, lib::*};
+use crate::{EventContext, FsmBackend, FsmCompletionEvent, FsmExitPoint, History, lib::*};
 
 use super::{FsmEventBuilderState, FsmQueueMock, FsmStateBuilder};
 
@@ -19,6 +19,22 @@ impl<TFsm, TContext, TSubMachine> FsmSubMachineBuilder<TFsm, TContext, TSubMachi
 		self
 	}
 
+	/// Resume the submachine from its last active states when it is re-entered, instead of
+	/// restarting it from the initial state. The entry actions of the restored states are executed.
+	pub fn with_history(&self, _history: History) -> &Self {
+		self
+	}
+
+	/// A transition that is triggered once the submachine reaches this exit point.
+	pub fn on_exit_point<TExitPoint>(&self) -> FsmEventBuilderState<TFsm, TContext, TExitPoint, TSubMachine>
+		where TSubMachine: FsmExitPoint<TExitPoint>
+	{
+		FsmEventBuilderState {
+			_state_builder: &self._state_builder,
+			_event: PhantomData::default()
+		}
+	}
+
 	/// Execute this action when entering the sub-machine state.
 	pub fn on_entry<'a, TAction: Fn(&mut TSubMachine, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)>(&self, _action: TAction) -> &Self {
 		self
@@ -29,6 +45,38 @@ impl<TFsm, TContext, TSubMachine> FsmSubMachineBuilder<TFsm, TContext, TSubMachi
 		self
 	}	
 
+	/// Execute this action when entering the sub-machine state by a transition that was triggered by the event
+	/// `TEvent`. Executed after the `on_entry` action, but not for the async transitions.
+	pub fn on_entry_with<'a, TEvent>(&self, _action: impl Fn(&mut TSubMachine, &TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)) -> &Self {
+		self
+	}
+
+	/// Execute this action when exiting the sub-machine state by a transition that was triggered by the event
+	/// `TEvent`. Executed before the `on_exit` action.
+	pub fn on_exit_with<'a, TEvent>(&self, _action: impl Fn(&mut TSubMachine, &TEvent, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)) -> &Self {
+		self
+	}
+
+	/// Defer this event while in this submachine's state, if none of the transitions match it.
+	pub fn defer_event<TEvent>(&self) -> &Self {
+		self
+	}
+
+	/// Ignore this event while in this submachine's state, if none of the transitions match it.
+	pub fn ignore_event<TEvent>(&self) -> &Self {
+		self
+	}
+
+	/// Ignore all the events that don't match any of the transitions while in this submachine's state.
+	pub fn ignore_all_unhandled(&self) -> &Self {
+		self
+	}
+
+	/// Execute this handler for the events that don't match any of the transitions while in this submachine's state.
+	pub fn on_unhandled<'a, THandler: Fn(&<TFsm as FsmBackend>::Events, &mut EventContext<'a, TFsm, FsmQueueMock<TFsm>>)>(&self, _handler: THandler) -> &Self {
+		self
+	}
+
 	/// What happens if we receive this event and we are in this submachine's state right now?
 	pub fn on_event<TEvent>(&self) -> FsmEventBuilderState<TFsm, TContext, TEvent, TSubMachine> {
 		FsmEventBuilderState {
@@ -36,4 +84,12 @@ impl<TFsm, TContext, TSubMachine> FsmSubMachineBuilder<TFsm, TContext, TSubMachi
 			_event: PhantomData::default()
 		}
 	}
+
+	/// A completion transition, triggered automatically right after entering this submachine's state.
+	pub fn on_completion(&self) -> FsmEventBuilderState<TFsm, TContext, FsmCompletionEvent, TSubMachine> {
+		FsmEventBuilderState {
+			_state_builder: &self._state_builder,
+			_event: PhantomData::default()
+		}
+	}
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use crate::{FsmTimers, FsmTimersSub, lib::*};
-use crate::{EventContext, FsmBackend, FsmBackendImpl, FsmEvent, FsmEventQueue, FsmEventQueueSub, FsmRegionId, FsmResult, Inspect};
+use crate::{EventContext, FsmBackend, FsmBackendImpl, FsmCurrentState, FsmEvent, FsmEventQueue, FsmEventQueueSub, FsmRegionId, FsmResult, History, Inspect};
 
 pub struct DispatchContext<'a, 'b, 'c, F, Q, I, T>
     where F: FsmBackend,
@@ -30,9 +30,9 @@ where F: FsmBackend,
     } 
 }
 
-/// Used to funnel the event down to the sub-machine.
+/// Used to funnel the event down to the sub-machine, which is held by the `region` of the parent.
 pub fn dispatch_to_submachine<'a, 'b, 'c, TFsm, TSubMachine, Q, I, T>(ctx: &mut DispatchContext<'a, 'b, 'c, TFsm, Q, I, T>,
-        ev: FsmEvent<<TSubMachine as FsmBackend>::Events, <TSubMachine as FsmBackend>::Timers>, inspect_event_ctx: &mut I)
+        ev: FsmEvent<<TSubMachine as FsmBackend>::Events, <TSubMachine as FsmBackend>::Timers>, region: FsmRegionId, inspect_event_ctx: &mut I)
     -> FsmResult<()>
     where
         TFsm: FsmBackend,
@@ -48,6 +48,7 @@ pub fn dispatch_to_submachine<'a, 'b, 'c, TFsm, TSubMachine, Q, I, T>(ctx: &mut
 
     let mut queue_adapter = FsmEventQueueSub {
         parent: ctx.queue,
+        region,
         _parent_fsm: core::marker::PhantomData::<TFsm>::default(),
         _sub_fsm: core::marker::PhantomData::<TSubMachine>::default()
     };
@@ -68,4 +69,34 @@ pub fn dispatch_to_submachine<'a, 'b, 'c, TFsm, TSubMachine, Q, I, T>(ctx: &mut
     };
     
     <TSubMachine>::dispatch_event(sub_dispatch_ctx, ev)
-}
\ No newline at end of file
+}
+/// Enters the sub-machine's state. Its last active states are resumed if a history is given
+/// and the sub-machine was already started, otherwise it is started from its initial state.
+pub fn enter_submachine<'a, 'b, 'c, TFsm, TSubMachine, Q, I, T>(ctx: &mut DispatchContext<'a, 'b, 'c, TFsm, Q, I, T>,
+        history: Option<History>, region: FsmRegionId, inspect_event_ctx: &mut I)
+    -> FsmResult<()>
+    where
+        TFsm: FsmBackend,
+        <TFsm as FsmBackend>::States: AsMut<TSubMachine>,
+        <TFsm as FsmBackend>::Events: From<<TSubMachine as FsmBackend>::Events>,
+        <TFsm as FsmBackend>::Timers: From<<TSubMachine as FsmBackend>::Timers>,
+        TSubMachine: FsmBackend + DerefMut<Target = FsmBackendImpl<TSubMachine>>,
+        Q: FsmEventQueue<TFsm>,
+        I: Inspect,
+        T: FsmTimers<TFsm>,
+{
+    let sub_fsm: &mut TSubMachine = ctx.backend.states.as_mut();
+    let stopped = FsmCurrentState::all_stopped(sub_fsm.get_current_states().as_ref());
+
+    match history {
+        Some(history) if !stopped => {
+            inspect_event_ctx.info("Resuming the submachine from its history.");
+            dispatch_to_submachine::<_, TSubMachine, _, _, _>(ctx, FsmEvent::Resume(history), region, inspect_event_ctx)
+        },
+        _ => {
+            sub_fsm.current_states = Default::default();
+            inspect_event_ctx.info("Setting the state of the submachine to Start.");
+            dispatch_to_submachine::<_, TSubMachine, _, _, _>(ctx, FsmEvent::Start, region, inspect_event_ctx)
+        }
+    }
+}
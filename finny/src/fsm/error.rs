@@ -0,0 +1,192 @@
+use crate::{AllVariants, FsmBackend, FsmBackendImpl, FsmCurrentState, FsmEvent, FsmRegionId, FsmStates, FsmStaticName, lib::*};
+
+#[cfg(feature="std")]
+use crate::FsmQueueErrorReport;
+
+/// The maximum number of regions whose states are recorded in the error context.
+pub const FSM_ERROR_MAX_REGIONS: usize = 8;
+
+/// The lib-level error type.
+#[derive(Debug, PartialEq)]
+pub enum FsmError {
+    /// None of the regions had a transition for the event.
+    NoTransition(FsmErrorContext),
+    QueueOverCapacity,
+    NotSupported,
+    TimerNotStarted,
+    /// The snapshot was stored with an unsupported schema version.
+    #[cfg(feature="serde")]
+    SnapshotVersion(u32),
+    /// The thread that runs the machine has stopped.
+    #[cfg(feature="std")]
+    Disconnected,
+    /// Errors of the queued events, returned with the `StopOnFirstError` and `CollectErrors` queue policies.
+    #[cfg(feature="std")]
+    QueueErrors(FsmQueueErrorReport)
+}
+
+/// The state of a single region at the time of the error.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FsmErrorRegionState {
+    pub region: FsmRegionId,
+    pub state: FsmCurrentState<&'static str>
+}
+
+/// Describes the machine, the event and the states of all the regions when the error
+/// happened. Doesn't require allocations. The states are kept as indices into the machine's
+/// states, `states` returns them by their names and `typed_states` as the typed state kinds.
+#[derive(Clone)]
+pub struct FsmErrorContext {
+    /// The type name of the machine.
+    pub fsm: &'static str,
+    /// The name of the event.
+    pub event: &'static str,
+    /// The states of the first `region_count` regions, up to `FSM_ERROR_MAX_REGIONS`.
+    region_states: [FsmCurrentState<u16>; FSM_ERROR_MAX_REGIONS],
+    region_count: u8,
+    state_name: fn(u16) -> &'static str
+}
+
+impl FsmErrorContext {
+    pub fn new<F: FsmBackend>(event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>, backend: &FsmBackendImpl<F>) -> Self
+        where <<F as FsmBackend>::States as FsmStates<F>>::StateKind: AllVariants
+    {
+        let event = match event {
+            FsmEvent::Start => "Fsm::Start",
+            FsmEvent::Stop => "Fsm::Stop",
+            FsmEvent::Resume(_) => "Fsm::Resume",
+            FsmEvent::TransitionCompleted(_) => "Fsm::TransitionCompleted",
+            FsmEvent::Timer(_) => "Fsm::Timer",
+            FsmEvent::Event(ev) => ev.static_name()
+        };
+
+        let index = |s: &<<F as FsmBackend>::States as FsmStates<F>>::StateKind| {
+            <<<F as FsmBackend>::States as FsmStates<F>>::StateKind as AllVariants>::iter().position(|k| k == *s).unwrap_or_default() as u16
+        };
+
+        let mut region_states = [FsmCurrentState::Stopped; FSM_ERROR_MAX_REGIONS];
+        let current_states: &[FsmCurrentState<<<F as FsmBackend>::States as FsmStates<F>>::StateKind>] = backend.current_states.as_ref();
+        let region_count = current_states.len().min(FSM_ERROR_MAX_REGIONS);
+        for (region_state, state) in region_states.iter_mut().zip(current_states) {
+            *region_state = match state {
+                FsmCurrentState::Stopped => FsmCurrentState::Stopped,
+                FsmCurrentState::State(s) => FsmCurrentState::State(index(s)),
+                FsmCurrentState::InTransition(s) => FsmCurrentState::InTransition(index(s))
+            };
+        }
+
+        FsmErrorContext {
+            fsm: type_name::<F>(),
+            event,
+            region_states,
+            region_count: region_count as u8,
+            state_name: Self::state_name::<F>
+        }
+    }
+
+    fn state_name<F>(index: u16) -> &'static str
+        where F: FsmBackend, <<F as FsmBackend>::States as FsmStates<F>>::StateKind: AllVariants
+    {
+        Self::find_state_kind::<F>(index).map_or("", |k| k.static_name())
+    }
+
+    fn find_state_kind<F>(index: u16) -> Option<<<F as FsmBackend>::States as FsmStates<F>>::StateKind>
+        where F: FsmBackend, <<F as FsmBackend>::States as FsmStates<F>>::StateKind: AllVariants
+    {
+        <<<F as FsmBackend>::States as FsmStates<F>>::StateKind as AllVariants>::iter().nth(index as usize)
+    }
+
+    /// The states of the regions, by their names.
+    pub fn states(&self) -> impl Iterator<Item = FsmErrorRegionState> + '_ {
+        self.region_states[..self.region_count as usize].iter().enumerate().map(move |(region, state)| {
+            let state = match *state {
+                FsmCurrentState::Stopped => FsmCurrentState::Stopped,
+                FsmCurrentState::State(s) => FsmCurrentState::State((self.state_name)(s)),
+                FsmCurrentState::InTransition(s) => FsmCurrentState::InTransition((self.state_name)(s))
+            };
+            FsmErrorRegionState { region, state }
+        })
+    }
+
+    /// The typed states of the regions, in the order of the regions. `None` if the error wasn't
+    /// raised by the machine `F`.
+    pub fn typed_states<F>(&self) -> Option<heapless::Vec<FsmCurrentState<<<F as FsmBackend>::States as FsmStates<F>>::StateKind>, FSM_ERROR_MAX_REGIONS>>
+        where F: FsmBackend, <<F as FsmBackend>::States as FsmStates<F>>::StateKind: AllVariants
+    {
+        if self.fsm != type_name::<F>() {
+            return None;
+        }
+
+        let mut states = heapless::Vec::new();
+        for s in &self.region_states[..self.region_count as usize] {
+            let state = match *s {
+                FsmCurrentState::Stopped => FsmCurrentState::Stopped,
+                FsmCurrentState::State(index) => FsmCurrentState::State(Self::find_state_kind::<F>(index)?),
+                FsmCurrentState::InTransition(index) => FsmCurrentState::InTransition(Self::find_state_kind::<F>(index)?)
+            };
+            states.push(state).ok()?;
+        }
+
+        Some(states)
+    }
+}
+
+// the names of the states follow from the machine, they aren't compared
+impl PartialEq for FsmErrorContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.fsm == other.fsm && self.event == other.event && self.region_states[..self.region_count as usize] == other.region_states[..other.region_count as usize]
+    }
+}
+
+impl Debug for FsmErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FsmErrorContext")
+            .field("fsm", &self.fsm)
+            .field("event", &self.event)
+            .field("states", &self.states().map(|s| s.state).collect::<heapless::Vec<_, FSM_ERROR_MAX_REGIONS>>())
+            .finish()
+    }
+}
+
+impl fmt::Display for FsmErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event '{}' in '{}', states: [", self.event, self.fsm)?;
+        for (i, s) in self.states().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match s.state {
+                FsmCurrentState::Stopped => write!(f, "region {}: Fsm::Stopped", s.region)?,
+                FsmCurrentState::State(state) => write!(f, "region {}: {}", s.region, state)?,
+                FsmCurrentState::InTransition(state) => write!(f, "region {}: Fsm::InTransition({})", s.region, state)?
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+impl fmt::Display for FsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsmError::NoTransition(ctx) => write!(f, "No transition for the {}", ctx),
+            FsmError::QueueOverCapacity => f.write_str("The event queue is over capacity"),
+            FsmError::NotSupported => f.write_str("Not supported"),
+            FsmError::TimerNotStarted => f.write_str("The timer wasn't started"),
+            #[cfg(feature="serde")]
+            FsmError::SnapshotVersion(v) => write!(f, "Unsupported snapshot schema version {}", v),
+            #[cfg(feature="std")]
+            FsmError::Disconnected => f.write_str("The machine's thread has stopped"),
+            #[cfg(feature="std")]
+            FsmError::QueueErrors(report) => {
+                write!(f, "{} errors while dispatching the queue", report.errors.len())?;
+                for e in &report.errors {
+                    write!(f, "; {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature="std")]
+impl std::error::Error for FsmError { }
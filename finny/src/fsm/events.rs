@@ -1,14 +1,94 @@
-use crate::{FsmBackend, FsmEventQueue, FsmEventQueueSender, lib::*};
+use crate::{FSM_STATE_PATH_MAX_STATES, FsmBackend, FsmError, FsmEventQueue, FsmEventQueueSender, FsmResult, lib::*};
 
 /// The internal event type that also allows stopping or starting the machine.
 #[derive(Clone)]
 pub enum FsmEvent<E, T> {
     Start,
     Stop,
+    /// Re-enters the last active states of a submachine that was declared with a history.
+    Resume(History),
+    /// The async action of the transition has completed, the target state is entered.
+    TransitionCompleted(FsmTransitionCompleted),
     Timer(T),
     Event(E)
 }
 
+/// Identifies the async transition whose action has completed, and the machine that started it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FsmTransitionCompleted {
+    /// The regions from the machine that started the transition up to the outermost machine, the
+    /// region of the receiving machine is the last one.
+    pub regions: heapless::Vec<FsmRegionId, FSM_STATE_PATH_MAX_STATES>,
+    /// The index of the transition within its region.
+    pub transition: usize
+}
+
+impl FsmTransitionCompleted {
+    pub fn new(region: FsmRegionId, transition: usize) -> Self {
+        let mut regions = heapless::Vec::new();
+        let _ = regions.push(region);
+        Self { regions, transition }
+    }
+
+    /// The region of the receiving machine.
+    pub fn region(&self) -> FsmRegionId {
+        self.regions.last().copied().unwrap_or_default()
+    }
+
+    /// The transition was started by a submachine in the region of its parent.
+    pub fn with_parent_region(mut self, region: FsmRegionId) -> FsmResult<Self> {
+        self.regions.push(region).map_err(|_| FsmError::NotSupported)?;
+        Ok(self)
+    }
+
+    /// The completion as it is dispatched to the submachine in the receiving region.
+    pub fn to_submachine(&self) -> Self {
+        let mut completed = self.clone();
+        completed.regions.pop();
+        completed
+    }
+}
+
+/// The history of a submachine. Decides which states are restored when re-entering it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum History {
+    /// Only the states of the submachine itself are restored, the nested submachines are started again.
+    Shallow,
+    /// The states of the submachine and all of its nested submachines are restored.
+    Deep
+}
+
+/// The marker event of the completion transitions, which are triggered automatically after
+/// entering the state.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FsmCompletionEvent;
+
+/// The event of the transitions that are triggered once all the regions of the submachine `F`
+/// have reached their final states.
+pub struct SubCompleted<F> {
+    _fsm: PhantomData<F>
+}
+
+impl<F> Default for SubCompleted<F> {
+    fn default() -> Self {
+        SubCompleted { _fsm: PhantomData::default() }
+    }
+}
+
+impl<F> Clone for SubCompleted<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F> Copy for SubCompleted<F> { }
+
+impl<F> Debug for SubCompleted<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SubCompleted")
+    }
+}
+
 impl<E, T> From<E> for FsmEvent<E, T> {
     fn from(event: E) -> Self {
         FsmEvent::Event(event)
@@ -20,6 +100,8 @@ impl<E, T> Debug for FsmEvent<E, T> where E: Debug, T: Debug {
         match self {
             FsmEvent::Start => f.write_str("Fsm::Start"),
             FsmEvent::Stop => f.write_str("Fsm::Stop"),
+            FsmEvent::Resume(h) => f.write_fmt(format_args!("Fsm::Resume({:?})", h)),
+            FsmEvent::TransitionCompleted(c) => f.write_fmt(format_args!("Fsm::TransitionCompleted({})", c.region())),
             FsmEvent::Timer(t) => f.write_fmt(format_args!("Fsm::Timer({:?})", t)),
             FsmEvent::Event(ev) => ev.fmt(f)
         }
@@ -31,6 +113,8 @@ impl<E, T> AsRef<str> for FsmEvent<E, T> where E: AsRef<str> {
         match self {
             FsmEvent::Start => "Fsm::Start",
             FsmEvent::Stop => "Fsm::Stop",
+            FsmEvent::Resume(_) => "Fsm::Resume",
+            FsmEvent::TransitionCompleted(_) => "Fsm::TransitionCompleted",
             FsmEvent::Timer(_) => "Fsm::Timer",
             FsmEvent::Event(e) => e.as_ref()
         }
@@ -46,6 +130,8 @@ impl<E, T> FsmEvent<E, T> {
         match self {
             FsmEvent::Start => FsmEvent::Start,
             FsmEvent::Stop => FsmEvent::Stop,
+            FsmEvent::Resume(h) => FsmEvent::Resume(h),
+            FsmEvent::TransitionCompleted(c) => FsmEvent::TransitionCompleted(c),
             FsmEvent::Timer(t) => {
                 FsmEvent::Timer(t.into())
             }
@@ -0,0 +1,282 @@
+//! An async frontend that waits for the events and the timers, without depending on a specific runtime.
+
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::{Context, Poll};
+
+use crate::{FsmBackend, FsmCurrentStatePath, FsmEvent, FsmEventQueue, FsmEventQueueNoDeferral, FsmFrontend, FsmResult, FsmTimers, Inspect, lib::*};
+
+/// The async version of the event queue, used by the `AsyncFsmFrontend` to wait for the next event.
+pub trait FsmEventQueueAsync<F: FsmBackend>: FsmEventQueue<F> {
+    /// Poll for the next event. Has to register the waker if there are no events available. Returns
+    /// `Poll::Ready(None)` once the queue is closed and no more events will arrive.
+    fn poll_dequeue(&mut self, cx: &mut Context<'_>) -> Poll<Option<<F as FsmBackend>::Events>>;
+}
+
+/// Sleeping with the timer of the async runtime, for example `tokio::time::sleep`.
+pub trait FsmAsyncSleep {
+    type Sleep: Future<Output = ()>;
+
+    fn sleep(&mut self, duration: Duration) -> Self::Sleep;
+}
+
+enum NextEvent<E> {
+    Event(E),
+    Timer,
+    Closed
+}
+
+/// A frontend that awaits the events from the async queue and sleeps until the next timer is due. Each event
+/// is run to completition, just like with the `FsmFrontend`, which is still available through `Deref`.
+pub struct AsyncFsmFrontend<F, Q, I, T, S, D = FsmEventQueueNoDeferral<F>>
+    where F: FsmBackend, Q: FsmEventQueueAsync<F>, I: Inspect, T: FsmTimers<F>, S: FsmAsyncSleep, D: FsmEventQueue<F>
+{
+    pub frontend: FsmFrontend<F, Q, I, T, D>,
+    pub sleep: S
+}
+
+impl<F, Q, I, T, S, D> AsyncFsmFrontend<F, Q, I, T, S, D>
+    where F: FsmBackend + FsmCurrentStatePath, Q: FsmEventQueueAsync<F>, I: Inspect, T: FsmTimers<F>, S: FsmAsyncSleep, D: FsmEventQueue<F>
+{
+    pub fn new(frontend: FsmFrontend<F, Q, I, T, D>, sleep: S) -> Self {
+        Self {
+            frontend,
+            sleep
+        }
+    }
+
+    /// Wait for the next event or the next timer and dispatch it, including the events it enqueued. Returns
+    /// `false` once the queue is closed.
+    pub async fn dispatch_next(&mut self) -> FsmResult<bool> {
+        // the machine is in a transition, the events wait in the queue until its actions complete
+        #[cfg(feature = "std")]
+        {
+            if self.frontend.is_in_transition() {
+                let actions = &mut self.frontend.pending_actions;
+                let idx = poll_fn(|cx| {
+                    for (idx, (_, action)) in actions.iter_mut().enumerate() {
+                        if action.poll(cx).is_ready() {
+                            return Poll::Ready(idx);
+                        }
+                    }
+                    Poll::Pending
+                }).await;
+
+                let (completed, _) = self.frontend.pending_actions.remove(idx);
+                self.frontend.dispatch_single_event(FsmEvent::TransitionCompleted(completed))?;
+                self.frontend.dispatch_queue()?;
+                return Ok(true);
+            }
+        }
+
+        // the timers that are already due
+        if let Some(timer_id) = self.frontend.timers.get_triggered_timer() {
+            self.frontend.dispatch_single_event(FsmEvent::Timer(timer_id))?;
+            self.frontend.dispatch_timer_events()?;
+            return Ok(true);
+        }
+
+        let sleep = self.frontend.timers.get_next_deadline().map(|d| self.sleep.sleep(d));
+        let mut sleep = pin!(sleep);
+        let queue = &mut self.frontend.queue;
+
+        let next = poll_fn(|cx| {
+            if let Poll::Ready(ev) = queue.poll_dequeue(cx) {
+                return Poll::Ready(match ev {
+                    Some(ev) => NextEvent::Event(ev),
+                    None => NextEvent::Closed
+                });
+            }
+
+            if let Some(sleep) = sleep.as_mut().as_pin_mut() {
+                if sleep.poll(cx).is_ready() {
+                    return Poll::Ready(NextEvent::Timer);
+                }
+            }
+
+            Poll::Pending
+        }).await;
+
+        match next {
+            NextEvent::Event(ev) => {
+                self.frontend.dispatch(ev)?;
+                Ok(true)
+            },
+            NextEvent::Timer => {
+                self.frontend.dispatch_timer_events()?;
+                Ok(true)
+            },
+            NextEvent::Closed => Ok(false)
+        }
+    }
+
+    /// Keep dispatching the events and the timers until the queue is closed. Returns the first error.
+    pub async fn run(&mut self) -> FsmResult<()> {
+        while self.dispatch_next().await? { }
+
+        Ok(())
+    }
+}
+
+impl<F, Q, I, T, S, D> Deref for AsyncFsmFrontend<F, Q, I, T, S, D>
+    where F: FsmBackend, Q: FsmEventQueueAsync<F>, I: Inspect, T: FsmTimers<F>, S: FsmAsyncSleep, D: FsmEventQueue<F>
+{
+    type Target = FsmFrontend<F, Q, I, T, D>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.frontend
+    }
+}
+
+impl<F, Q, I, T, S, D> DerefMut for AsyncFsmFrontend<F, Q, I, T, S, D>
+    where F: FsmBackend, Q: FsmEventQueueAsync<F>, I: Inspect, T: FsmTimers<F>, S: FsmAsyncSleep, D: FsmEventQueue<F>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.frontend
+    }
+}
+
+#[cfg(feature = "std")]
+mod action_async {
+    use std::boxed::Box;
+    use core::pin::Pin;
+
+    use super::*;
+
+    /// The future of an async transition action. The frontend drives it, the region stays in the
+    /// transition until it resolves.
+    pub struct FsmAsyncAction {
+        future: Pin<Box<dyn Future<Output = ()> + Send>>
+    }
+
+    impl FsmAsyncAction {
+        pub fn new<A: Future<Output = ()> + Send + 'static>(action: A) -> Self {
+            Self {
+                future: Box::pin(action)
+            }
+        }
+
+        pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+            self.future.as_mut().poll(cx)
+        }
+    }
+
+    impl Debug for FsmAsyncAction {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("FsmAsyncAction")
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::action_async::*;
+
+#[cfg(feature = "std")]
+mod queue_async_shared {
+    use std::sync::{Arc, Mutex};
+    use core::task::Waker;
+
+    use crate::{FsmError, FsmEventQueueSender};
+
+    use super::*;
+
+    /// An unbound async event queue that uses `VecDeque`. Can be cloned and used from other threads and tasks,
+    /// the waiting frontend is woken up by the new events.
+    pub struct FsmEventQueueAsyncShared<F: FsmBackend> {
+        inner: Arc<Mutex<Inner<F>>>
+    }
+
+    impl<F> Clone for FsmEventQueueAsyncShared<F> where F: FsmBackend {
+        fn clone(&self) -> Self {
+            Self { inner: self.inner.clone() }
+        }
+    }
+
+    struct Inner<F: FsmBackend> {
+        queue: VecDeque<<F as FsmBackend>::Events>,
+        waker: Option<Waker>,
+        closed: bool
+    }
+
+    impl<F: FsmBackend> FsmEventQueueAsyncShared<F> {
+        pub fn new() -> Self {
+            let inner = Inner {
+                queue: VecDeque::new(),
+                waker: None,
+                closed: false
+            };
+            FsmEventQueueAsyncShared {
+                inner: Arc::new(Mutex::new(inner))
+            }
+        }
+
+        /// Close the queue. The frontend stops running once the remaining events are dispatched.
+        pub fn close(&self) {
+            if let Ok(mut q) = self.inner.lock() {
+                q.closed = true;
+                if let Some(waker) = q.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    impl<F: FsmBackend> Default for FsmEventQueueAsyncShared<F> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueueAsyncShared<F> {
+        fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+            if let Ok(mut q) = self.inner.lock() {
+                q.queue.pop_front()
+            } else {
+                None
+            }
+        }
+
+        fn len(&self) -> usize {
+            if let Ok(q) = self.inner.lock() {
+                q.queue.len()
+            } else {
+                0
+            }
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueAsyncShared<F> {
+        fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+            if let Ok(mut q) = self.inner.lock() {
+                q.queue.push_back(event.into());
+                if let Some(waker) = q.waker.take() {
+                    waker.wake();
+                }
+                Ok(())
+            } else {
+                Err(FsmError::QueueOverCapacity)
+            }
+        }
+    }
+
+    impl<F: FsmBackend> FsmEventQueueAsync<F> for FsmEventQueueAsyncShared<F> {
+        fn poll_dequeue(&mut self, cx: &mut Context<'_>) -> Poll<Option<<F as FsmBackend>::Events>> {
+            match self.inner.lock() {
+                Ok(mut q) => {
+                    if let Some(ev) = q.queue.pop_front() {
+                        Poll::Ready(Some(ev))
+                    } else if q.closed {
+                        Poll::Ready(None)
+                    } else {
+                        q.waker = Some(cx.waker().clone());
+                        Poll::Pending
+                    }
+                },
+                Err(_) => Poll::Ready(None)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::queue_async_shared::*;
@@ -1,41 +1,117 @@
-use crate::{FsmBackend, FsmBackendImpl, FsmEventQueue, FsmFrontend, FsmResult, FsmTimers, FsmTimersNull, Inspect};
+use crate::{FsmBackend, FsmBackendImpl, FsmCurrentStatePath, FsmEventQueue, FsmEventQueueNoDeferral, FsmFrontend, FsmNoAsyncActions, FsmNoDeferrals, FsmQueueErrorPolicy, FsmResult, FsmTimers, FsmTimersNull, Inspect};
 
 #[cfg(feature="std")]
 use crate::{FsmEventQueueVec, timers::std::TimersStd};
 
+#[cfg(feature="serde")]
+use crate::{FSM_SNAPSHOT_VERSION, FsmError, FsmSnapshot, FsmStates};
+
+#[cfg(feature="async")]
+use crate::{AsyncFsmFrontend, FsmAsyncSleep, FsmEventQueueAsync};
+
 /// Builds a frontend for running your FSM.
 pub trait FsmFactory {
-    type Fsm: FsmBackend;
+    type Fsm: FsmBackend + FsmCurrentStatePath;
 
     /// For submachines, for use with codegen.
     fn new_submachine_backend(backend: FsmBackendImpl<Self::Fsm>) -> FsmResult<Self> where Self: Sized;
 
-    /// Build a new frontend for the FSM with all the environmental services provided by the caller.
+    /// Build a new frontend for the FSM with all the environmental services provided by the caller. Only for the
+    /// machines that don't defer any events, use `new_with_deferred` for the others. The machines with async actions
+    /// are built with `new_async_with`.
     fn new_with<Q, I, T>(context: <Self::Fsm as FsmBackend>::Context, queue: Q, inspect: I, timers: T) -> FsmResult<FsmFrontend<Self::Fsm, Q, I, T>>
-        where Q: FsmEventQueue<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>
+        where Q: FsmEventQueue<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>, Self::Fsm: FsmNoDeferrals + FsmNoAsyncActions
     {
-        let frontend = FsmFrontend {
-            queue,
-            inspect,
-            backend: FsmBackendImpl::new(context)?,
-            timers
-        };
-        
-        Ok(frontend)
+        Self::new_with_deferred(context, queue, FsmEventQueueNoDeferral::new(), inspect, timers)
     }
 
-    /// Build a new frontend for the FSM with a `FsmEventQueueVec` queue, `TimersStd` for timers and no logging.
+    /// Build a new frontend for the FSM with all the environmental services provided by the caller, including
+    /// the queue for the deferred events.
+    fn new_with_deferred<Q, D, I, T>(context: <Self::Fsm as FsmBackend>::Context, queue: Q, deferred: D, inspect: I, timers: T) -> FsmResult<FsmFrontend<Self::Fsm, Q, I, T, D>>
+        where Q: FsmEventQueue<Self::Fsm>, D: FsmEventQueue<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>, Self::Fsm: FsmNoAsyncActions
+    {
+        Ok(new_frontend(FsmBackendImpl::new(context)?, queue, deferred, inspect, timers))
+    }
+
+    /// Build a new frontend for the FSM with a `FsmEventQueueVec` queue for both the events and the deferred events,
+    /// `TimersStd` for timers and no logging.
     #[cfg(feature="std")]
-    fn new(context: <Self::Fsm as FsmBackend>::Context) -> FsmResult<FsmFrontend<Self::Fsm, FsmEventQueueVec<Self::Fsm>, crate::inspect::null::InspectNull, TimersStd<Self::Fsm>>> {
+    fn new(context: <Self::Fsm as FsmBackend>::Context) -> FsmResult<FsmFrontend<Self::Fsm, FsmEventQueueVec<Self::Fsm>, crate::inspect::null::InspectNull, TimersStd<Self::Fsm>, FsmEventQueueVec<Self::Fsm>>>
+        where Self::Fsm: FsmNoAsyncActions
+    {
+        use crate::inspect::null::InspectNull;
+
+        Self::new_with_deferred(context, FsmEventQueueVec::new(), FsmEventQueueVec::new(), InspectNull::new(), TimersStd::new())
+    }
+
+    /// Build a new async frontend for the FSM with all the environmental services provided by the caller. The only
+    /// frontend for the machines with async actions. Only for the machines that don't defer any events, use
+    /// `new_async_with_deferred` for the others.
+    #[cfg(feature="async")]
+    fn new_async_with<Q, I, T, S>(context: <Self::Fsm as FsmBackend>::Context, queue: Q, inspect: I, timers: T, sleep: S) -> FsmResult<AsyncFsmFrontend<Self::Fsm, Q, I, T, S>>
+        where Q: FsmEventQueueAsync<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>, S: FsmAsyncSleep, Self::Fsm: FsmNoDeferrals
+    {
+        Self::new_async_with_deferred(context, queue, FsmEventQueueNoDeferral::new(), inspect, timers, sleep)
+    }
+
+    /// Build a new async frontend for the FSM with all the environmental services provided by the caller, including
+    /// the queue for the deferred events.
+    #[cfg(feature="async")]
+    fn new_async_with_deferred<Q, D, I, T, S>(context: <Self::Fsm as FsmBackend>::Context, queue: Q, deferred: D, inspect: I, timers: T, sleep: S) -> FsmResult<AsyncFsmFrontend<Self::Fsm, Q, I, T, S, D>>
+        where Q: FsmEventQueueAsync<Self::Fsm>, D: FsmEventQueue<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>, S: FsmAsyncSleep
+    {
+        let frontend = new_frontend(FsmBackendImpl::new(context)?, queue, deferred, inspect, timers);
+        Ok(AsyncFsmFrontend::new(frontend, sleep))
+    }
+
+    /// Restore the machine from a snapshot, with all the environmental services provided by the caller. The
+    /// machine isn't started again, the timers that were running are created anew with their full timeouts. Only
+    /// for the machines that don't defer any events, use `restore_with_deferred` for the others.
+    #[cfg(feature="serde")]
+    fn restore_with<Q, I, T>(snapshot: FsmSnapshot<FsmBackendImpl<Self::Fsm>>, queue: Q, inspect: I, timers: T) -> FsmResult<FsmFrontend<Self::Fsm, Q, I, T>>
+        where Q: FsmEventQueue<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>, Self::Fsm: FsmNoDeferrals + FsmNoAsyncActions
+    {
+        Self::restore_with_deferred(snapshot, queue, FsmEventQueueNoDeferral::new(), inspect, timers)
+    }
+
+    /// Restore the machine from a snapshot, with all the environmental services provided by the caller, including
+    /// the queue for the deferred events.
+    #[cfg(feature="serde")]
+    fn restore_with_deferred<Q, D, I, T>(snapshot: FsmSnapshot<FsmBackendImpl<Self::Fsm>>, queue: Q, deferred: D, inspect: I, mut timers: T) -> FsmResult<FsmFrontend<Self::Fsm, Q, I, T, D>>
+        where Q: FsmEventQueue<Self::Fsm>, D: FsmEventQueue<Self::Fsm>, I: Inspect, T: FsmTimers<Self::Fsm>, Self::Fsm: FsmNoAsyncActions
+    {
+        if snapshot.version != FSM_SNAPSHOT_VERSION {
+            return Err(FsmError::SnapshotVersion(snapshot.version));
+        }
+
+        let backend = snapshot.backend;
+        backend.states.restore_timers(&mut timers)?;
+
+        Ok(new_frontend(backend, queue, deferred, inspect, timers))
+    }
+
+    /// Restore the machine from a snapshot, with the same environmental services as `new`.
+    #[cfg(all(feature="std", feature="serde"))]
+    fn restore(snapshot: FsmSnapshot<FsmBackendImpl<Self::Fsm>>) -> FsmResult<FsmFrontend<Self::Fsm, FsmEventQueueVec<Self::Fsm>, crate::inspect::null::InspectNull, TimersStd<Self::Fsm>, FsmEventQueueVec<Self::Fsm>>>
+        where Self::Fsm: FsmNoAsyncActions
+    {
         use crate::inspect::null::InspectNull;
 
-        let frontend = FsmFrontend {
-            queue: FsmEventQueueVec::new(),
-            backend: FsmBackendImpl::new(context)?,
-            inspect: InspectNull::new(),
-            timers: TimersStd::new(),
-        };
+        Self::restore_with_deferred(snapshot, FsmEventQueueVec::new(), FsmEventQueueVec::new(), InspectNull::new(), TimersStd::new())
+    }
+}
 
-        Ok(frontend)
+fn new_frontend<F, Q, D, I, T>(backend: FsmBackendImpl<F>, queue: Q, deferred: D, inspect: I, timers: T) -> FsmFrontend<F, Q, I, T, D>
+    where F: FsmBackend, Q: FsmEventQueue<F>, D: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>
+{
+    FsmFrontend {
+        queue,
+        inspect,
+        backend,
+        timers,
+        queue_error_policy: FsmQueueErrorPolicy::default(),
+        deferred,
+        #[cfg(all(feature="async", feature="std"))]
+        pending_actions: Vec::new()
     }
-}
\ No newline at end of file
+}
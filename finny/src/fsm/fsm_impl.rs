@@ -1,10 +1,18 @@
-use crate::{DispatchContext, FsmTimers, Inspect, lib::*};
-use crate::{FsmBackend, FsmEvent, FsmEventQueue, FsmResult, FsmStates};
+use crate::{DispatchContext, FsmError, FsmEventQueueDeferred, FsmEventQueueNoDeferral, FsmTimers, Inspect, lib::*};
+use crate::{FsmBackend, FsmCurrentState, FsmEvent, FsmEventQueue, FsmResult, FsmStates};
 
-use super::FsmStateFactory;
+use super::{FsmActiveState, FsmCurrentStatePath, FsmStateFactory, FsmStatePath, FsmStateTryAsRef};
+
+#[cfg(all(feature="async", feature="std"))]
+use crate::FsmAsyncAction;
 
 /// The struct that holds the core context and state of the given Finny FSM. Doesn't include
 /// environmental traits that can be changed at runtime.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(bound(
+    serialize = "<F as FsmBackend>::Context: serde::Serialize, <F as FsmBackend>::States: serde::Serialize, <<F as FsmBackend>::States as FsmStates<F>>::CurrentState: serde::Serialize",
+    deserialize = "<F as FsmBackend>::Context: serde::Deserialize<'de>, <F as FsmBackend>::States: serde::Deserialize<'de>, <<F as FsmBackend>::States as FsmStates<F>>::CurrentState: serde::Deserialize<'de>"
+)))]
 pub struct FsmBackendImpl<F: FsmBackend> {
     pub context: <F as FsmBackend>::Context,
     pub states: <F as FsmBackend>::States,
@@ -34,11 +42,52 @@ impl<F: FsmBackend> FsmBackendImpl<F> {
         self.current_states
     }
 
+    /// The state, for the states that are always stored. With the enum storage, the states of the regions
+    /// are only available through `try_get_state`.
     pub fn get_state<S>(&self) -> &S
         where <F as FsmBackend>::States : AsRef<S>
     {
         self.states.as_ref()
     }
+
+    /// The state, if it is stored. Always available, except for the inactive states with the enum storage.
+    pub fn try_get_state<S>(&self) -> Option<&S>
+        where <F as FsmBackend>::States : FsmStateTryAsRef<S>
+    {
+        self.states.try_as_ref()
+    }
+
+    /// Is the state `S` active in any of the regions or the nested submachines? Called as `is_in_state::<S, _>()`,
+    /// the path to the state is resolved at compile time.
+    pub fn is_in_state<S, TPath>(&self) -> bool
+        where F: FsmActiveState<S, TPath>
+    {
+        F::is_active(self)
+    }
+
+    /// The state `S`, only if it is active in any of the regions or the nested submachines.
+    pub fn try_get_active_state<S, TPath>(&self) -> Option<&S>
+        where F: FsmActiveState<S, TPath>
+    {
+        F::get_active_state(self)
+    }
+
+    /// The active states of all the regions, including the nested submachines.
+    pub fn current_state_path(&self) -> FsmStatePath
+        where F: FsmCurrentStatePath
+    {
+        let mut path = FsmStatePath::default();
+        F::write_state_path(self, &mut path, 0);
+        path
+    }
+
+    /// All the regions of the machine have reached one of their final states.
+    pub fn is_completed(&self) -> bool {
+        self.current_states.as_ref().iter().all(|s| match s {
+            FsmCurrentState::State(state) => <<F as FsmBackend>::States as FsmStates<F>>::is_final_state(state),
+            FsmCurrentState::Stopped | FsmCurrentState::InTransition(_) => false
+        })
+    }
 }
 
 impl<F: FsmBackend> Deref for FsmBackendImpl<F> {
@@ -60,25 +109,100 @@ pub trait FsmBackendResetSubmachine<F: FsmBackend, FSub> {
 }
 
 
+/// The schema version of the snapshots created by this version of the library.
+#[cfg(feature="serde")]
+pub const FSM_SNAPSHOT_VERSION: u32 = 1;
+
+/// A serializable snapshot of the machine's context, states, timers and the current states. The
+/// queued and the deferred events aren't included. Created with `FsmFrontend::snapshot()` and
+/// restored with `FsmFactory::restore()`.
+#[cfg(feature="serde")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct FsmSnapshot<B> {
+    /// The schema version, see `FSM_SNAPSHOT_VERSION`.
+    pub version: u32,
+    pub backend: B
+}
+
+/// How to handle the errors of the events that are dispatched from the queue. The errors of the
+/// queued events are never returned as they are, so they can't be mistaken for the error of the
+/// dispatched event.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FsmQueueErrorPolicy {
+    /// Stop dispatching the queue and return the first error in a `FsmError::QueueErrors` report.
+    /// The remaining events stay in the queue.
+    #[cfg(feature="std")]
+    StopOnFirstError,
+    /// Dispatch the entire queue and return all the errors in a `FsmError::QueueErrors` report.
+    #[cfg(feature="std")]
+    CollectErrors,
+    /// Dispatch the entire queue and report the errors to the `Inspect` implementation. The default.
+    Inspect
+}
+
+impl Default for FsmQueueErrorPolicy {
+    fn default() -> Self {
+        FsmQueueErrorPolicy::Inspect
+    }
+}
+
+/// The errors that were collected while dispatching the queue.
+#[cfg(feature="std")]
+#[derive(Debug, PartialEq)]
+pub struct FsmQueueErrorReport {
+    pub errors: Vec<FsmError>
+}
+
 /// The frontend of a state machine which also includes environmental services like queues
 /// and inspection. The usual way to use the FSM.
-pub struct FsmFrontend<F, Q, I, T> 
-    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>
+pub struct FsmFrontend<F, Q, I, T, D = FsmEventQueueNoDeferral<F>>
+    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>, D: FsmEventQueue<F>
 {
     pub backend: FsmBackendImpl<F>,
     pub queue: Q,
     pub inspect: I,
-    pub timers: T
+    pub timers: T,
+    pub queue_error_policy: FsmQueueErrorPolicy,
+    /// The events that were deferred by the states, redelivered after the next handled event.
+    pub deferred: D,
+    /// The pending async actions of the transitions, for each region.
+    #[cfg(all(feature="async", feature="std"))]
+    pub pending_actions: Vec<(crate::FsmTransitionCompleted, FsmAsyncAction)>
 }
 
-impl<F, Q, I, T> FsmFrontend<F, Q, I, T>
-    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>
+impl<F, Q, I, T, D> FsmFrontend<F, Q, I, T, D>
+    where F: FsmBackend + FsmCurrentStatePath, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>, D: FsmEventQueue<F>
 {
     /// Start the FSM, initiates the transition to the initial state.
     pub fn start(&mut self) -> FsmResult<()> {
         Self::dispatch_single_event(self, FsmEvent::Start)
     }
 
+    /// Stop the FSM. Executes the exit actions of the active states in all regions, stops the
+    /// active submachines and cancels the state timers. The queued and deferred events and the pending
+    /// async actions are dropped. The FSM can be started again afterwards.
+    pub fn stop(&mut self) -> FsmResult<()> {
+        Self::dispatch_single_event(self, FsmEvent::Stop)?;
+
+        self.queue.clear();
+        self.deferred.clear();
+
+        #[cfg(all(feature="async", feature="std"))]
+        self.pending_actions.clear();
+
+        Ok(())
+    }
+
+    /// Take a snapshot of the machine that can be serialized. Best taken between the dispatches,
+    /// as the pending events in the queues aren't stored.
+    #[cfg(feature="serde")]
+    pub fn snapshot(&self) -> FsmSnapshot<&FsmBackendImpl<F>> {
+        FsmSnapshot {
+            version: FSM_SNAPSHOT_VERSION,
+            backend: &self.backend
+        }
+    }
+
     /// Dispatch any pending timer events into the queue, then run all the
     /// events from the queue until completition.
     pub fn dispatch_timer_events(&mut self) -> FsmResult<()> {
@@ -104,32 +228,129 @@ impl<F, Q, I, T> FsmFrontend<F, Q, I, T>
         self.dispatch_queue()
     }
 
-    /// Dispatch only this event, do not run it to completition.
+    /// Is any of the regions waiting for the async action of its transition to complete?
+    #[cfg(all(feature="async", feature="std"))]
+    pub fn is_in_transition(&self) -> bool {
+        !self.pending_actions.is_empty()
+    }
+
+    /// Dispatch only this event, do not run it to completition. If the machine has left the states
+    /// that deferred the events, they are redelivered. While the machine is in an async transition,
+    /// the events are put into the queue instead.
     pub fn dispatch_single_event(&mut self, event: FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>) -> FsmResult<()> {
+        #[cfg(all(feature="async", feature="std"))]
+        let event = match event {
+            FsmEvent::Event(ev) if self.is_in_transition() => return self.queue.enqueue(ev),
+            event => event
+        };
+
+        let is_event = matches!(event, FsmEvent::Event(_) | FsmEvent::TransitionCompleted(_));
+        let state_path = if is_event && self.deferred.len() > 0 {
+            Some(self.backend.current_state_path())
+        } else {
+            None
+        };
+
+        self.dispatch_event_to_backend(event)?;
+
+        if let Some(state_path) = state_path {
+            if self.backend.current_state_path() != state_path {
+                self.redeliver_deferred();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dispatch_event_to_backend(&mut self, event: FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>) -> FsmResult<()> {
+        let mut queue = FsmEventQueueDeferred {
+            queue: &mut self.queue,
+            deferred: &mut self.deferred,
+            #[cfg(all(feature="async", feature="std"))]
+            pending_actions: &mut self.pending_actions,
+            _fsm: PhantomData::default()
+        };
+
         let dispatch_ctx = DispatchContext {
             backend: &mut self.backend,
             inspect: &mut self.inspect,
-            queue: &mut self.queue,
+            queue: &mut queue,
             timers: &mut self.timers
         };
 
         F::dispatch_event(dispatch_ctx, event)
     }
 
-    /// Dispatch the entire event queue and run it to completition.
+    /// Dispatch the deferred events again, in their original order. The events that are still
+    /// deferred are put back into the queue. Repeats while the redelivered events change the
+    /// active states. The errors of the redelivered events are unrelated to the dispatched event,
+    /// they are only reported to the inspection and the events are dropped.
+    fn redeliver_deferred(&mut self) {
+        loop {
+            #[cfg(all(feature="async", feature="std"))]
+            {
+                if self.is_in_transition() {
+                    break;
+                }
+            }
+
+            let state_path = self.backend.current_state_path();
+            for _ in 0..self.deferred.len() {
+                if let Some(ev) = self.deferred.dequeue() {
+                    if let Err(e) = self.dispatch_event_to_backend(FsmEvent::Event(ev)) {
+                        self.inspect.on_error("Failed to redeliver a deferred event", &e);
+                    }
+                }
+            }
+
+            if self.deferred.len() == 0 || self.backend.current_state_path() == state_path {
+                break;
+            }
+        }
+    }
+
+    /// Dispatch the entire event queue and run it to completition. The errors are handled
+    /// according to the `queue_error_policy`.
     pub fn dispatch_queue(&mut self) -> FsmResult<()> {
-        while let Some(ev) = self.queue.dequeue() {
-            let ev: <F as FsmBackend>::Events = ev.into();
-            // todo: log?
-            Self::dispatch(self, ev);
+        #[cfg(feature="std")]
+        let mut errors = vec![];
+
+        loop {
+            #[cfg(all(feature="async", feature="std"))]
+            {
+                if self.is_in_transition() {
+                    break;
+                }
+            }
+
+            let ev = match self.queue.dequeue() {
+                Some(ev) => FsmEvent::Event(ev),
+                None => break
+            };
+            if let Err(e) = Self::dispatch_single_event(self, ev) {
+                match self.queue_error_policy {
+                    #[cfg(feature="std")]
+                    FsmQueueErrorPolicy::StopOnFirstError => return Err(FsmError::QueueErrors(FsmQueueErrorReport { errors: vec![e] })),
+                    #[cfg(feature="std")]
+                    FsmQueueErrorPolicy::CollectErrors => errors.push(e),
+                    FsmQueueErrorPolicy::Inspect => self.inspect.on_error("Failed to dispatch a queued event", &e)
+                }
+            }
+        }
+
+        #[cfg(feature="std")]
+        {
+            if !errors.is_empty() {
+                return Err(FsmError::QueueErrors(FsmQueueErrorReport { errors }));
+            }
         }
 
         Ok(())
     }
 }
 
-impl<F, Q, I, T> Deref for FsmFrontend<F, Q, I, T>
-    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>
+impl<F, Q, I, T, D> Deref for FsmFrontend<F, Q, I, T, D>
+    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>, D: FsmEventQueue<F>
 {
     type Target = FsmBackendImpl<F>;
 
@@ -138,8 +359,8 @@ impl<F, Q, I, T> Deref for FsmFrontend<F, Q, I, T>
     }
 }
 
-impl<F, Q, I, T> DerefMut for FsmFrontend<F, Q, I, T>
-    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>
+impl<F, Q, I, T, D> DerefMut for FsmFrontend<F, Q, I, T, D>
+    where F: FsmBackend, Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>, D: FsmEventQueue<F>
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.backend
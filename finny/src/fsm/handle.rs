@@ -0,0 +1,143 @@
+//! A thread-safe handle to a state machine that runs on its own thread.
+
+use std::boxed::Box;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use crate::{FsmBackend, FsmBackendImpl, FsmCurrentStatePath, FsmError, FsmEventQueue, FsmFrontend, FsmResult, FsmTimers, Inspect};
+
+type FsmQuery<F> = Box<dyn FnOnce(&FsmBackendImpl<F>) + Send>;
+
+enum FsmCommand<F: FsmBackend> {
+    Event(<F as FsmBackend>::Events, Option<SyncSender<FsmResult<()>>>),
+    Query(FsmQuery<F>),
+    Stop
+}
+
+/// A cloneable handle for sending the events to the machine that is owned by a `FsmThread`. Can be
+/// sent to other threads.
+pub struct FsmHandle<F: FsmBackend> {
+    sender: Sender<FsmCommand<F>>
+}
+
+impl<F: FsmBackend> Clone for FsmHandle<F> {
+    fn clone(&self) -> Self {
+        Self { sender: self.sender.clone() }
+    }
+}
+
+impl<F: FsmBackend> FsmHandle<F> {
+    /// Send the event to the machine without waiting for it to be dispatched. The errors
+    /// of the dispatch are only reported to the machine's `Inspect` implementation.
+    pub fn send<E>(&self, event: E) -> FsmResult<()>
+        where E: Into<<F as FsmBackend>::Events>
+    {
+        self.sender.send(FsmCommand::Event(event.into(), None)).map_err(|_| FsmError::Disconnected)
+    }
+
+    /// Send the event and wait until it is dispatched and run to completition.
+    pub fn send_and_wait<E>(&self, event: E) -> FsmResult<()>
+        where E: Into<<F as FsmBackend>::Events>
+    {
+        let (reply, result) = mpsc::sync_channel(1);
+        self.sender.send(FsmCommand::Event(event.into(), Some(reply))).map_err(|_| FsmError::Disconnected)?;
+        result.recv().map_err(|_| FsmError::Disconnected)?
+    }
+
+    /// Run the query with the backend of the machine, between the dispatches of the events. Used for
+    /// reading the context and the states.
+    pub fn query<R, Q>(&self, query: Q) -> FsmResult<R>
+        where Q: FnOnce(&FsmBackendImpl<F>) -> R + Send + 'static, R: Send + 'static
+    {
+        let (reply, result) = mpsc::sync_channel(1);
+        let query = Box::new(move |backend: &FsmBackendImpl<F>| {
+            let _ = reply.send(query(backend));
+        });
+        self.sender.send(FsmCommand::Query(query)).map_err(|_| FsmError::Disconnected)?;
+        result.recv().map_err(|_| FsmError::Disconnected)
+    }
+}
+
+/// Owns the machine's frontend and runs it on a dedicated thread, together with its timers. The
+/// events are sent using the `FsmHandle`. The thread is stopped when this owner is dropped.
+pub struct FsmThread<F: FsmBackend> {
+    handle: FsmHandle<F>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl<F> FsmThread<F>
+    where F: FsmBackend + FsmCurrentStatePath + 'static, <F as FsmBackend>::Events: Send
+{
+    /// Move the frontend to a new thread. The machine isn't started automatically.
+    pub fn spawn<Q, I, T, D>(frontend: FsmFrontend<F, Q, I, T, D>) -> Self
+        where Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>, D: FsmEventQueue<F>,
+        FsmFrontend<F, Q, I, T, D>: Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || Self::run(frontend, receiver));
+
+        Self {
+            handle: FsmHandle { sender },
+            thread: Some(thread)
+        }
+    }
+
+    fn run<Q, I, T, D>(mut frontend: FsmFrontend<F, Q, I, T, D>, receiver: Receiver<FsmCommand<F>>)
+        where Q: FsmEventQueue<F>, I: Inspect, T: FsmTimers<F>, D: FsmEventQueue<F>
+    {
+        loop {
+            let command = match frontend.timers.get_next_deadline() {
+                Some(deadline) => match receiver.recv_timeout(deadline) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break
+                },
+                None => match receiver.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => break
+                }
+            };
+
+            match command {
+                None => {
+                    let _ = frontend.dispatch_timer_events();
+                },
+                Some(FsmCommand::Event(event, reply)) => {
+                    let result = frontend.dispatch(event);
+                    if let Some(reply) = reply {
+                        let _ = reply.send(result);
+                    }
+                },
+                Some(FsmCommand::Query(query)) => query(&frontend.backend),
+                Some(FsmCommand::Stop) => break
+            }
+        }
+    }
+}
+
+impl<F: FsmBackend> FsmThread<F> {
+    /// A new handle to the machine.
+    pub fn handle(&self) -> FsmHandle<F> {
+        self.handle.clone()
+    }
+
+    /// Stop the thread after the already sent events are dispatched and wait for it to finish.
+    pub fn stop(mut self) -> FsmResult<()> {
+        self.stop_thread()
+    }
+
+    fn stop_thread(&mut self) -> FsmResult<()> {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.handle.sender.send(FsmCommand::Stop);
+            thread.join().map_err(|_| FsmError::Disconnected)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: FsmBackend> Drop for FsmThread<F> {
+    fn drop(&mut self) {
+        let _ = self.stop_thread();
+    }
+}
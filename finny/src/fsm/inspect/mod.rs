@@ -22,6 +22,7 @@ pub trait Inspect: InspectEvent {
     fn on_state_enter<S>(&self);
     fn on_state_exit<S>(&self);
     fn on_action<S>(&self);
+    fn on_timer_trigger<T>(&self);
 
     fn on_error<E>(&self, msg: &str, error: &E) where E: core::fmt::Debug;
     fn info(&self, msg: &str);    
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use crate::{FsmBackend, FsmBackendImpl, FsmCurrentState, FsmStates, FsmStaticName, inspect::highlight::InspectHighlight};
+use finny_meta::{FinnyFsm, diagrams::{DiagramHighlight, DiagramKind, render_diagram}};
+
+/// The description of the machine, embedded by the code generator with the `meta` feature.
+pub trait FsmMeta: FsmBackend {
+    fn fsm_meta() -> FinnyFsm;
+
+    /// Collects the descriptions of the submachines and of their own submachines, by their ids.
+    fn fsm_sub_meta(submachines: &mut HashMap<String, FinnyFsm>);
+
+    /// The highlight of the submachine, if the state is one.
+    fn sub_diagram_highlight(backend: &FsmBackendImpl<Self>, state: &<<Self as FsmBackend>::States as FsmStates<Self>>::StateKind, inspect: Option<&InspectHighlight>) -> Option<DiagramHighlight>;
+}
+
+impl<F: FsmMeta> FsmBackendImpl<F> {
+    /// The current states of the running regions and the transitions that were recently recorded
+    /// by the inspector, including the ones of the active submachines.
+    pub fn diagram_highlight(&self, inspect: Option<&InspectHighlight>) -> DiagramHighlight {
+        let mut highlight = DiagramHighlight {
+            recent_transitions: inspect.map(|i| i.recent_transitions()).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for state in self.current_states.as_ref() {
+            if let FsmCurrentState::State(state) = state {
+                highlight.current_states.push(state.static_name().to_string());
+                if let Some(sub) = F::sub_diagram_highlight(self, state, inspect) {
+                    highlight.submachines.insert(state.static_name().to_string(), sub);
+                }
+            }
+        }
+
+        highlight
+    }
+
+    /// Draw the live machine, with its current states highlighted. The most recent transitions are
+    /// colored when the machine is inspected with `InspectHighlight`. The submachines are nested
+    /// within their states and highlighted the same way.
+    pub fn render_diagram(&self, kind: DiagramKind, inspect: Option<&InspectHighlight>) -> String {
+        let mut submachines = HashMap::new();
+        F::fsm_sub_meta(&mut submachines);
+        render_diagram(kind, &F::fsm_meta(), &submachines, Some(&self.diagram_highlight(inspect)))
+    }
+}
@@ -11,6 +11,14 @@ mod tests_fsm;
 mod dispatch;
 mod timers;
 mod inspect;
+mod error;
+mod state_path;
+#[cfg(feature="async")]
+mod frontend_async;
+#[cfg(feature="std")]
+mod handle;
+#[cfg(feature="meta")]
+mod meta;
 
 pub use self::events::*;
 pub use self::fsm_factory::*;
@@ -21,20 +29,19 @@ pub use self::transitions::*;
 pub use self::inspect::*;
 pub use self::dispatch::*;
 pub use self::timers::*;
+pub use self::error::*;
+pub use self::state_path::*;
+#[cfg(feature="async")]
+pub use self::frontend_async::*;
+#[cfg(feature="std")]
+pub use self::handle::*;
+#[cfg(feature="meta")]
+pub use self::meta::*;
 
 use crate::lib::*;
 
 pub type FsmResult<T> = Result<T, FsmError>;
 
-/// The lib-level error type.
-#[derive(Debug, PartialEq)]
-pub enum FsmError {
-    NoTransition,
-    QueueOverCapacity,
-    NotSupported,
-    TimerNotStarted
-}
-
 pub type FsmDispatchResult = FsmResult<()>;
 
 /// Finite State Machine backend. Handles the dispatching, the types are
@@ -46,7 +53,7 @@ pub trait FsmBackend where Self: Sized + Debug {
     type States: FsmStates<Self>;
     /// A tagged union type with all the supported events. This type has to support cloning to facilitate
     /// the dispatch into sub-machines and into multiple regions.
-    type Events: AsRef<str> + Clone;
+    type Events: AsRef<str> + FsmStaticName + Clone;
     /// An enum with variants for all the possible timer instances, with support for submachines.
     type Timers: Debug + Clone + PartialEq + AllVariants;
 
@@ -60,4 +67,29 @@ pub trait AllVariants where Self: Sized
     type Iter: Iterator<Item=Self>;
 
     fn iter() -> Self::Iter;
-}
\ No newline at end of file
+}
+/// Implemented by the machines for each of their entry points. Entering a submachine through an
+/// entry point skips its initial state.
+pub trait FsmEntryPoint<TEntryPoint>: FsmBackend {
+    /// The event that enters the stopped machine through this entry point.
+    fn entry_event() -> <Self as FsmBackend>::Events;
+}
+
+/// Implemented by the machines for each of their exit points. The event is enqueued to the parent
+/// machine once the exit point is reached.
+pub trait FsmExitPoint<TExitPoint>: FsmBackend {
+    fn get_exit_point(event: &<Self as FsmBackend>::Events) -> Option<&TExitPoint>;
+}
+
+/// Implemented by the machines that never defer the events, including their submachines. The frontends without
+/// a deferred queue are limited to these machines.
+pub trait FsmNoDeferrals: FsmBackend { }
+
+/// Implemented by the machines without any async transition actions, including their submachines. The other
+/// machines can only be driven by the `AsyncFsmFrontend`.
+pub trait FsmNoAsyncActions: FsmBackend { }
+
+/// A static name of the variant, used for the events and the states in the error reports.
+pub trait FsmStaticName {
+    fn static_name(&self) -> &'static str;
+}
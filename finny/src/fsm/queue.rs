@@ -1,5 +1,8 @@
 use crate::lib::*;
-use crate::{FsmBackend, FsmResult};
+use crate::{FsmBackend, FsmError, FsmRegionId, FsmResult};
+
+#[cfg(all(feature = "async", feature = "std"))]
+use crate::{FsmAsyncAction, FsmTransitionCompleted};
 
 /// The event queueing trait for FSMs. Can be used from outside or from within the actions of the FSM.
 pub trait FsmEventQueue<F: FsmBackend>: FsmEventQueueSender<F> {
@@ -7,11 +10,25 @@ pub trait FsmEventQueue<F: FsmBackend>: FsmEventQueueSender<F> {
     fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events>;
     /// Number of messages to be dequeued.
     fn len(&self) -> usize;
+    /// Drop all the messages.
+    fn clear(&mut self) {
+        while self.dequeue().is_some() { }
+    }
 }
 
 pub trait FsmEventQueueSender<F: FsmBackend> {
     /// Try to enqueue an event.
     fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()>;
+    /// Try to put the event into the deferred queue. It will be redelivered once the machine has left the state that deferred it.
+    fn defer<E: Into<<F as FsmBackend>::Events>>(&mut self, _event: E) -> FsmResult<()> {
+        Err(FsmError::NotSupported)
+    }
+    /// Hand over the future of an async transition action to the frontend. The region stays in the transition
+    /// until the future resolves.
+    #[cfg(all(feature = "async", feature = "std"))]
+    fn spawn_action(&mut self, _completed: FsmTransitionCompleted, _action: FsmAsyncAction) -> FsmResult<()> {
+        Err(FsmError::NotSupported)
+    }
 }
 
 #[cfg(feature = "std")]
@@ -270,12 +287,41 @@ impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueNull<F> {
     }
 }
 
+/// The default queue for the deferred events, which doesn't support deferring them.
+pub struct FsmEventQueueNoDeferral<F> {
+    _ty: PhantomData<F>
+}
+
+impl<F> FsmEventQueueNoDeferral<F> {
+    pub fn new() -> Self {
+        FsmEventQueueNoDeferral { _ty: PhantomData::default() }
+    }
+}
+
+impl<F: FsmBackend> FsmEventQueue<F> for FsmEventQueueNoDeferral<F> {
+    fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+        None
+    }
+
+    fn len(&self) -> usize {
+        0
+    }
+}
+
+impl<F: FsmBackend> FsmEventQueueSender<F> for FsmEventQueueNoDeferral<F> {
+    fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, _event: E) -> FsmResult<()> {
+        Err(FsmError::NotSupported)
+    }
+}
+
 pub struct FsmEventQueueSub<'a, Q, F, FSub>
     where 
         F: FsmBackend,
         Q: FsmEventQueueSender<F>
 {
     pub parent: &'a mut Q,
+    /// The region of the parent machine that holds the submachine.
+    pub region: FsmRegionId,
     pub _parent_fsm: PhantomData<F>,
     pub _sub_fsm: PhantomData<FSub>
 }
@@ -307,6 +353,67 @@ impl<'a, Q, F, FSub> FsmEventQueueSender<FSub> for FsmEventQueueSub<'a, Q, F, FS
     {
         self.parent.enqueue(event.into())
     }
+
+    fn defer<E: Into<<FSub as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()>
+    {
+        self.parent.defer(event.into())
+    }
+
+    #[cfg(all(feature = "async", feature = "std"))]
+    fn spawn_action(&mut self, completed: FsmTransitionCompleted, action: FsmAsyncAction) -> FsmResult<()> {
+        self.parent.spawn_action(completed.with_parent_region(self.region)?, action)
+    }
+}
+
+/// Combines the main event queue with the queue of the deferred events. Used by the frontend
+/// while dispatching.
+pub struct FsmEventQueueDeferred<'a, F, Q, D>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+        D: FsmEventQueue<F>
+{
+    pub queue: &'a mut Q,
+    pub deferred: &'a mut D,
+    #[cfg(all(feature = "async", feature = "std"))]
+    pub pending_actions: &'a mut Vec<(FsmTransitionCompleted, FsmAsyncAction)>,
+    pub _fsm: PhantomData<F>
+}
+
+impl<'a, F, Q, D> FsmEventQueue<F> for FsmEventQueueDeferred<'a, F, Q, D>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+        D: FsmEventQueue<F>
+{
+    fn dequeue(&mut self) -> Option<<F as FsmBackend>::Events> {
+        self.queue.dequeue()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+impl<'a, F, Q, D> FsmEventQueueSender<F> for FsmEventQueueDeferred<'a, F, Q, D>
+    where
+        F: FsmBackend,
+        Q: FsmEventQueue<F>,
+        D: FsmEventQueue<F>
+{
+    fn enqueue<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+        self.queue.enqueue(event)
+    }
+
+    fn defer<E: Into<<F as FsmBackend>::Events>>(&mut self, event: E) -> FsmResult<()> {
+        self.deferred.enqueue(event)
+    }
+
+    #[cfg(all(feature = "async", feature = "std"))]
+    fn spawn_action(&mut self, completed: FsmTransitionCompleted, action: FsmAsyncAction) -> FsmResult<()> {
+        self.pending_actions.push((completed, action));
+        Ok(())
+    }
 }
 
 
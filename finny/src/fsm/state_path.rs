@@ -0,0 +1,106 @@
+use crate::{FsmBackend, FsmBackendImpl, FsmCurrentState, FsmRegionId, FsmStates, FsmStaticName, lib::*};
+
+/// The maximum number of states in a path, including the states of the nested submachines.
+pub const FSM_STATE_PATH_MAX_STATES: usize = 16;
+
+/// One of the active states, owned by the machine `fsm`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FsmStatePathItem {
+    /// The name of the machine.
+    pub fsm: &'static str,
+    pub region: FsmRegionId,
+    pub state: &'static str,
+    /// Zero for the states of the outermost machine, incremented for each nested submachine.
+    pub depth: usize
+}
+
+/// The active states of the machine and of its active submachines, listed depth first. The states of a
+/// submachine follow the parent's state. Doesn't require allocations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FsmStatePath {
+    /// The states, up to `FSM_STATE_PATH_MAX_STATES`.
+    pub states: heapless::Vec<FsmStatePathItem, FSM_STATE_PATH_MAX_STATES>,
+    /// Set when there were more active states than fit into the path, the remaining ones were left out.
+    pub truncated: bool
+}
+
+impl FsmStatePath {
+    /// The innermost active states, each of them ends one of the branches of the path.
+    pub fn leaves(&self) -> impl Iterator<Item = &FsmStatePathItem> {
+        let states = &self.states;
+        states.iter().enumerate().filter(move |(i, s)| states.get(i + 1).is_none_or(|next| next.depth <= s.depth)).map(|(_, s)| s)
+    }
+
+    /// Is this state of the machine active?
+    pub fn contains(&self, fsm: &str, state: &str) -> bool {
+        self.states.iter().any(|s| s.fsm == fsm && s.state == state)
+    }
+}
+
+impl fmt::Display for FsmStatePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.states.is_empty() {
+            return f.write_str("Fsm::Stopped");
+        }
+
+        // the branches from the outermost machine to each of the innermost states
+        let mut branch: heapless::Vec<&FsmStatePathItem, FSM_STATE_PATH_MAX_STATES> = heapless::Vec::new();
+        let mut first = true;
+        for (i, s) in self.states.iter().enumerate() {
+            branch.truncate(s.depth);
+            let _ = branch.push(s);
+
+            let is_leaf = self.states.get(i + 1).is_none_or(|next| next.depth <= s.depth);
+            if !is_leaf {
+                continue;
+            }
+
+            if !first {
+                f.write_str(", ")?;
+            }
+            first = false;
+
+            for (j, s) in branch.iter().enumerate() {
+                if j > 0 {
+                    f.write_str(" -> ")?;
+                }
+                write!(f, "{}::{}", s.fsm, s.state)?;
+            }
+        }
+
+        if self.truncated {
+            f.write_str(", …")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds the path of the active states. Implemented by the code generator.
+pub trait FsmCurrentStatePath: FsmBackend {
+    /// The name of the machine's type, without the generics.
+    fn fsm_name() -> &'static str;
+
+    /// Appends the active states of the submachine, if the state is one.
+    fn write_sub_state_path(backend: &FsmBackendImpl<Self>, state: &<<Self as FsmBackend>::States as FsmStates<Self>>::StateKind, path: &mut FsmStatePath, depth: usize);
+
+    fn write_state_path(backend: &FsmBackendImpl<Self>, path: &mut FsmStatePath, depth: usize) {
+        let current_states: &[FsmCurrentState<<<Self as FsmBackend>::States as FsmStates<Self>>::StateKind>] = backend.current_states.as_ref();
+        for (region, state) in current_states.iter().enumerate() {
+            if let FsmCurrentState::State(state) = state {
+                let item = FsmStatePathItem {
+                    fsm: Self::fsm_name(),
+                    region,
+                    state: state.static_name(),
+                    depth
+                };
+                if path.states.push(item).is_err() {
+                    path.truncated = true;
+                    return;
+                }
+
+                Self::write_sub_state_path(backend, state, path, depth + 1);
+            }
+        }
+    }
+}
@@ -1,22 +1,37 @@
-use crate::{FsmBackend, lib::*};
+use crate::{FsmBackend, FsmBackendImpl, FsmStaticName, FsmTimers, lib::*};
 
 use crate::FsmResult;
 
 /// The implementation should hold all of the FSM's states as fields.
 pub trait FsmStates<TFsm>: FsmStateFactory<TFsm> where TFsm: FsmBackend {
     /// The enum type for all states that's used as the "current state" field in the FSM's backend.
-    type StateKind: Clone + Copy + Debug + PartialEq + 'static;
+    type StateKind: Clone + Copy + Debug + PartialEq + FsmStaticName + 'static;
     /// An array of current states for the machine, one for each region.
     type CurrentState: Clone + Copy + Debug + Default + AsRef<[FsmCurrentState<Self::StateKind>]> + AsMut<[FsmCurrentState<Self::StateKind>]> + 'static;
+
+    /// Reaching this state completes its region.
+    fn is_final_state(_state: &Self::StateKind) -> bool {
+        false
+    }
+
+    /// Create the timers that were running when these states were stored, including the ones of the
+    /// submachines. Used when restoring the machine.
+    fn restore_timers<T: FsmTimers<TFsm>>(&self, _timers: &mut T) -> FsmResult<()> {
+        Ok(())
+    }
 }
 
 /// The current state of the FSM.
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FsmCurrentState<S> where S: Clone + Copy {
     /// The FSM is halted and has to be started using the `start()` method.
     Stopped,
     /// The FSM is in this state.
-    State(S)
+    State(S),
+    /// The FSM left the previous state and waits for the async action of the transition to complete, the
+    /// target state isn't entered yet.
+    InTransition(S)
 }
 
 impl<S> FsmCurrentState<S> where S: Clone + Copy {
@@ -32,7 +47,8 @@ impl<S> Debug for FsmCurrentState<S> where S: Debug + Copy {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FsmCurrentState::Stopped => f.write_str("Fsm::Stopped"),
-            FsmCurrentState::State(s) => s.fmt(f)
+            FsmCurrentState::State(s) => s.fmt(f),
+            FsmCurrentState::InTransition(s) => f.write_fmt(format_args!("Fsm::InTransition({:?})", s))
         }
     }
 }
@@ -56,6 +72,30 @@ impl<TState, TFsm> FsmStateFactory<TFsm> for TState where TState: Default, TFsm:
     }
 }
 
+/// Builds the state from the event that triggered the transition into it. Used by the states that are
+/// constructed on entry.
+pub trait FsmStateFromEvent<TFsm, TEvent> where Self: Sized, TFsm: FsmBackend {
+    fn from_event(event: &TEvent, context: &<TFsm as FsmBackend>::Context) -> Self;
+}
+
+/// Retrieve a state, if it is stored at the moment. With the enum storage, only the active states are.
+pub trait FsmStateTryAsRef<S> {
+    fn try_as_ref(&self) -> Option<&S>;
+    fn try_as_mut(&mut self) -> Option<&mut S>;
+}
+
+/// The kind of the state in the machine `TFsm`. Implemented by the derive for each of the machine's states.
+pub trait FsmStateKindOf<TFsm>: Sized where TFsm: FsmBackend {
+    const STATE_KIND: <<TFsm as FsmBackend>::States as FsmStates<TFsm>>::StateKind;
+}
+
+/// Finds the active state `S` in any of the regions, including the nested submachines. The path is inferred
+/// by the compiler, it's `()` for the machine's own states and `(TSub, TPath)` for the states of the submachine `TSub`.
+pub trait FsmActiveState<S, TPath>: FsmBackend {
+    fn is_active(backend: &FsmBackendImpl<Self>) -> bool;
+    fn get_active_state(backend: &FsmBackendImpl<Self>) -> Option<&S>;
+}
+
 /// Retrieve a pair of states as immutable references. Used in state transitions.
 pub trait FsmStateTransitionAsRef<T1, T2> {
     fn as_state_transition_ref(&self) -> (&T1, &T2);
@@ -1,6 +1,6 @@
 //! A minimal, internal FSM for unit tests, manually written.
 
-use crate::{AllVariants, FsmBackend, FsmCurrentState, FsmStates};
+use crate::{AllVariants, FsmBackend, FsmCurrentState, FsmStates, FsmStaticName};
 use derive_more::From;
 
 #[derive(Default)]
@@ -21,6 +21,12 @@ pub enum StateKind {
     StateA
 }
 
+impl FsmStaticName for StateKind {
+    fn static_name(&self) -> &'static str {
+        "StateA"
+    }
+}
+
 impl FsmStates<TestFsm> for States {
     type StateKind = StateKind;
     type CurrentState = [FsmCurrentState<StateKind>; 1];
@@ -36,6 +42,12 @@ impl AsRef<str> for Events {
         todo!()
     }
 }
+impl FsmStaticName for Events {
+    fn static_name(&self) -> &'static str {
+        "EventA"
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FsmBackendTimers {
 
@@ -1,5 +1,5 @@
 use crate::{AllVariants, DispatchContext, FsmError, FsmEvent, FsmEventQueue, Inspect, lib::*};
-use crate::{FsmBackend, FsmResult};
+use crate::{FsmBackend, FsmResult, FsmStateTryAsRef};
 
 /// Associate some data with a specific timer ID.
 pub trait TimersStorage<FT, T> : Default
@@ -9,6 +9,11 @@ pub trait TimersStorage<FT, T> : Default
 }
 
 
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(bound(
+    serialize = "<F as FsmBackend>::Timers: serde::Serialize",
+    deserialize = "<F as FsmBackend>::Timers: serde::Deserialize<'de>"
+)))]
 pub struct TimerInstance<F>
     where F: FsmBackend
 {
@@ -65,19 +70,46 @@ pub trait FsmTimer<F, S>
         }
     }
 
+    /// Cancel the timer when the machine is stopped, regardless of its state exit settings.
+    fn execute_on_stop<I: Inspect, T: FsmTimers<F>>(&mut self, id: F::Timers, inspect: &mut I, timers: &mut T) {
+        self.execute_cancel(id, inspect, timers, "Cancelled the timer, the machine was stopped.");
+    }
+
+    /// Cancel the timer when its state is exited, regardless of its state exit settings. Used with the enum
+    /// storage, where the exited state isn't stored anymore and couldn't be passed to the trigger.
+    fn execute_on_exit_cancel<I: Inspect, T: FsmTimers<F>>(&mut self, id: F::Timers, inspect: &mut I, timers: &mut T) {
+        self.execute_cancel(id, inspect, timers, "Cancelled the timer, its state isn't stored after the exit.");
+    }
+
+    fn execute_cancel<I: Inspect, T: FsmTimers<F>>(&mut self, id: F::Timers, inspect: &mut I, timers: &mut T, msg: &str) {
+        let log = inspect.for_timer::<F>(id.clone());
+        if self.get_instance().is_some() {
+            match timers.cancel(id) {
+                Ok(_) => {
+                    *self.get_instance_mut() = None;
+                    log.info(msg);
+                },
+                Err(ref e) => {
+                    log.on_error("Failed to cancel the timer", e);
+                }
+            }
+        }
+    }
+
     fn execute_trigger<'a, 'b, 'c, 'd, Q, I, T>(id: F::Timers, context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, inspect: &mut I)
         where 
             Q: FsmEventQueue<F>,
             I: Inspect,
-            <F as FsmBackend>::States: AsRef<S>,
+            <F as FsmBackend>::States: FsmStateTryAsRef<S>,
             <F as FsmBackend>::States: AsRef<Self>,
             T: FsmTimers<F>
     {
         let inspect = inspect.for_timer::<F>(id);
+        inspect.on_timer_trigger::<Self>();
         let timer: &Self = context.backend.states.as_ref();
-        match timer.get_instance() {
-            Some(_) => {                
-                match Self::trigger(&context.backend.context, context.backend.states.as_ref()) {
+        match (timer.get_instance(), FsmStateTryAsRef::<S>::try_as_ref(&context.backend.states)) {
+            (Some(_), Some(state)) => {
+                match Self::trigger(&context.backend.context, state) {
                     Some(ev) => {
                         let inspect = inspect.new_event::<F>(&FsmEvent::Event(ev.clone()), &context.backend);
                         match context.queue.enqueue(ev) {
@@ -93,7 +125,8 @@ pub trait FsmTimer<F, S>
                 }
 
             },
-            None => {
+            // with the enum storage, the timers are cancelled together with their states
+            _ => {
                 let error = FsmError::TimerNotStarted;
                 inspect.on_error("Timer hasn't been started.", &error);
             }
@@ -102,6 +135,7 @@ pub trait FsmTimer<F, S>
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimerFsmSettings {
     pub enabled: bool,
     pub timeout: Duration,
@@ -148,6 +182,12 @@ pub trait FsmTimers<F>
     /// Return the timer that was triggered. Poll this until it returns None. The events
     /// should be dequeued in a FIFO manner.
     fn get_triggered_timer(&mut self) -> Option<<F as FsmBackend>::Timers>;
+
+    /// The time left until the next timer triggers, if there are any running timers. Used by
+    /// the async frontend to sleep until then.
+    fn get_next_deadline(&mut self) -> Option<Duration> {
+        None
+    }
 }
 
 
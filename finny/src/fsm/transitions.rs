@@ -1,10 +1,30 @@
 //! All of these traits will be implemented by the procedural code generator.
 
 use crate::{FsmBackendImpl, FsmDispatchResult, FsmEventQueueSub, FsmTimers, FsmTimersSub, lib::*};
-use crate::{DispatchContext, EventContext, FsmBackend, FsmCurrentState, FsmEvent, FsmEventQueue, FsmRegionId, FsmStateTransitionAsMut, FsmStates, Inspect};
+use crate::{DispatchContext, EventContext, FsmBackend, FsmCurrentState, FsmEvent, FsmEventQueue, FsmRegionId, FsmStateTransitionAsMut, FsmStateTryAsRef, FsmStates, Inspect};
 
 use super::inspect::InspectFsmEvent;
 
+/// Replaces the state with the one that was built for its entry, if it is constructed on entry. The state
+/// is built before the transition starts, a failure leaves the machine untouched.
+fn replace_entered_state<F: FsmBackend, S>(states: &mut <F as FsmBackend>::States, entered_state: Option<S>)
+    where <F as FsmBackend>::States: FsmStateTryAsRef<S>
+{
+    if let (Some(entered_state), Some(state)) = (entered_state, FsmStateTryAsRef::<S>::try_as_mut(states)) {
+        *state = entered_state;
+    }
+}
+
+/// The stored state whose actions are executed. The state is always stored while it's being entered, exited
+/// or active, a missing one means that the enum storage is out of sync with the current states.
+fn stored_state<F: FsmBackend, S>(states: &mut <F as FsmBackend>::States) -> Option<&mut S>
+    where <F as FsmBackend>::States: FsmStateTryAsRef<S>
+{
+    let state = FsmStateTryAsRef::<S>::try_as_mut(states);
+    debug_assert!(state.is_some(), "The state {} isn't stored.", type_name::<S>());
+    state
+}
+
 /// A state's entry and exit actions.
 pub trait FsmState<F: FsmBackend> where Self: Sized {
     /// Action that is executed whenever this state is being entered.
@@ -13,7 +33,7 @@ pub trait FsmState<F: FsmBackend> where Self: Sized {
     fn on_exit<'a, Q: FsmEventQueue<F>>(&mut self, context: &mut EventContext<'a, F, Q>);
 
     fn execute_on_entry<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, region: FsmRegionId) 
-        where Q: FsmEventQueue<F>, I: Inspect, <F as FsmBackend>::States: AsMut<Self>, T: FsmTimers<F>
+        where Q: FsmEventQueue<F>, I: Inspect, <F as FsmBackend>::States: FsmStateTryAsRef<Self>, T: FsmTimers<F>
     {
         let mut event_context = EventContext {
             context: &mut context.backend.context,
@@ -30,12 +50,13 @@ pub trait FsmState<F: FsmBackend> where Self: Sized {
             context.inspect.on_event(&ev);
         }
 
-        let state: &mut Self = context.backend.states.as_mut();
-        state.on_entry(&mut event_context);
+        if let Some(state) = stored_state::<F, Self>(&mut context.backend.states) {
+            state.on_entry(&mut event_context);
+        }
     }
 
     fn execute_on_exit<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, region: FsmRegionId) 
-        where Q: FsmEventQueue<F>, I: Inspect, <F as FsmBackend>::States: AsMut<Self>, T: FsmTimers<F>
+        where Q: FsmEventQueue<F>, I: Inspect, <F as FsmBackend>::States: FsmStateTryAsRef<Self>, T: FsmTimers<F>
     {
         let mut event_context = EventContext {
             context: &mut context.backend.context,
@@ -43,8 +64,9 @@ pub trait FsmState<F: FsmBackend> where Self: Sized {
             region
         };
 
-        let state: &mut Self = context.backend.states.as_mut();
-        state.on_exit(&mut event_context);
+        if let Some(state) = stored_state::<F, Self>(&mut context.backend.states) {
+            state.on_exit(&mut event_context);
+        }
 
         // inspection
         {
@@ -59,6 +81,42 @@ pub trait FsmState<F: FsmBackend> where Self: Sized {
     fn fsm_state() -> <<F as FsmBackend>::States as FsmStates<F>>::StateKind;
 }
 
+/// A state's entry and exit actions that also receive the event `E`, which triggered the transition.
+pub trait FsmStateEventActions<F: FsmBackend, E>: FsmState<F> {
+    /// Action that is executed after `on_entry`, when the state is entered by the event `E`.
+    fn on_entry_with<'a, Q: FsmEventQueue<F>>(&mut self, event: &E, context: &mut EventContext<'a, F, Q>);
+    /// Action that is executed before `on_exit`, when the state is exited by the event `E`.
+    fn on_exit_with<'a, Q: FsmEventQueue<F>>(&mut self, event: &E, context: &mut EventContext<'a, F, Q>);
+
+    fn execute_on_entry_with<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId)
+        where Q: FsmEventQueue<F>, I: Inspect, <F as FsmBackend>::States: FsmStateTryAsRef<Self>, T: FsmTimers<F>
+    {
+        let mut event_context = EventContext {
+            context: &mut context.backend.context,
+            queue: context.queue,
+            region
+        };
+
+        if let Some(state) = stored_state::<F, Self>(&mut context.backend.states) {
+            state.on_entry_with(event, &mut event_context);
+        }
+    }
+
+    fn execute_on_exit_with<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId)
+        where Q: FsmEventQueue<F>, I: Inspect, <F as FsmBackend>::States: FsmStateTryAsRef<Self>, T: FsmTimers<F>
+    {
+        let mut event_context = EventContext {
+            context: &mut context.backend.context,
+            queue: context.queue,
+            region
+        };
+
+        if let Some(state) = stored_state::<F, Self>(&mut context.backend.states) {
+            state.on_exit_with(event, &mut event_context);
+        }
+    }
+}
+
 /// Check if this transition is allowed to be entered.
 pub trait FsmTransitionGuard<F: FsmBackend, E> {
     /// Return a boolean value whether this transition is usable at the moment. The check shouln't mutate any structures.
@@ -84,20 +142,23 @@ pub trait FsmTransitionGuard<F: FsmBackend, E> {
 
 /// The transition that starts the machine, triggered using the `start()` method.
 pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
+    /// The `entered_state` replaces the initial state before its entry, if it is constructed on entry.
     fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, 
         _fsm_event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>,
         region: FsmRegionId,
-        inspect_event_ctx: &mut I)
+        inspect_event_ctx: &mut I,
+        entered_state: Option<TInitialState>)
         where
             I: Inspect,
             TInitialState: FsmState<F>,
-            <F as FsmBackend>::States: AsMut<TInitialState>,
-            <F as FsmBackend>::States: AsRef<TInitialState>,
+            <F as FsmBackend>::States: FsmStateTryAsRef<TInitialState>,
             Self: Sized,
             T: FsmTimers<F>
     {
-        let ctx = inspect_event_ctx.for_transition::<Self>();
-        ctx.on_state_enter::<TInitialState>();
+        // the entry of the state is inspected by its entry action
+        inspect_event_ctx.for_transition::<Self>();
+
+        replace_entered_state::<F, _>(&mut context.backend.states, entered_state);
         
         <TInitialState>::execute_on_entry(context, region);
         
@@ -107,7 +168,7 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
 
     /// Executed after the transition on the parent FSM (F) and triggers the first `start()` call if necessary. Subsequent
     /// dispatches are handled using the main dispatch table.
-    fn execute_on_sub_entry<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, _region: FsmRegionId, inspect_event_ctx: &mut I) 
+    fn execute_on_sub_entry<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, region: FsmRegionId, inspect_event_ctx: &mut I) 
         -> FsmDispatchResult
         where
         TInitialState: FsmBackend,
@@ -124,6 +185,7 @@ pub trait FsmTransitionFsmStart<F: FsmBackend, TInitialState> {
         if FsmCurrentState::all_stopped(states.as_ref()) {
             let mut queue_adapter = FsmEventQueueSub {
                 parent: context.queue,
+                region,
                 _parent_fsm: PhantomData::<F>::default(),
                 _sub_fsm: PhantomData::<TInitialState>::default()
             };
@@ -155,12 +217,14 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
     /// This action is executed after the first state's exit event, and just before the second event's entry action. It can mutate both states.
     fn action<'a, Q: FsmEventQueue<F>>(event: &E, context: &mut EventContext<'a, F, Q>, from: &mut TStateFrom, to: &mut TStateTo);
 
-    fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId, inspect_event_ctx: &mut I)
+    /// The `entered_state` replaces the second state after the first state's exit and before the action, if
+    /// it is constructed on entry.
+    fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId, inspect_event_ctx: &mut I, entered_state: Option<TStateTo>)
         where 
             I: Inspect,
             <F as FsmBackend>::States: FsmStateTransitionAsMut<TStateFrom, TStateTo>,
-            <F as FsmBackend>::States: AsMut<TStateFrom>,
-            <F as FsmBackend>::States: AsMut<TStateTo>,
+            <F as FsmBackend>::States: FsmStateTryAsRef<TStateFrom>,
+            <F as FsmBackend>::States: FsmStateTryAsRef<TStateTo>,
             TStateFrom: FsmState<F>,
             TStateTo: FsmState<F>, Self: Sized,
             T: FsmTimers<F>
@@ -168,6 +232,8 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
         let inspect_ctx = inspect_event_ctx.for_transition::<Self>();
 
         <TStateFrom>::execute_on_exit(context, region);
+
+        replace_entered_state::<F, _>(&mut context.backend.states, entered_state);
         
         // transition action
         {
@@ -191,7 +257,7 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
 
     /// Executed after the transition on the parent FSM (F) and triggers the first `start()` call if necessary. Subsequent
     /// dispatches are handled using the main dispatch table.
-    fn execute_on_sub_entry<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, _region: FsmRegionId, inspect_event_ctx: &mut I) 
+    fn execute_on_sub_entry<'a, 'b, 'c, 'd, Q, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, region: FsmRegionId, inspect_event_ctx: &mut I) 
         -> FsmDispatchResult
         where
             TStateTo: FsmBackend,
@@ -208,6 +274,7 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
         if FsmCurrentState::all_stopped(states.as_ref()) {
             let mut queue_adapter = FsmEventQueueSub {
                 parent: context.queue,
+                region,
                 _parent_fsm: PhantomData::<F>::default(),
                 _sub_fsm: PhantomData::<TStateTo>::default()
             };
@@ -234,6 +301,54 @@ pub trait FsmTransitionAction<F: FsmBackend, E, TStateFrom, TStateTo> {
     }
 }
 
+/// A transition's action that returns a future. The target state is entered once the future, driven by the
+/// frontend, resolves. Until then, the machine is in the transition and the events wait in the queue.
+#[cfg(all(feature="async", feature="std"))]
+pub trait FsmTransitionActionAsync<F: FsmBackend, E, TStateFrom, TStateTo>: FsmTransitionAction<F, E, TStateFrom, TStateTo> {
+    /// This action is executed after the first state's exit event. The returned future can't borrow the
+    /// context or the states.
+    fn action_async<'a, Q: FsmEventQueue<F>>(event: &E, context: &mut EventContext<'a, F, Q>, from: &mut TStateFrom, to: &mut TStateTo) -> crate::FsmAsyncAction;
+
+    /// The `transition` is the index of the transition within its region, it identifies the completion.
+    fn execute_transition_async<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId, transition: usize, inspect_event_ctx: &mut I, entered_state: Option<TStateTo>)
+        -> FsmDispatchResult
+        where
+            I: Inspect,
+            <F as FsmBackend>::States: FsmStateTransitionAsMut<TStateFrom, TStateTo>,
+            <F as FsmBackend>::States: FsmStateTryAsRef<TStateFrom>,
+            <F as FsmBackend>::States: FsmStateTryAsRef<TStateTo>,
+            TStateFrom: FsmState<F>,
+            TStateTo: FsmState<F>, Self: Sized,
+            T: FsmTimers<F>
+    {
+        let inspect_ctx = inspect_event_ctx.for_transition::<Self>();
+
+        <TStateFrom>::execute_on_exit(context, region);
+
+        replace_entered_state::<F, _>(&mut context.backend.states, entered_state);
+
+        let action = {
+            inspect_ctx.on_action::<Self>();
+
+            let mut event_context = EventContext {
+                context: &mut context.backend.context,
+                queue: context.queue,
+                region
+            };
+            let states: (&mut TStateFrom, &mut TStateTo) = context.backend.states.as_state_transition_mut();
+            Self::action_async(event, &mut event_context, states.0, states.1)
+        };
+
+        context.queue.spawn_action(crate::FsmTransitionCompleted::new(region, transition), action)?;
+        inspect_ctx.info("Waiting for the async action to complete.");
+
+        let cs = context.backend.current_states.as_mut();
+        cs[region] = FsmCurrentState::InTransition(<TStateTo>::fsm_state());
+
+        Ok(())
+    }
+}
+
 /// An internal or self action can only mutate itself.
 pub trait FsmAction<F: FsmBackend, E, State> {
     /// This action is executed as part of an internal or self transition.
@@ -242,7 +357,7 @@ pub trait FsmAction<F: FsmBackend, E, State> {
     fn should_trigger_state_actions() -> bool;
 
     fn execute_action<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId)
-        where <F as FsmBackend>::States: AsMut<State>, I: Inspect, T: FsmTimers<F>
+        where <F as FsmBackend>::States: FsmStateTryAsRef<State>, I: Inspect, T: FsmTimers<F>
     {
         let mut event_context = EventContext {
             context: &mut context.backend.context,
@@ -250,21 +365,24 @@ pub trait FsmAction<F: FsmBackend, E, State> {
             region
         };
 
-        let state: &mut State = context.backend.states.as_mut();
-
-        Self::action(event, &mut event_context, state);
+        if let Some(state) = stored_state::<F, State>(&mut context.backend.states) {
+            Self::action(event, &mut event_context, state);
+        }
     }
 
-    fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId, inspect_event_ctx: &mut I)
+    /// The `entered_state` replaces the state after its exit in a self transition, if it is constructed on entry.
+    fn execute_transition<'a, 'b, 'c, 'd, Q: FsmEventQueue<F>, I, T>(context: &'d mut DispatchContext<'a, 'b, 'c, F, Q, I, T>, event: &E, region: FsmRegionId, inspect_event_ctx: &mut I, entered_state: Option<State>)
         where I: Inspect,
             State: FsmState<F>,
-            <F as FsmBackend>::States: AsMut<State>, Self: Sized,
+            <F as FsmBackend>::States: FsmStateTryAsRef<State>, Self: Sized,
             T: FsmTimers<F>
     {
-        let ctx = inspect_event_ctx.for_transition::<Self>();
+        inspect_event_ctx.for_transition::<Self>();
 
         if Self::should_trigger_state_actions() {
             <State>::execute_on_exit(context, region);
+
+            replace_entered_state::<F, _>(&mut context.backend.states, entered_state);
         }
 
         Self::execute_action(context, event, region);
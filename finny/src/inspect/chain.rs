@@ -97,6 +97,11 @@ impl<A, B> Inspect for InspectChain<A, B>
         self.b.on_action::<S>();
     }
 
+    fn on_timer_trigger<T>(&self) {
+        self.a.on_timer_trigger::<T>();
+        self.b.on_timer_trigger::<T>();
+    }
+
     fn on_error<E>(&self, msg: &str, error: &E) where E: core::fmt::Debug {
         self.a.on_error(msg, error);
         self.b.on_error(msg, error);
@@ -0,0 +1,278 @@
+use crate::{FsmBackend, FsmBackendImpl, FsmEvent, Inspect, InspectEvent, InspectFsmEvent};
+use core::fmt::Debug;
+use core::any::{Any, type_name};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Records the transitions, guard outcomes, actions, state entries and exits and the timers that ran,
+/// shared between the clones. With the `meta` feature, the records are compared against the description
+/// of the machine to report the coverage.
+#[derive(Clone, Default)]
+pub struct InspectCoverage {
+    data: Arc<Mutex<CoverageData>>
+}
+
+/// How many times each of the parts ran, by the fully qualified name of its type.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageData {
+    pub transitions: HashMap<String, usize>,
+    pub guards_passed: HashMap<String, usize>,
+    pub guards_rejected: HashMap<String, usize>,
+    pub actions: HashMap<String, usize>,
+    pub state_entries: HashMap<String, usize>,
+    pub state_exits: HashMap<String, usize>,
+    pub timers: HashMap<String, usize>
+}
+
+impl InspectCoverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A snapshot of the records.
+    pub fn data(&self) -> CoverageData {
+        self.data.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        *self.data.lock().unwrap() = CoverageData::default();
+    }
+
+    fn record<N: FnOnce(&mut CoverageData) -> &mut HashMap<String, usize>>(&self, name: &str, map: N) {
+        let mut data = self.data.lock().unwrap();
+        *map(&mut data).entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+impl Inspect for InspectCoverage {
+    fn new_event<F: FsmBackend>(&self, _event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>, _fsm: &FsmBackendImpl<F>) -> Self {
+        self.clone()
+    }
+
+    fn for_transition<T>(&self) -> Self {
+        self.record(type_name::<T>(), |d| &mut d.transitions);
+        self.clone()
+    }
+
+    fn for_sub_machine<FSub: FsmBackend>(&self) -> Self {
+        self.clone()
+    }
+
+    fn for_timer<F>(&self, _timer_id: <F as FsmBackend>::Timers) -> Self where F: FsmBackend {
+        self.clone()
+    }
+
+    fn on_guard<T>(&self, guard_result: bool) {
+        match guard_result {
+            true => self.record(type_name::<T>(), |d| &mut d.guards_passed),
+            false => self.record(type_name::<T>(), |d| &mut d.guards_rejected)
+        }
+    }
+
+    fn on_state_enter<S>(&self) {
+        self.record(type_name::<S>(), |d| &mut d.state_entries);
+    }
+
+    fn on_state_exit<S>(&self) {
+        self.record(type_name::<S>(), |d| &mut d.state_exits);
+    }
+
+    fn on_action<S>(&self) {
+        self.record(type_name::<S>(), |d| &mut d.actions);
+    }
+
+    fn on_timer_trigger<T>(&self) {
+        self.record(type_name::<T>(), |d| &mut d.timers);
+    }
+
+    fn event_done<F: FsmBackend>(self, _fsm: &FsmBackendImpl<F>) {
+
+    }
+
+    fn on_error<E>(&self, _msg: &str, _error: &E) where E: core::fmt::Debug {
+
+    }
+
+    fn info(&self, _msg: &str) {
+
+    }
+}
+
+impl InspectEvent for InspectCoverage {
+    fn on_event<S: Any + Debug + Clone>(&self, _event: &InspectFsmEvent<S>) {
+
+    }
+}
+
+#[cfg(feature="meta")]
+pub use self::report::*;
+
+#[cfg(feature="meta")]
+mod report {
+    use std::fmt;
+
+    use finny_meta::{FinnyFsm, FinnyStateKind, diagrams::{short_type_name, sorted_regions}};
+
+    use super::InspectCoverage;
+    use crate::FsmMeta;
+
+    /// The parts of the machine that never ran, sorted by their names.
+    #[derive(Clone, Debug)]
+    pub struct CoverageReport {
+        pub fsm_id: String,
+        pub transitions: usize,
+        pub uncovered_transitions: Vec<String>,
+        /// Each of the guards has two outcomes.
+        pub guard_outcomes: usize,
+        pub uncovered_guard_outcomes: Vec<(String, bool)>,
+        pub actions: usize,
+        pub uncovered_actions: Vec<String>,
+        pub states: usize,
+        pub unvisited_states: Vec<String>,
+        pub timers: usize,
+        pub untriggered_timers: Vec<String>
+    }
+
+    fn percentage(total: usize, uncovered: usize) -> f64 {
+        match total {
+            0 => 100.0,
+            total => (total - uncovered) as f64 * 100.0 / total as f64
+        }
+    }
+
+    impl CoverageReport {
+        pub fn transition_coverage(&self) -> f64 {
+            percentage(self.transitions, self.uncovered_transitions.len())
+        }
+
+        pub fn guard_coverage(&self) -> f64 {
+            percentage(self.guard_outcomes, self.uncovered_guard_outcomes.len())
+        }
+
+        pub fn action_coverage(&self) -> f64 {
+            percentage(self.actions, self.uncovered_actions.len())
+        }
+
+        pub fn state_coverage(&self) -> f64 {
+            percentage(self.states, self.unvisited_states.len())
+        }
+
+        pub fn timer_coverage(&self) -> f64 {
+            percentage(self.timers, self.untriggered_timers.len())
+        }
+
+        /// Everything ran at least once, with both outcomes of the guards.
+        pub fn is_complete(&self) -> bool {
+            self.uncovered_transitions.is_empty() && self.uncovered_guard_outcomes.is_empty() &&
+            self.uncovered_actions.is_empty() && self.unvisited_states.is_empty() && self.untriggered_timers.is_empty()
+        }
+    }
+
+    impl fmt::Display for CoverageReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            writeln!(f, "Coverage of {}", self.fsm_id)?;
+
+            let section = |f: &mut fmt::Formatter<'_>, name: &str, total: usize, uncovered: Vec<String>| -> fmt::Result {
+                writeln!(f, "{}: {}/{} ({:.1}%)", name, total - uncovered.len(), total, percentage(total, uncovered.len()))?;
+                if !uncovered.is_empty() {
+                    writeln!(f, "  not covered: {}", uncovered.join(", "))?;
+                }
+                Ok(())
+            };
+
+            section(f, "Transitions", self.transitions, self.uncovered_transitions.clone())?;
+            section(f, "Guard outcomes", self.guard_outcomes, self.uncovered_guard_outcomes.iter().map(|(g, o)| format!("{} ({})", g, o)).collect())?;
+            section(f, "Actions", self.actions, self.uncovered_actions.clone())?;
+            section(f, "States", self.states, self.unvisited_states.clone())?;
+            section(f, "Timers", self.timers, self.untriggered_timers.clone())
+        }
+    }
+
+    impl InspectCoverage {
+        /// Compare the records against the description of the machine. The submachines are reported
+        /// separately, with their own descriptions. Without the names of the types, as in a description
+        /// that was read from its JSON, the records are matched by the names without their modules.
+        pub fn report(&self, fsm: &FinnyFsm) -> CoverageReport {
+            let data = self.data();
+            let ran = |map: &std::collections::HashMap<String, usize>, id: &str| match fsm.type_names.get(id) {
+                Some(type_name) => map.contains_key(type_name),
+                None => map.keys().any(|name| short_type_name(name) == id)
+            };
+
+            let mut report = CoverageReport {
+                fsm_id: fsm.fsm_id.clone(),
+                transitions: 0,
+                uncovered_transitions: vec![],
+                guard_outcomes: 0,
+                uncovered_guard_outcomes: vec![],
+                actions: 0,
+                uncovered_actions: vec![],
+                states: 0,
+                unvisited_states: vec![],
+                timers: 0,
+                untriggered_timers: vec![]
+            };
+
+            for (region, states) in sorted_regions(fsm) {
+                for transition in &region.transitions {
+                    report.transitions += 1;
+                    if !ran(&data.transitions, &transition.transition_id) {
+                        report.uncovered_transitions.push(transition.transition_id.clone());
+                    }
+
+                    // the guards and the actions are recorded by their transition
+                    if let Some(ref guard) = transition.guard {
+                        report.guard_outcomes += 2;
+                        if !ran(&data.guards_passed, &transition.transition_id) {
+                            report.uncovered_guard_outcomes.push((guard.clone(), true));
+                        }
+                        if !ran(&data.guards_rejected, &transition.transition_id) {
+                            report.uncovered_guard_outcomes.push((guard.clone(), false));
+                        }
+                    }
+
+                    if let Some(ref action) = transition.action {
+                        report.actions += 1;
+                        if !ran(&data.actions, &transition.transition_id) {
+                            report.uncovered_actions.push(action.clone());
+                        }
+                    }
+                }
+
+                for (state_id, state) in states {
+                    match state {
+                        FinnyStateKind::State(_) | FinnyStateKind::SubMachine(_) => {
+                            report.states += 1;
+                            if !ran(&data.state_entries, state_id) {
+                                report.unvisited_states.push(state_id.clone());
+                            }
+                        },
+                        _ => ()
+                    }
+
+                    if let FinnyStateKind::State(state) = state {
+                        for timer in &state.timers {
+                            report.timers += 1;
+                            if !ran(&data.timers, &timer.timer_id) {
+                                report.untriggered_timers.push(timer.timer_id.clone());
+                            }
+                        }
+                    }
+                }
+            }
+
+            report.uncovered_transitions.sort();
+            report.uncovered_guard_outcomes.sort();
+            report.uncovered_actions.sort();
+            report.unvisited_states.sort();
+            report.untriggered_timers.sort();
+
+            report
+        }
+
+        /// The coverage of the machine, using its embedded description.
+        pub fn report_for<F: FsmMeta>(&self) -> CoverageReport {
+            self.report(&F::fsm_meta())
+        }
+    }
+}
@@ -54,6 +54,10 @@ impl<TI> Inspect for EventInspector<TI>
         
     }
 
+    fn on_timer_trigger<T>(&self) {
+        
+    }
+
     fn event_done<F: FsmBackend>(self, fsm: &FsmBackendImpl<F>) {
         
     }
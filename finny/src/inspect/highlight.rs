@@ -0,0 +1,102 @@
+use crate::{FsmBackend, FsmBackendImpl, FsmEvent, Inspect, InspectEvent, InspectFsmEvent};
+use core::fmt::Debug;
+use core::any::Any;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use finny_meta::diagrams::short_type_name;
+
+/// Records the most recently executed transitions, shared between the clones. Used to color the
+/// transitions when drawing the live machine with `render_diagram`.
+#[derive(Clone)]
+pub struct InspectHighlight {
+    recent: Arc<Mutex<VecDeque<&'static str>>>,
+    capacity: usize
+}
+
+impl InspectHighlight {
+    /// Keeps up to `capacity` of the most recent transitions.
+    pub fn new(capacity: usize) -> Self {
+        InspectHighlight {
+            recent: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity
+        }
+    }
+
+    /// The names of the transition types, the most recent one first.
+    pub fn recent_transitions(&self) -> Vec<String> {
+        let recent = self.recent.lock().unwrap();
+        recent.iter().map(|t| short_type_name(t).to_string()).collect()
+    }
+
+    pub fn clear(&self) {
+        self.recent.lock().unwrap().clear();
+    }
+}
+
+impl Default for InspectHighlight {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl Inspect for InspectHighlight {
+    fn new_event<F: FsmBackend>(&self, _event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>, _fsm: &FsmBackendImpl<F>) -> Self {
+        self.clone()
+    }
+
+    fn for_transition<T>(&self) -> Self {
+        if self.capacity > 0 {
+            let mut recent = self.recent.lock().unwrap();
+            recent.truncate(self.capacity - 1);
+            recent.push_front(core::any::type_name::<T>());
+        }
+        self.clone()
+    }
+
+    fn for_sub_machine<FSub: FsmBackend>(&self) -> Self {
+        self.clone()
+    }
+
+    fn for_timer<F>(&self, _timer_id: <F as FsmBackend>::Timers) -> Self where F: FsmBackend {
+        self.clone()
+    }
+
+    fn on_guard<T>(&self, _guard_result: bool) {
+
+    }
+
+    fn on_state_enter<S>(&self) {
+
+    }
+
+    fn on_state_exit<S>(&self) {
+
+    }
+
+    fn on_action<S>(&self) {
+
+    }
+
+    fn on_timer_trigger<T>(&self) {
+
+    }
+
+    fn event_done<F: FsmBackend>(self, _fsm: &FsmBackendImpl<F>) {
+
+    }
+
+    fn on_error<E>(&self, _msg: &str, _error: &E) where E: core::fmt::Debug {
+
+    }
+
+    fn info(&self, _msg: &str) {
+
+    }
+}
+
+impl InspectEvent for InspectHighlight {
+    fn on_event<S: Any + Debug + Clone>(&self, _event: &InspectFsmEvent<S>) {
+
+    }
+}
@@ -1,6 +1,10 @@
 pub mod null;
 pub mod chain;
 pub mod events;
+#[cfg(feature="meta")]
+pub mod highlight;
+#[cfg(feature="std")]
+pub mod coverage;
 
 
 #[cfg(feature="inspect_slog")]
@@ -44,6 +44,10 @@ impl Inspect for InspectNull {
         
     }
 
+    fn on_timer_trigger<T>(&self) {
+        
+    }
+
     fn event_done<F: FsmBackend>(self, fsm: &FsmBackendImpl<F>) {
         
     }
@@ -83,6 +83,11 @@ impl Inspect for InspectSlog
         info!(self.logger, "Executing {action}", action = action);
     }
 
+    fn on_timer_trigger<T>(&self) {
+        let timer = type_name::<T>();
+        info!(self.logger, "Triggered {timer}", timer = timer);
+    }
+
     fn event_done<F: FsmBackend>(self, fsm: &FsmBackendImpl<F>) {
         let states = format!("{:?}", fsm.get_current_states());
         info!(self.logger, "Dispatch done"; "stop_state" => states);
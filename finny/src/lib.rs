@@ -98,6 +98,14 @@ pub mod bundled {
     pub mod derive_more {
         pub use crate::derive_more::From;
     }
+
+    /// Serde crate for deriving the serialization of the states, with the `serde` feature.
+    #[cfg(feature="serde")]
+    pub use ::serde;
+
+    /// The description of the machines, embedded with the `meta` feature.
+    #[cfg(feature="meta")]
+    pub use ::finny_meta;
 }
 
 mod lib {
@@ -16,6 +16,15 @@ enum StdTimer {
     Interval { started_at: Instant, interval: Duration }
 }
 
+impl StdTimer {
+    fn time_left(&self) -> Duration {
+        match self {
+            StdTimer::Timeout { started_at, duration } => duration.saturating_sub(started_at.elapsed()),
+            StdTimer::Interval { started_at, interval } => interval.saturating_sub(started_at.elapsed())
+        }
+    }
+}
+
 impl<F> TimersStd<F>
     where F: FsmBackend
 {
@@ -86,4 +95,12 @@ impl<F> FsmTimers<F> for TimersStd<F>
 
         None
     }
+
+    fn get_next_deadline(&mut self) -> Option<Duration> {
+        if self.pending_intervals.is_some() {
+            return Some(Duration::from_secs(0));
+        }
+
+        self.timers.iter().map(|(_, timer)| timer.time_left()).min()
+    }
 }
\ No newline at end of file
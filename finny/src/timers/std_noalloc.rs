@@ -17,6 +17,15 @@ pub enum StdTimer {
     Interval { started_at: Instant, interval: Duration }
 }
 
+impl StdTimer {
+    fn time_left(&self) -> Duration {
+        match self {
+            StdTimer::Timeout { started_at, duration } => duration.saturating_sub(started_at.elapsed()),
+            StdTimer::Interval { started_at, interval } => interval.saturating_sub(started_at.elapsed())
+        }
+    }
+}
+
 impl<F, S> TimersStdNoAlloc<F, S>
     where F: FsmBackend,
     S: TimersStorage<<F as FsmBackend>::Timers, StdTimer>,
@@ -95,4 +104,19 @@ impl<F, S> FsmTimers<F> for TimersStdNoAlloc<F, S>
         
         None
     }
+
+    fn get_next_deadline(&mut self) -> Option<Duration> {
+        if self.pending_intervals.is_some() {
+            return Some(Duration::from_secs(0));
+        }
+
+        let mut next: Option<Duration> = None;
+        for timer_id in <F as FsmBackend>::Timers::iter() {
+            if let Some(timer) = self.timers.get_timer_storage_mut(&timer_id) {
+                let left = timer.time_left();
+                next = Some(next.map_or(left, |n| n.min(left)));
+            }
+        }
+        next
+    }
 }
\ No newline at end of file
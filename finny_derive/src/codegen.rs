@@ -2,9 +2,9 @@ use std::collections::HashSet;
 
 use proc_macro2::{Span, TokenStream};
 use quote::{TokenStreamExt, quote};
-use crate::{codegen_meta::generate_fsm_meta, fsm::FsmTypes, parse::{FsmState, FsmStateAction, FsmStateKind}, utils::{remap_closure_inputs, to_field_name, tokens_to_string}};
+use crate::{codegen_meta::generate_fsm_meta, fsm::FsmTypes, parse::{FsmHistory, FsmState, FsmStateAction, FsmStateKind, FsmSubMachineOptions}, utils::{remap_closure_inputs, to_field_name, tokens_to_string}};
 
-use crate::{parse::{FsmFnInput, FsmStateTransition, FsmTransitionState, FsmTransitionType}, utils::ty_append};
+use crate::{parse::{EventGuardAction, FsmFnInput, FsmTransitionEvent, FsmStateTransition, FsmTransitionState, FsmTransitionType}, utils::ty_append};
 
 pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStream) -> syn::Result<TokenStream> {
     let fsm_ty = &fsm.base.fsm_ty;
@@ -18,18 +18,25 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
     let timers_enum_iter_ty = fsm_types.get_fsm_timers_iter_ty();
     let timers_storage_ty = fsm_types.get_fsm_timers_storage_ty();
     let event_enum_ty = fsm_types.get_fsm_events_ty();
+    let event_kind_enum_ty = fsm_types.get_fsm_event_kind_ty();
 
     let region_count = fsm.fsm.regions.len();
 
     let (fsm_generics_impl, fsm_generics_type, fsm_generics_where) = fsm.base.fsm_generics.split_for_impl();
 
+    let serde_derive = generate_serde_derive(fsm, None);
+
     let states_store = {
 
         let mut code_fields = TokenStream::new();
         let mut new_state_fields = TokenStream::new();
         let mut state_variants = TokenStream::new();
+        let mut state_all = vec![];
+        let mut state_names = TokenStream::new();
         let mut state_accessors = TokenStream::new();
-
+        let mut final_states = TokenStream::new();
+        let mut restore_timers = TokenStream::new();
+        let mut region_variants = vec![TokenStream::new(); region_count];
 
         for (i, (_, state)) in fsm.fsm.states.iter().enumerate() {
             let name = &state.state_storage_field;
@@ -42,6 +49,11 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                 let timer_field = timer.get_field(&fsm.base);
 
                 code_fields.append_all(quote! { #timer_field: #timer_ty #fsm_generics_type, });
+                restore_timers.append_all(quote! {
+                    if let Some(instance) = finny::FsmTimer::< #fsm_ty #fsm_generics_type, #ty >::get_instance(&self. #timer_field) {
+                        timers.create(instance.id.clone(), &instance.settings.to_timer_settings())?;
+                    }
+                });
                 new_state_fields.append_all(quote! { #timer_field: #timer_ty::default(), });
 
                 state_accessors.append_all(quote! {
@@ -59,11 +71,47 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                 });
             }
 
-            code_fields.append_all(quote! { #name: #ty, });
             state_variants.append_all(quote!{ #ty_name, });
+            let ty_name_str = crate::utils::tokens_to_string(ty_name);
+            state_all.push((ty_name_str.clone(), quote!{ #states_enum_ty :: #ty_name, }));
+            state_names.append_all(quote!{ #states_enum_ty :: #ty_name => #ty_name_str, });
+            if state.is_final {
+                final_states.append_all(quote! { | #states_enum_ty :: #ty_name });
+            }
+
+            // only the active state of the region is stored
+            if let Some(region_id) = get_state_slot(fsm, state) {
+                let region_field = get_region_field(region_id);
+                let region_enum_ty = get_region_enum_ty(fsm, region_id);
+
+                region_variants[region_id].append_all(quote! { #ty_name(#ty), });
+
+                // the state isn't always stored, it's only available through the fallible accessors
+                state_accessors.append_all(quote! {
+                    impl #fsm_generics_impl finny::FsmStateTryAsRef<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                        fn try_as_ref(&self) -> Option<& #ty> {
+                            match self. #region_field {
+                                #region_enum_ty :: #ty_name(ref state) => Some(state),
+                                _ => None
+                            }
+                        }
+
+                        fn try_as_mut(&mut self) -> Option<&mut #ty> {
+                            match self. #region_field {
+                                #region_enum_ty :: #ty_name(ref mut state) => Some(state),
+                                _ => None
+                            }
+                        }
+                    }
+                });
+
+                continue;
+            }
+
+            code_fields.append_all(quote! { #name: #ty, });
 
             let new_state_field = match state.kind {
-                FsmStateKind::Normal => {
+                FsmStateKind::Normal | FsmStateKind::Choice | FsmStateKind::ExitPoint => {
                     quote! {
                         #name: < #ty as finny::FsmStateFactory< #fsm_ty #fsm_generics_type > >::new_state(context)?,
                     }
@@ -88,6 +136,17 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                         }
                     };
 
+                    restore_timers.append_all(quote! {
+                        {
+                            let mut timers = finny::FsmTimersSub {
+                                parent: &mut *timers,
+                                _parent_fsm: core::marker::PhantomData::< #fsm_ty #fsm_generics_type >::default(),
+                                _sub_fsm: core::marker::PhantomData::< #ty >::default()
+                            };
+                            self. #name .states.restore_timers(&mut timers)?;
+                        }
+                    });
+
                     quote! {
                         #name: {
                             use finny::{FsmFactory};
@@ -116,6 +175,146 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                         &mut self. #name
                     }
                 }
+
+                impl #fsm_generics_impl finny::FsmStateTryAsRef<#ty> for #states_store_ty #fsm_generics_type #fsm_generics_where {
+                    fn try_as_ref(&self) -> Option<& #ty> {
+                        Some(&self. #name)
+                    }
+
+                    fn try_as_mut(&mut self) -> Option<&mut #ty> {
+                        Some(&mut self. #name)
+                    }
+                }
+            });
+        }
+
+        // the active states, including the ones of the nested submachines
+        let mut active_states = TokenStream::new();
+        {
+            let mut generics = fsm.base.fsm_generics.clone();
+            generics.params.push(syn::parse_quote!(TFinnyState));
+            {
+                let where_clause = generics.make_where_clause();
+                where_clause.predicates.push(syn::parse_quote!(TFinnyState: finny::FsmStateKindOf<#fsm_ty #fsm_generics_type>));
+                where_clause.predicates.push(syn::parse_quote!(#states_store_ty #fsm_generics_type: finny::FsmStateTryAsRef<TFinnyState>));
+            }
+            let (state_generics_impl, _, state_generics_where) = generics.split_for_impl();
+
+            active_states.append_all(quote! {
+                impl #state_generics_impl finny::FsmActiveState<TFinnyState, ()> for #fsm_ty #fsm_generics_type #state_generics_where {
+                    fn is_active(backend: &finny::FsmBackendImpl<Self>) -> bool {
+                        backend.current_states.iter().any(|s| *s == finny::FsmCurrentState::State(<TFinnyState as finny::FsmStateKindOf<Self>>::STATE_KIND))
+                    }
+
+                    fn get_active_state(backend: &finny::FsmBackendImpl<Self>) -> Option<&TFinnyState> {
+                        if <Self as finny::FsmActiveState<TFinnyState, ()>>::is_active(backend) {
+                            finny::FsmStateTryAsRef::<TFinnyState>::try_as_ref(&backend.states)
+                        } else {
+                            None
+                        }
+                    }
+                }
+            });
+        }
+
+        for state in fsm.fsm.states.values() {
+            let state_types = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+            let ty = state_types.get_fsm_ty();
+            let variant = state_types.get_fsm_no_generics_ty();
+
+            active_states.append_all(quote! {
+                impl #fsm_generics_impl finny::FsmStateKindOf<#fsm_ty #fsm_generics_type> for #ty #fsm_generics_where {
+                    const STATE_KIND: #states_enum_ty = #states_enum_ty :: #variant;
+                }
+            });
+
+            if let FsmStateKind::SubMachine(_) = state.kind {
+                let mut generics = fsm.base.fsm_generics.clone();
+                generics.params.push(syn::parse_quote!(TFinnyState));
+                generics.params.push(syn::parse_quote!(TFinnyPath));
+                generics.make_where_clause().predicates.push(syn::parse_quote!(#ty: finny::FsmActiveState<TFinnyState, TFinnyPath>));
+                let (sub_generics_impl, _, sub_generics_where) = generics.split_for_impl();
+
+                active_states.append_all(quote! {
+                    impl #sub_generics_impl finny::FsmActiveState<TFinnyState, (#ty, TFinnyPath)> for #fsm_ty #fsm_generics_type #sub_generics_where {
+                        fn is_active(backend: &finny::FsmBackendImpl<Self>) -> bool {
+                            <Self as finny::FsmActiveState<#ty, ()>>::is_active(backend) && {
+                                let sub: & #ty = backend.states.as_ref();
+                                <#ty as finny::FsmActiveState<TFinnyState, TFinnyPath>>::is_active(sub)
+                            }
+                        }
+
+                        fn get_active_state(backend: &finny::FsmBackendImpl<Self>) -> Option<&TFinnyState> {
+                            if <Self as finny::FsmActiveState<#ty, ()>>::is_active(backend) {
+                                let sub: & #ty = backend.states.as_ref();
+                                <#ty as finny::FsmActiveState<TFinnyState, TFinnyPath>>::get_active_state(sub)
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
+        // the path of the active states, descends into the active submachines
+        let state_path = {
+            let fsm_name = tokens_to_string(fsm_types.get_fsm_no_generics_ty());
+
+            let mut sub_paths = TokenStream::new();
+            for state in fsm.fsm.states.values() {
+                if let FsmStateKind::SubMachine(_) = state.kind {
+                    let state_types = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+                    let ty = state_types.get_fsm_ty();
+                    let variant = state_types.get_fsm_no_generics_ty();
+
+                    sub_paths.append_all(quote! {
+                        if *state == #states_enum_ty :: #variant {
+                            let sub: & #ty = backend.states.as_ref();
+                            <#ty as finny::FsmCurrentStatePath>::write_state_path(sub, path, depth);
+                        }
+                    });
+                }
+            }
+
+            // without submachines, the arguments aren't used
+            let args = if sub_paths.is_empty() {
+                quote! { _backend: &finny::FsmBackendImpl<Self>, _state: & #states_enum_ty, _path: &mut finny::FsmStatePath, _depth: usize }
+            } else {
+                quote! { backend: &finny::FsmBackendImpl<Self>, state: & #states_enum_ty, path: &mut finny::FsmStatePath, depth: usize }
+            };
+
+            quote! {
+                impl #fsm_generics_impl finny::FsmCurrentStatePath for #fsm_ty #fsm_generics_type #fsm_generics_where {
+                    fn fsm_name() -> &'static str {
+                        #fsm_name
+                    }
+
+                    fn write_sub_state_path(#args) {
+                        #sub_paths
+                    }
+                }
+            }
+        };
+
+        let mut regions_store = TokenStream::new();
+        for (region_id, variants) in region_variants.iter().enumerate() {
+            if !fsm.fsm.codegen_options.enum_storage { break; }
+
+            let region_field = get_region_field(region_id);
+            let region_enum_ty = get_region_enum_ty(fsm, region_id);
+
+            code_fields.append_all(quote! { #region_field: #region_enum_ty #fsm_generics_type, });
+            new_state_fields.append_all(quote! { #region_field: #region_enum_ty::Empty(core::marker::PhantomData::default()), });
+
+            regions_store.append_all(quote! {
+                /// The stored state of the region. Emptied when the machine is stopped, the start builds the initial state again.
+                #serde_derive
+                pub enum #region_enum_ty #fsm_generics_type #fsm_generics_where {
+                    /// The region wasn't entered yet, or the machine was stopped.
+                    Empty(core::marker::PhantomData< #fsm_ty #fsm_generics_type >),
+                    #variants
+                }
             });
         }
 
@@ -138,6 +337,8 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
 
                                 let key = (state_from_ty.clone(), state_to_ty.clone());
                                 if transitions_seen.contains(&key) { continue; }
+                                // the stored states are replaced by the transition
+                                if get_state_slot(fsm, state_from).is_some() || get_state_slot(fsm, state_to).is_some() { continue; }
                                 transitions_seen.insert(key);
 
                                 transition_states.append_all(quote! {
@@ -157,8 +358,38 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
             }
         }
 
+        let restore_timers = if restore_timers.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                fn restore_timers<T: finny::FsmTimers< #fsm_ty #fsm_generics_type >>(&self, timers: &mut T) -> finny::FsmResult<()> {
+                    #restore_timers
+                    Ok(())
+                }
+            }
+        };
+
+        // the states are stored in a hash map, they are listed by their names for a stable order
+        state_all.sort_by(|a, b| a.0.cmp(&b.0));
+        let state_count = state_all.len();
+        let state_all: TokenStream = state_all.into_iter().map(|(_, t)| t).collect();
+
+        let is_final_state = if final_states.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                fn is_final_state(state: &Self::StateKind) -> bool {
+                    match state {
+                        #final_states => true,
+                        _ => false
+                    }
+                }
+            }
+        };
+
         quote! {
             /// States storage struct for the state machine.
+            #serde_derive
             pub struct #states_store_ty #fsm_generics_type #fsm_generics_where {
                 #code_fields
                 _fsm: core::marker::PhantomData< #fsm_ty #fsm_generics_type >
@@ -175,18 +406,57 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
             }
             
             #[derive(Copy, Clone, Debug, PartialEq)]
+            #serde_derive
             pub enum #states_enum_ty {
                 #state_variants
             }
 
+            impl #states_enum_ty {
+                /// The number of the machine's states.
+                pub const STATE_COUNT: usize = #state_count;
+                /// All the states, sorted by their names.
+                pub const ALL: [Self; #state_count] = [ #state_all ];
+
+                pub const fn name(&self) -> &'static str {
+                    match *self {
+                        #state_names
+                    }
+                }
+            }
+
+            impl finny::FsmStaticName for #states_enum_ty {
+                fn static_name(&self) -> &'static str {
+                    self.name()
+                }
+            }
+
+            impl finny::AllVariants for #states_enum_ty {
+                type Iter = core::iter::Copied<core::slice::Iter<'static, Self>>;
+
+                fn iter() -> Self::Iter {
+                    let all: &'static [Self] = &Self::ALL;
+                    all.iter().copied()
+                }
+            }
+
             impl #fsm_generics_impl finny::FsmStates< #fsm_ty #fsm_generics_type > for #states_store_ty #fsm_generics_type #fsm_generics_where {
                 type StateKind = #states_enum_ty;
                 type CurrentState = [finny::FsmCurrentState<Self::StateKind>; #region_count];
+
+                #is_final_state
+
+                #restore_timers
             }
 
+            #regions_store
+
             #state_accessors
 
             #transition_states
+
+            #active_states
+
+            #state_path
         }
     };
     
@@ -195,7 +465,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
 
         let submachines: Vec<_> = fsm.fsm.states.iter().filter_map(|(_, state)| {
             match &state.kind {
-                FsmStateKind::Normal => None,
+                FsmStateKind::Normal | FsmStateKind::Choice | FsmStateKind::ExitPoint => None,
                 FsmStateKind::SubMachine(ref sub) => {
                     Some((sub, state))
                 }
@@ -204,13 +474,21 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
 
         let mut variants = TokenStream::new();
         let mut as_ref_str = TokenStream::new();
-        let mut i = 0;
+        let mut kind_variants = TokenStream::new();
+        let mut kind_all = vec![];
+        let mut kinds = TokenStream::new();
+        let mut kind_names = TokenStream::new();
+        let mut i = 0usize;
 
         for (ty, _ev) in  fsm.fsm.events.iter() {
             let ty_str = crate::utils::tokens_to_string(ty);
 
             variants.append_all(quote! { #ty ( #ty ),  });            
             as_ref_str.append_all(quote! { #event_enum_ty:: #ty(_) => #ty_str, });
+            kind_variants.append_all(quote! { #ty, });
+            kind_all.push((ty_str.clone(), quote! { #event_kind_enum_ty :: #ty, }));
+            kinds.append_all(quote! { #event_enum_ty:: #ty(_) => #event_kind_enum_ty :: #ty, });
+            kind_names.append_all(quote! { #event_kind_enum_ty :: #ty => #ty_str, });
             i += 1;
         }
 
@@ -227,9 +505,16 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
             as_ref_str.append_all(quote! {
                 #event_enum_ty :: #sub_fsm_ty(_) => #sub_fsm_event_ty_str ,
             });
+            kind_variants.append_all(quote! { #sub_fsm_ty, });
+            kind_all.push((sub_fsm_event_ty_str.clone(), quote! { #event_kind_enum_ty :: #sub_fsm_ty, }));
+            kinds.append_all(quote! { #event_enum_ty :: #sub_fsm_ty(_) => #event_kind_enum_ty :: #sub_fsm_ty, });
+            kind_names.append_all(quote! { #event_kind_enum_ty :: #sub_fsm_ty => #sub_fsm_event_ty_str, });
             i += 1;
         }
 
+        kind_all.sort_by(|a, b| a.0.cmp(&b.0));
+        let kind_all: TokenStream = kind_all.into_iter().map(|(_, t)| t).collect();
+
         let mut derives = TokenStream::new();
         if fsm.fsm.codegen_options.event_debug {
             derives.append_all(quote! {
@@ -265,6 +550,55 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     #as_ref_str
                 }
             }
+
+            impl finny::FsmStaticName for #event_enum_ty {
+                fn static_name(&self) -> &'static str {
+                    #as_ref_str
+                }
+            }
+
+            impl #event_enum_ty {
+                /// The number of the event kinds, including the events of the submachines.
+                pub const EVENT_COUNT: usize = #i;
+
+                pub fn kind(&self) -> #event_kind_enum_ty {
+                    match *self {
+                        #kinds
+                    }
+                }
+            }
+
+            /// The kinds of the machine's events, without their data.
+            #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+            pub enum #event_kind_enum_ty {
+                #kind_variants
+            }
+
+            impl #event_kind_enum_ty {
+                /// All the event kinds, sorted by their names.
+                pub const ALL: [Self; #i] = [ #kind_all ];
+
+                pub const fn name(&self) -> &'static str {
+                    match *self {
+                        #kind_names
+                    }
+                }
+            }
+
+            impl finny::FsmStaticName for #event_kind_enum_ty {
+                fn static_name(&self) -> &'static str {
+                    self.name()
+                }
+            }
+
+            impl finny::AllVariants for #event_kind_enum_ty {
+                type Iter = core::iter::Copied<core::slice::Iter<'static, Self>>;
+
+                fn iter() -> Self::Iter {
+                    let all: &'static [Self] = &Self::ALL;
+                    all.iter().copied()
+                }
+            }
         };
 
         evs
@@ -272,7 +606,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
     
     let transition_types = {
         let mut t = TokenStream::new();
-        
+
         for region in &fsm.fsm.regions {
             for transition in &region.transitions {
 
@@ -339,6 +673,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                         };
 
                         let state_ty = &state.ty;
+
                         q.append_all(quote! {
                             impl #fsm_generics_impl finny::FsmAction<#fsm_ty #fsm_generics_type, #event_ty, #state_ty > for #ty #fsm_generics_where {
                                 fn action<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, state: &mut #state_ty)
@@ -358,7 +693,10 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     FsmTransitionType::StateTransition(s @ FsmStateTransition { state_from: FsmTransitionState::None, .. }) => {
                         let initial_state_ty = &s.state_to.get_fsm_state()?.ty;
 
-                        transition_doc.push_str(" Start transition.");
+                        match &s.event {
+                            FsmTransitionEvent::EntryPoint(ty_entry) => transition_doc.push_str(&format!(" Transition through the entry point [{}].", tokens_to_string(ty_entry))),
+                            _ => transition_doc.push_str(" Start transition.")
+                        }
 
                         q.append_all(quote! {
                             impl #fsm_generics_impl finny::FsmTransitionFsmStart<#fsm_ty #fsm_generics_type, #initial_state_ty > for #ty #fsm_generics_where {
@@ -371,19 +709,24 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     // normal state transition
                     FsmTransitionType::StateTransition(s) => {
 
-                        let event_ty = &s.event.get_event()?.ty;
+                        let event_ty = s.event.get_event_ty()?;
                         let state_from = s.state_from.get_fsm_state()?;
                         let state_to = s.state_to.get_fsm_state()?;
 
-                        transition_doc.push_str(&format!(" Transition, from state [{}] to state [{}] upon the event [{}].",
-                            tokens_to_string(&state_from.ty),
-                            tokens_to_string(&state_to.ty),
-                            tokens_to_string(&event_ty)
-                        ));
+                        if s.event.is_completion() {
+                            transition_doc.push_str(&format!(" Completion transition, from state [{}] to state [{}].",
+                                tokens_to_string(&state_from.ty),
+                                tokens_to_string(&state_to.ty)
+                            ));
+                        } else {
+                            transition_doc.push_str(&format!(" Transition, from state [{}] to state [{}] upon the event [{}].",
+                                tokens_to_string(&state_from.ty),
+                                tokens_to_string(&state_to.ty),
+                                tokens_to_string(&event_ty)
+                            ));
+                        }
 
                         if let Some(ref guard) = s.action.guard {
-                            let event_ty = &s.event.get_event()?.ty;
-
                             transition_doc.push_str(" Guarded.");
 
                             let remap = remap_closure_inputs(&guard.inputs, vec![
@@ -407,25 +750,49 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                             q.append_all(g);
                         }
 
-                        let action_body = if let Some(ref action) = s.action.action {
-                            transition_doc.push_str(" Executes an action.");
+                        let state_from_ty = &state_from.ty;
+                        let state_to_ty = &state_to.ty;
 
-                            let remap = remap_closure_inputs(&action.inputs, vec![
-                                quote! { event }, quote! { context }, quote! { from }, quote! { to }
-                            ].as_slice())?;
+                        let action_body = match s.action.action {
+                            Some(ref action) if s.action.is_async => {
+                                transition_doc.push_str(" Executes an async action.");
 
-                            let body = &action.body;
+                                let remap = remap_closure_inputs(&action.inputs, vec![
+                                    quote! { event }, quote! { context }, quote! { from }, quote! { to }
+                                ].as_slice())?;
 
-                            quote! {
-                                #remap
-                                { #body }
-                            }
-                        } else {
-                            TokenStream::new()
+                                let body = &action.body;
+
+                                q.append_all(quote! {
+                                    impl #fsm_generics_impl finny::FsmTransitionActionAsync<#fsm_ty #fsm_generics_type, #event_ty, #state_from_ty, #state_to_ty> for #ty #fsm_generics_where {
+                                        fn action_async<'fsm_event, Q>(event: & #event_ty , context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q >, from: &mut #state_from_ty, to: &mut #state_to_ty) -> finny::FsmAsyncAction
+                                            where Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>
+                                        {
+                                            #remap
+                                            let action = { #body };
+                                            finny::FsmAsyncAction::new(action)
+                                        }
+                                    }
+                                });
+
+                                TokenStream::new()
+                            },
+                            Some(ref action) => {
+                                transition_doc.push_str(" Executes an action.");
+
+                                let remap = remap_closure_inputs(&action.inputs, vec![
+                                    quote! { event }, quote! { context }, quote! { from }, quote! { to }
+                                ].as_slice())?;
+
+                                let body = &action.body;
+
+                                quote! {
+                                    #remap
+                                    { #body }
+                                }
+                            },
+                            None => TokenStream::new()
                         };
-                        
-                        let state_from_ty = &state_from.ty;
-                        let state_to_ty = &state_to.ty;
 
                         let a = quote! {
                             impl #fsm_generics_impl finny::FsmTransitionAction<#fsm_ty #fsm_generics_type, #event_ty, #state_from_ty, #state_to_ty> for #ty #fsm_generics_where {
@@ -461,11 +828,26 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
         let mut regions = TokenStream::new();
         for region in &fsm.fsm.regions {
             let mut region_transitions = TokenStream::new();
+            let mut region_completions = TokenStream::new();
+            let mut region_exit_points = TokenStream::new();
+            let mut region_transitions_completed = TokenStream::new();
+            let mut has_async = false;
+            let mut region_start = None;
+
+            // the states that have to be checked for completion transitions after entering them
+            let completion_states: HashSet<_> = region.transitions.iter().filter_map(|t| match &t.ty {
+                FsmTransitionType::StateTransition(FsmStateTransition { event, state_from: FsmTransitionState::State(st), .. }) if event.is_completion() => Some(st.ty.clone()),
+                _ => None
+            }).collect();
 
             let region_id = region.region_id;
-            for transition in &region.transitions {
+            for (transition_idx, transition) in region.transitions.iter().enumerate() {
 
                 let transition_ty = &transition.transition_ty;
+                let is_completion = match &transition.ty {
+                    FsmTransitionType::StateTransition(s) => s.event.is_completion(),
+                    _ => false
+                };
                 
                 let match_state = {
                     let state_from = match &transition.ty {
@@ -497,7 +879,10 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                         crate::parse::FsmTransitionEvent::Event(ref ev) => {
                             let kind = &ev.ty;
                             quote! { finny::FsmEvent::Event(#event_enum_ty::#kind(ref ev)) }
-                        }
+                        },
+                        crate::parse::FsmTransitionEvent::EntryPoint(ref ty_entry) => quote! { ev @ finny::FsmEvent::Event(#event_enum_ty::#ty_entry(_)) },
+                        crate::parse::FsmTransitionEvent::Completion | crate::parse::FsmTransitionEvent::SubCompleted(_) |
+                        crate::parse::FsmTransitionEvent::ExitPoint(_) => TokenStream::new()
                     }
                 };
 
@@ -521,6 +906,30 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                 };
                 
                 let fsm_sub_entry = match &transition.ty {
+                    FsmTransitionType::StateTransition(FsmStateTransition {state_to: FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(_), .. }), action: EventGuardAction { entry_point: Some(ty_entry), .. }, .. }) => {
+
+                        let sub_ty = &s.ty;
+
+                        quote! {
+                            {
+                                use finny::FsmBackendResetSubmachine;
+                                <Self as FsmBackendResetSubmachine<_, #sub_ty >>::reset(ctx.backend, &mut inspect_event_ctx);
+                            }
+                            {
+                                let ev = < #sub_ty as finny::FsmEntryPoint< #ty_entry > >::entry_event();
+                                finny::dispatch_to_submachine::<_, #sub_ty, _, _, _>(&mut ctx, finny::FsmEvent::Event(ev), #region_id, &mut inspect_event_ctx)?;
+                            }
+                        }
+                    },
+                    FsmTransitionType::StateTransition(FsmStateTransition {state_to: FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(FsmSubMachineOptions { history: Some(history), .. }), .. }), .. }) => {
+
+                        let sub_ty = &s.ty;
+                        let history = generate_history(Some(*history));
+
+                        quote! {
+                            finny::enter_submachine::<_, #sub_ty, _, _, _>(&mut ctx, #history, #region_id, &mut inspect_event_ctx)?;
+                        }
+                    },
                     FsmTransitionType::StateTransition(FsmStateTransition {state_to: FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(_), .. }), .. }) => {
 
                         let sub_ty = &s.ty;
@@ -584,6 +993,12 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     };
 
                     if let Some(state) = state {
+                        // the exited state isn't stored with the enum storage, its timers can't outlive it
+                        let execute_on_exit = match get_state_slot(fsm, state) {
+                            Some(_) => quote! { execute_on_exit_cancel },
+                            None => quote! { execute_on_exit }
+                        };
+
                         for timer in &state.timers {
                             let timer_field = timer.get_field(&fsm.base);
                             let timer_ty = timer.get_ty(&fsm.base);
@@ -591,7 +1006,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                             timers_exit.append_all(quote! {
                                 {
                                     use finny::FsmTimer;
-                                    ctx.backend.states. #timer_field . execute_on_exit( #timers_enum_ty :: #timer_ty , &mut inspect_event_ctx, ctx.timers );
+                                    ctx.backend.states. #timer_field . #execute_on_exit( #timers_enum_ty :: #timer_ty , &mut inspect_event_ctx, ctx.timers );
                                 }
                             });
                         }
@@ -600,61 +1015,367 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     timers_exit
                 };
 
-                let m = quote! {
-                    ( #match_state , #match_event ) #guard => {
+                // the entry and exit actions that receive the triggering event
+                let (exit_with, entry_with) = {
+                    let (event, state_from, state_to) = match &transition.ty {
+                        FsmTransitionType::SelfTransition(s) => (&s.event, &s.state, &s.state),
+                        FsmTransitionType::StateTransition(s) => (&s.event, &s.state_from, &s.state_to),
+                        FsmTransitionType::InternalTransition(s) => (&s.event, &FsmTransitionState::None, &FsmTransitionState::None)
+                    };
 
-                        #timers_exit
+                    let state_with = |st: &FsmTransitionState| match (event, st) {
+                        (crate::parse::FsmTransitionEvent::Event(ev), FsmTransitionState::State(st)) => fsm.fsm.states.get(&st.ty).map(|st| (&ev.ty, st)),
+                        _ => None
+                    };
 
-                        <#transition_ty>::execute_transition(&mut ctx, &ev, #region_id, &mut inspect_event_ctx);
+                    let exit_with = match state_with(state_from) {
+                        Some((ty_event, st)) if st.on_exit_with.iter().any(|(ty, _)| ty == ty_event) => {
+                            let state_ty = &st.ty;
+                            quote! {
+                                <#state_ty as finny::FsmStateEventActions<_, #ty_event>>::execute_on_exit_with(&mut ctx, ev, #region_id);
+                            }
+                        },
+                        _ => TokenStream::new()
+                    };
 
-                        #fsm_sub_entry
-                        
-                        #timers_enter                        
-                    },
-                };
+                    let entry_with = match state_with(state_to) {
+                        Some((ty_event, st)) if st.on_entry_with.iter().any(|(ty, _)| ty == ty_event) => {
+                            let state_ty = &st.ty;
+                            quote! {
+                                <#state_ty as finny::FsmStateEventActions<_, #ty_event>>::execute_on_entry_with(&mut ctx, ev, #region_id);
+                            }
+                        },
+                        _ => TokenStream::new()
+                    };
 
-                region_transitions.append_all(m);
-            }
+                    (exit_with, entry_with)
+                };
 
-            // match and dispatch to submachines
-            let region_submachines = {
+                // check the completion transitions of the entered state
+                let mark_completion = {
+                    let state = match &transition.ty {
+                        FsmTransitionType::SelfTransition(FsmStateAction { state: FsmTransitionState::State(st), .. }) => Some(st),
+                        FsmTransitionType::StateTransition(FsmStateTransition { state_to: FsmTransitionState::State(st), .. }) => Some(st),
+                        _ => None
+                    };
 
-                let mut sub_matches = TokenStream::new();
+                    match state {
+                        Some(st) if completion_states.contains(&st.ty) => quote! { completion_pending = true; },
+                        _ => TokenStream::new()
+                    }
+                };
 
-                let submachines: Vec<_> = region.transitions.iter().filter_map(|t| match &t.ty {
-                    FsmTransitionType::InternalTransition(_) => None,
-                    FsmTransitionType::SelfTransition(_) => None,
-                    FsmTransitionType::StateTransition(FsmStateTransition { state_to: FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(_), .. }), .. }) => {
-                        Some(s)
+                // notify the parent machine
+                let exit_point = match &transition.ty {
+                    FsmTransitionType::StateTransition(FsmStateTransition { state_to: FsmTransitionState::State(st @ FsmState { kind: FsmStateKind::ExitPoint, .. }), .. }) => {
+                        let ty_exit = &st.ty;
+                        quote! {
+                            inspect_event_ctx.info("Reached the exit point.");
+                            ctx.queue.enqueue(< #ty_exit as Default >::default())?;
+                        }
                     },
-                    _ => None
-                }).collect();
+                    _ => TokenStream::new()
+                };
 
-                for submachine in submachines {
-                    let kind = &submachine.ty;
-                    let fsm_sub = FsmTypes::new(&submachine.ty, &fsm.base.fsm_generics);
-                    let kind_variant = fsm_sub.get_fsm_no_generics_ty();
+                // the entered state is built before anything else, a failure leaves the machine untouched
+                let enum_transition = generate_enum_transition(fsm, transition, region_id, transition_idx)?;
+                let build_state = match enum_transition {
+                    Some((ref build, _)) => build.clone(),
+                    None => {
+                        let entered = match &transition.ty {
+                            FsmTransitionType::SelfTransition(s) => Some((s.state.get_fsm_state()?, Some(s.event.get_event()?.ty.clone()))),
+                            FsmTransitionType::StateTransition(s @ FsmStateTransition { state_from: FsmTransitionState::None, .. }) => Some((s.state_to.get_fsm_state()?, None)),
+                            FsmTransitionType::StateTransition(s) => Some((s.state_to.get_fsm_state()?, Some(s.event.get_event_ty()?))),
+                            FsmTransitionType::InternalTransition(_) => None
+                        };
 
-                    let sub = quote! {
-                        ( finny::FsmCurrentState::State(#states_enum_ty :: #kind_variant), finny::FsmEvent::Event(#event_enum_ty::#kind_variant(ev))  ) => {
-                            return finny::dispatch_to_submachine::<_, #kind, _, _, _>(&mut ctx, finny::FsmEvent::Event(ev.clone()), &mut inspect_event_ctx);
-                        },
-                    };
+                        match entered {
+                            Some((state, event_ty)) if fsm.fsm.states.get(&state.ty).is_some_and(|st| st.construct_on_entry) => {
+                                let new_state = generate_new_state(fsm, state, event_ty.as_ref());
+                                quote! { let entered_state = Some(#new_state); }
+                            },
+                            _ => quote! { let entered_state = None; }
+                        }
+                    }
+                };
 
-                    sub_matches.append_all(sub);
-                }
+                let body = match &transition.ty {
+                    // the target state is entered once the async action completes
+                    FsmTransitionType::StateTransition(FsmStateTransition { state_to: FsmTransitionState::State(st), action: EventGuardAction { is_async: true, .. }, .. }) => {
+                        has_async = true;
+                        let state_to_ty = &st.ty;
+                        let state_types = FsmTypes::new(&st.ty, &fsm.base.fsm_generics);
+                        let variant = state_types.get_fsm_no_generics_ty();
+
+                        // each of the async transitions completes on its own, even with the same target state
+                        region_transitions_completed.append_all(quote! {
+                            ( finny::FsmCurrentState::InTransition(#states_enum_ty :: #variant), finny::FsmEvent::TransitionCompleted(completed) )
+                                if completed.region() == #region_id && completed.transition == #transition_idx =>
+                            {
+                                <#state_to_ty>::execute_on_entry(&mut ctx, #region_id);
+                                ctx.backend.current_states[#region_id] = finny::FsmCurrentState::State(#states_enum_ty :: #variant);
 
-                sub_matches
-            };
+                                #fsm_sub_entry
 
-            // match and dispatch timer events
-            let timers = {
-                let mut timer_dispatch = TokenStream::new();
+                                #timers_enter
 
-                // our timers
-                for state in &region.states {
-                    for timer in &state.timers {
-                        let timer_ty = timer.get_ty(&fsm.base);
+                                #exit_point
+
+                                #mark_completion
+                            },
+                        });
+
+                        let execute_transition = match enum_transition {
+                            Some((_, execute)) => execute,
+                            None => quote! {
+                                use finny::FsmTransitionActionAsync;
+                                <#transition_ty>::execute_transition_async(&mut ctx, &ev, #region_id, #transition_idx, &mut inspect_event_ctx, entered_state)?;
+                            }
+                        };
+
+                        quote! {
+                            #build_state
+
+                            #timers_exit
+
+                            #exit_with
+
+                            {
+                                #execute_transition
+                            }
+                        }
+                    },
+                    _ => {
+                        let execute_transition = match enum_transition {
+                            Some((_, execute)) => execute,
+                            None => quote! {
+                                <#transition_ty>::execute_transition(&mut ctx, &ev, #region_id, &mut inspect_event_ctx, entered_state);
+                            }
+                        };
+
+                        quote! {
+                            #build_state
+
+                            #timers_exit
+
+                            #exit_with
+
+                            #execute_transition
+
+                            #entry_with
+
+                            #fsm_sub_entry
+                        
+                            #timers_enter
+
+                            #exit_point
+
+                            #mark_completion
+                        }
+                    }
+                };
+
+                if let FsmTransitionType::StateTransition(FsmStateTransition { event: FsmTransitionEvent::Start, .. }) = &transition.ty {
+                    region_start = Some(body.clone());
+                }
+
+                if let FsmTransitionType::StateTransition(FsmStateTransition { event: FsmTransitionEvent::ExitPoint(ty_exit), state_from: FsmTransitionState::State(sub), action, .. }) = &transition.ty {
+                    // react to the exit point before the event is dispatched into the submachine
+                    let sub_ty = &sub.ty;
+                    let sub_types = FsmTypes::new(&sub.ty, &fsm.base.fsm_generics);
+                    let sub_variant = sub_types.get_fsm_no_generics_ty();
+                    let guard = if action.guard.is_some() {
+                        quote! { <#transition_ty>::execute_guard(&mut ctx, ev, #region_id, &mut inspect_event_ctx) }
+                    } else {
+                        quote! { true }
+                    };
+
+                    region_exit_points.append_all(quote! {
+                        ( #match_state, finny::FsmEvent::Event(#event_enum_ty::#sub_variant(ref sub_ev)) )
+                            if < #sub_ty as finny::FsmExitPoint< #ty_exit > >::get_exit_point(sub_ev).map_or(false, |ev| #guard) =>
+                        {
+                            if let Some(ev) = < #sub_ty as finny::FsmExitPoint< #ty_exit > >::get_exit_point(sub_ev) {
+                                #body
+                            }
+                        },
+                    });
+                } else if let (true, FsmTransitionType::StateTransition(FsmStateTransition { event, state_from: FsmTransitionState::State(st), action, .. })) = (is_completion, &transition.ty) {
+
+                    let ev = match event {
+                        FsmTransitionEvent::SubCompleted(ty_sub) => quote! { finny::SubCompleted::< #ty_sub >::default() },
+                        _ => quote! { finny::FsmCompletionEvent }
+                    };
+
+                    // the submachine states are completed once the submachine reaches its final states
+                    let mut conditions = vec![];
+                    if let FsmStateKind::SubMachine(_) = st.kind {
+                        let sub_ty = &st.ty;
+                        conditions.push(quote! { {
+                            let sub: & #sub_ty = ctx.backend.states.as_ref();
+                            sub.is_completed()
+                        } });
+                    }
+                    if action.guard.is_some() {
+                        conditions.push(quote! { {
+                            let ev = #ev;
+                            <#transition_ty>::execute_guard(&mut ctx, &ev, #region_id, &mut inspect_event_ctx)
+                        } });
+                    }
+
+                    let guard = if conditions.is_empty() {
+                        TokenStream::new()
+                    } else {
+                        quote! { if #(#conditions)&&* }
+                    };
+
+                    region_completions.append_all(quote! {
+                        #match_state #guard => {
+                            let ev = #ev;
+                            #body
+                        },
+                    });
+                } else {
+                    region_transitions.append_all(quote! {
+                        ( #match_state , #match_event ) #guard => {
+                            #body
+                        },
+                    });
+                }
+            }
+
+            // the regions that aren't entered through the entry point are started in their initial states
+            let entry_points = {
+                let mut entry_points = TokenStream::new();
+                if let Some(ref start) = region_start {
+                    for other_region in fsm.fsm.regions.iter().filter(|r| r.region_id != region_id) {
+                        for transition in &other_region.transitions {
+                            if let FsmTransitionType::StateTransition(FsmStateTransition { event: FsmTransitionEvent::EntryPoint(ty_entry), .. }) = &transition.ty {
+                                entry_points.append_all(quote! {
+                                    (finny::FsmCurrentState::Stopped, ev @ finny::FsmEvent::Event(#event_enum_ty::#ty_entry(_))) => {
+                                        #start
+                                    },
+                                });
+                            }
+                        }
+                    }
+                }
+
+                // the parent machine reacts to these events
+                for state in region.states.iter().filter(|s| s.kind == FsmStateKind::ExitPoint) {
+                    let ty_exit = &state.ty;
+                    entry_points.append_all(quote! {
+                        (_, finny::FsmEvent::Event(#event_enum_ty::#ty_exit(_))) => (),
+                    });
+                }
+
+                entry_points
+            };
+
+            let (completion_init, completion_loop) = if completion_states.is_empty() {
+                (TokenStream::new(), TokenStream::new())
+            } else {
+                (
+                    quote! {
+                        let mut completion_pending = false;
+                    },
+                    quote! {
+                        while completion_pending {
+                            completion_pending = false;
+
+                            let current_state = ctx.backend.current_states[#region_id];
+                            match current_state {
+                                #region_completions
+                                _ => ()
+                            }
+                        }
+                    }
+                )
+            };
+
+            // match and dispatch to submachines
+            let region_submachines = {
+
+                let mut sub_matches = TokenStream::new();
+
+                let submachines: Vec<_> = region.transitions.iter().filter_map(|t| match &t.ty {
+                    FsmTransitionType::InternalTransition(_) => None,
+                    FsmTransitionType::SelfTransition(_) => None,
+                    FsmTransitionType::StateTransition(FsmStateTransition { state_to: FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(_), .. }), .. }) => {
+                        Some(s)
+                    },
+                    _ => None
+                }).collect();
+
+                for submachine in submachines {
+                    let kind = &submachine.ty;
+                    let fsm_sub = FsmTypes::new(&submachine.ty, &fsm.base.fsm_generics);
+                    let kind_variant = fsm_sub.get_fsm_no_generics_ty();
+
+                    // the events that the submachine didn't handle are subject to the policies of its state
+                    // and of this machine
+                    let unhandled = &submachine.unhandled;
+                    let ignores_sub = unhandled.ignored_events.iter().any(|ev| ev == kind || ev == fsm_sub.get_fsm_no_generics_ty());
+                    let (event_binding, context_binding) = match (ignores_sub, &unhandled.handler, unhandled.ignore_all) {
+                        (false, Some(_), _) => (quote! { unhandled_event @ }, quote! { _ }),
+                        (false, None, false) => (TokenStream::new(), quote! { sub_context }),
+                        _ => (TokenStream::new(), quote! { _ })
+                    };
+                    let sub_unhandled = if ignores_sub {
+                        quote! {
+                            inspect_event_ctx.info("The event was ignored in this state.");
+                        }
+                    } else if let Some(ref handler) = unhandled.handler {
+                        generate_unhandled_handler(handler, quote! { #region_id })?
+                    } else if unhandled.ignore_all {
+                        quote! {
+                            inspect_event_ctx.info("The unhandled event was ignored in this state.");
+                        }
+                    } else {
+                        quote! {
+                            transition_misses += 1;
+                            sub_no_transition = Some(sub_context);
+                        }
+                    };
+
+                    // check the completion of the submachine
+                    let sub_handled = if completion_states.contains(kind) {
+                        quote! {
+                            r => {
+                                r?;
+                                completion_pending = true;
+                            }
+                        }
+                    } else {
+                        quote! {
+                            r => return r
+                        }
+                    };
+
+                    let sub = quote! {
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #kind_variant), finny::FsmEvent::Event(#event_binding #event_enum_ty::#kind_variant(ev))  ) => {
+                            match finny::dispatch_to_submachine::<_, #kind, _, _, _>(&mut ctx, finny::FsmEvent::Event(ev.clone()), #region_id, &mut inspect_event_ctx) {
+                                Err(finny::FsmError::NoTransition(#context_binding)) => {
+                                    #sub_unhandled
+                                },
+                                #sub_handled
+                            }
+                        },
+                    };
+
+                    sub_matches.append_all(sub);
+                }
+
+                sub_matches
+            };
+
+            // match and dispatch timer events
+            let timers = {
+                let mut timer_dispatch = TokenStream::new();
+
+                // our timers
+                for state in &region.states {
+                    for timer in &state.timers {
+                        let timer_ty = timer.get_ty(&fsm.base);
 
                         timer_dispatch.append_all(quote! {
                             (_, finny::FsmEvent::Timer( timer_id @ #timers_enum_ty :: #timer_ty )) => {
@@ -674,11 +1395,32 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     let sub_ty = FsmTypes::new(sub, &fsm.base.fsm_generics);
                     let sub_variant = sub_ty.get_fsm_no_generics_ty();
 
+                    let dispatch = if completion_states.contains(sub) {
+                        quote! {
+                            finny::dispatch_to_submachine::<_, #sub, _, _, _>(&mut ctx, ev, #region_id, &mut inspect_event_ctx)?;
+                            completion_pending = true;
+                        }
+                    } else {
+                        quote! {
+                            return finny::dispatch_to_submachine::<_, #sub, _, _, _>(&mut ctx, ev, #region_id, &mut inspect_event_ctx);
+                        }
+                    };
+
                     timer_dispatch.append_all(quote! {
                         (_, finny::FsmEvent::Timer( #timers_enum_ty :: #sub_variant (timer_id))) => {
                             {
                                 let ev = finny::FsmEvent::Timer(*timer_id);
-                                return finny::dispatch_to_submachine::<_, #sub, _, _, _>(&mut ctx, ev, &mut inspect_event_ctx);
+                                #dispatch
+                            }
+                        },
+                    });
+
+                    // the async transitions of the submachine complete through its parent's region
+                    region_transitions_completed.append_all(quote! {
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #sub_variant), finny::FsmEvent::TransitionCompleted(completed) ) if completed.region() == #region_id => {
+                            {
+                                let ev = finny::FsmEvent::TransitionCompleted(completed.to_submachine());
+                                #dispatch
                             }
                         },
                     });
@@ -687,25 +1429,333 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                 timer_dispatch
             };
 
+            // with the enum storage, the stopped region doesn't keep its state
+            let clear_region = if fsm.fsm.codegen_options.enum_storage {
+                let region_field = get_region_field(region_id);
+                let region_enum_ty = get_region_enum_ty(fsm, region_id);
+                quote! {
+                    ctx.backend.states. #region_field = #region_enum_ty :: Empty(core::marker::PhantomData::default());
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            // stop the active state, including its submachine and all the timers of this region
+            let stop = {
+                let mut timers_stop = TokenStream::new();
+                for state in &region.states {
+                    for timer in &state.timers {
+                        let timer_field = timer.get_field(&fsm.base);
+                        let timer_ty = timer.get_ty(&fsm.base);
+
+                        timers_stop.append_all(quote! {
+                            {
+                                use finny::FsmTimer;
+                                ctx.backend.states. #timer_field . execute_on_stop( #timers_enum_ty :: #timer_ty , &mut inspect_event_ctx, ctx.timers );
+                            }
+                        });
+                    }
+                }
+
+                let mut stop = TokenStream::new();
+                for state in &region.states {
+                    let state_ty = &state.ty;
+                    let state_types = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+                    let variant = state_types.get_fsm_no_generics_ty();
+
+                    let sub_stop = match state.kind {
+                        FsmStateKind::SubMachine(_) => quote! {
+                            finny::dispatch_to_submachine::<_, #state_ty, _, _, _>(&mut ctx, finny::FsmEvent::Stop, #region_id, &mut inspect_event_ctx)?;
+                        },
+                        _ => TokenStream::new()
+                    };
+
+                    stop.append_all(quote! {
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #variant), finny::FsmEvent::Stop ) => {
+                            #sub_stop
+
+                            #timers_stop
+
+                            <#state_ty>::execute_on_exit(&mut ctx, #region_id);
+                            #clear_region
+                            ctx.backend.current_states[#region_id] = finny::FsmCurrentState::Stopped;
+                        },
+                    });
+                }
+
+                stop
+            };
+
+            // the target of the async transition wasn't entered yet, the frontend drops the pending action
+            let stop_in_transition = if !has_async {
+                TokenStream::new()
+            } else {
+                quote! {
+                    ( finny::FsmCurrentState::InTransition(_), finny::FsmEvent::Stop ) => {
+                        #clear_region
+                        ctx.backend.current_states[#region_id] = finny::FsmCurrentState::Stopped;
+                    },
+                }
+            };
+
+            // re-enter the last active state when resuming this machine from the history
+            let resume = {
+                let mut resume = TokenStream::new();
+                for state in &region.states {
+                    let state_ty = &state.ty;
+                    let state_types = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+                    let variant = state_types.get_fsm_no_generics_ty();
+
+                    let mut timers_enter = TokenStream::new();
+                    for timer in &state.timers {
+                        let timer_field = timer.get_field(&fsm.base);
+                        let timer_ty = timer.get_ty(&fsm.base);
+
+                        timers_enter.append_all(quote! {
+                            {
+                                use finny::FsmTimer;
+                                ctx.backend.states. #timer_field . execute_on_enter( #timers_enum_ty :: #timer_ty , &mut ctx.backend.context, &mut inspect_event_ctx, ctx.timers );
+                            }
+                        });
+                    }
+
+                    let mark_completion = if completion_states.contains(&state.ty) {
+                        quote! { completion_pending = true; }
+                    } else {
+                        TokenStream::new()
+                    };
+
+                    let (match_history, sub_resume) = match state.kind {
+                        FsmStateKind::SubMachine(ref sub) => {
+                            // the shallow history enters the nested submachines as usual
+                            let sub_history = generate_history(sub.history);
+                            (quote! { history }, quote! {
+                                let sub_history = match history {
+                                    finny::History::Deep => Some(finny::History::Deep),
+                                    finny::History::Shallow => #sub_history
+                                };
+                                finny::enter_submachine::<_, #state_ty, _, _, _>(&mut ctx, sub_history, #region_id, &mut inspect_event_ctx)?;
+                            })
+                        },
+                        _ => (quote! { _ }, TokenStream::new())
+                    };
+
+                    resume.append_all(quote! {
+                        ( finny::FsmCurrentState::State(#states_enum_ty :: #variant), finny::FsmEvent::Resume(#match_history) ) => {
+                            <#state_ty>::execute_on_entry(&mut ctx, #region_id);
+
+                            #sub_resume
+
+                            #timers_enter
+
+                            #mark_completion
+                        },
+                    });
+                }
+                resume
+            };
+
+            // defer the events that none of the transitions matched in this state
+            let deferred = {
+                let mut deferred = TokenStream::new();
+                for state in &region.states {
+                    let state_types = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+                    let variant = state_types.get_fsm_no_generics_ty();
+
+                    for ev in &state.deferred_events {
+                        deferred.append_all(quote! {
+                            ( finny::FsmCurrentState::State(#states_enum_ty :: #variant), finny::FsmEvent::Event(#event_enum_ty :: #ev (_)) ) => {
+                                deferrals += 1;
+                            },
+                        });
+                    }
+                }
+                deferred
+            };
+
+            // ignore or handle the events that none of the transitions matched in this state
+            let unhandled = {
+                let mut unhandled = TokenStream::new();
+                for state in &region.states {
+                    let state_types = FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+                    let variant = state_types.get_fsm_no_generics_ty();
+                    let match_state = quote! { finny::FsmCurrentState::State(#states_enum_ty :: #variant) };
+
+                    for ev in &state.unhandled.ignored_events {
+                        unhandled.append_all(quote! {
+                            ( #match_state, finny::FsmEvent::Event(#event_enum_ty :: #ev (_)) ) => {
+                                inspect_event_ctx.info("The event was ignored in this state.");
+                            },
+                        });
+                    }
+
+                    if let Some(ref handler) = state.unhandled.handler {
+                        let handler = generate_unhandled_handler(handler, quote! { #region_id })?;
+                        unhandled.append_all(quote! {
+                            ( #match_state, finny::FsmEvent::Event(ref unhandled_event) ) => {
+                                #handler
+                            },
+                        });
+                    } else if state.unhandled.ignore_all {
+                        unhandled.append_all(quote! {
+                            ( #match_state, finny::FsmEvent::Event(_) ) => {
+                                inspect_event_ctx.info("The unhandled event was ignored in this state.");
+                            },
+                        });
+                    }
+                }
+                unhandled
+            };
+
             regions.append_all(quote! {
+                #completion_init
+
                 match (ctx.backend.current_states[#region_id], &event) {
 
+                    #region_exit_points
+
                     #region_submachines
-                    
+
                     #region_transitions
 
+                    #region_transitions_completed
+
+                    #stop
+
+                    #stop_in_transition
+
+                    // stopping an already stopped region is a no-op
+                    (finny::FsmCurrentState::Stopped, finny::FsmEvent::Stop) => (),
+
+                    #resume
+
+                    // a stopped region has no history to resume
+                    (finny::FsmCurrentState::Stopped, finny::FsmEvent::Resume(_)) => (),
+
                     // do not dispatch timers if the machine is stopped
                     (finny::FsmCurrentState::Stopped, finny::FsmEvent::Timer(_)) => (),
 
                     #timers
 
+                    #deferred
+
+                    #entry_points
+
+                    #unhandled
+
                     _ => {
                         transition_misses += 1;
                     }
                 }
+
+                #completion_loop
             });
         }
 
+        // put the event into the deferred queue if all the regions either deferred it or didn't match it
+        let has_deferrals = fsm.fsm.states.values().any(|s| !s.deferred_events.is_empty());
+        let (deferrals_init, deferrals_result) = if has_deferrals {
+            (
+                quote! {
+                    let mut deferrals = 0;
+                },
+                quote! {
+                    if deferrals > 0 && transition_misses + deferrals == #region_count {
+                        match &event {
+                            finny::FsmEvent::Event(ev) => {
+                                inspect_event_ctx.info("Deferring the event.");
+                                ctx.queue.defer(ev.clone())
+                            },
+                            _ => Ok(())
+                        }
+                    } else
+                }
+            )
+        } else {
+            (TokenStream::new(), TokenStream::new())
+        };
+
+        // the machine-wide policy for the events that none of the regions matched
+        let fsm_unhandled = {
+            let mut fsm_unhandled = TokenStream::new();
+            let unhandled = &fsm.fsm.unhandled;
+
+            for ev in &unhandled.ignored_events {
+                fsm_unhandled.append_all(quote! {
+                    finny::FsmEvent::Event(#event_enum_ty :: #ev (_)) => {
+                        inspect_event_ctx.info("The event was ignored.");
+                        Ok(())
+                    },
+                });
+            }
+
+            if let Some(ref handler) = unhandled.handler {
+                let handler = generate_unhandled_handler(handler, quote! { 0 })?;
+                fsm_unhandled.append_all(quote! {
+                    finny::FsmEvent::Event(ref unhandled_event) => {
+                        #handler
+                        Ok(())
+                    },
+                });
+            } else if unhandled.ignore_all {
+                fsm_unhandled.append_all(quote! {
+                    finny::FsmEvent::Event(_) => {
+                        inspect_event_ctx.info("The unhandled event was ignored.");
+                        Ok(())
+                    },
+                });
+            }
+
+            fsm_unhandled
+        };
+
+        // the frontends without a deferred queue only accept the machines that never defer the events, and the sync
+        // frontends the machines without async actions, including their submachines. The bounds are higher-ranked so
+        // that they're only checked where they're required.
+        let marker_impl = |marker: TokenStream| {
+            let mut generics = fsm.base.fsm_generics.clone();
+            for state in fsm.fsm.states.values() {
+                if let FsmStateKind::SubMachine(_) = state.kind {
+                    let ty = &state.ty;
+                    generics.make_where_clause().predicates.push(syn::parse_quote!(for<'finny> #ty: #marker));
+                }
+            }
+            let (generics_impl, _, generics_where) = generics.split_for_impl();
+
+            quote! {
+                impl #generics_impl #marker for #fsm_ty #fsm_generics_type #generics_where { }
+            }
+        };
+
+        let no_deferrals = if has_deferrals {
+            TokenStream::new()
+        } else {
+            marker_impl(quote! { finny::FsmNoDeferrals })
+        };
+
+        let has_async_actions = fsm.fsm.regions.iter().flat_map(|r| r.transitions.iter()).any(|t| matches!(t.ty,
+            FsmTransitionType::StateTransition(FsmStateTransition { action: EventGuardAction { is_async: true, .. }, .. })));
+        let no_async_actions = if has_async_actions {
+            TokenStream::new()
+        } else {
+            marker_impl(quote! { finny::FsmNoAsyncActions })
+        };
+
+        // the context of the submachine's error is kept, if none of the policies handle the event
+        let has_submachines = fsm.fsm.states.values().any(|s| matches!(s.kind, FsmStateKind::SubMachine(_)));
+        let (sub_no_transition_init, no_transition_context) = if has_submachines {
+            (
+                quote! {
+                    let mut sub_no_transition: Option<finny::FsmErrorContext> = None;
+                },
+                quote! {
+                    sub_no_transition.unwrap_or_else(|| finny::FsmErrorContext::new::<Self>(&event, &ctx.backend))
+                }
+            )
+        } else {
+            (TokenStream::new(), quote! { finny::FsmErrorContext::new::<Self>(&event, &ctx.backend) })
+        };
+
         quote! {
               
             impl #fsm_generics_impl finny::FsmBackend for #fsm_ty #fsm_generics_type
@@ -723,13 +1773,18 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     use finny::{FsmTransitionGuard, FsmTransitionAction, FsmAction, FsmState, FsmTransitionFsmStart};
 
                     let mut transition_misses = 0;
+                    #deferrals_init
+                    #sub_no_transition_init
 
                     let mut inspect_event_ctx = ctx.inspect.new_event::<Self>(&event, &ctx.backend);
 
                     #regions
 
-                    let result = if transition_misses == #region_count {
-                        Err(finny::FsmError::NoTransition)
+                    let result = #deferrals_result if transition_misses == #region_count {
+                        match &event {
+                            #fsm_unhandled
+                            _ => Err(finny::FsmError::NoTransition(#no_transition_context))
+                        }
                     } else {
                         Ok(())
                     };
@@ -747,6 +1802,10 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                     Ok(())
                 }
             }
+
+            #no_deferrals
+
+            #no_async_actions
         }
     };
     
@@ -776,7 +1835,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
             let state_ty = FsmTypes::new(&ty, &fsm.base.fsm_generics);
             let variant = state_ty.get_fsm_no_generics_ty();            
 
-            let state = quote! {
+            let state_impl = quote! {
 
                 impl #fsm_generics_impl finny::FsmState<#fsm_ty #fsm_generics_type> for #ty #fsm_generics_where {
                     fn on_entry<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) {
@@ -794,18 +1853,65 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
 
             };
 
-            states.append_all(state);
+            states.append_all(state_impl);
+
+            // the actions that receive the triggering event, each event type gets its own impl
+            let mut event_actions: Vec<&syn::Type> = vec![];
+            for (ty_event, _) in state.on_entry_with.iter().chain(state.on_exit_with.iter()) {
+                if !event_actions.contains(&ty_event) {
+                    event_actions.push(ty_event);
+                }
+            }
+
+            let remap_event_closure = |closures: &[(syn::Type, syn::ExprClosure)], ty_event: &syn::Type| -> syn::Result<TokenStream> {
+                match closures.iter().find(|(ty, _)| ty == ty_event) {
+                    Some((_, c)) => {
+                        let remap = remap_closure_inputs(&c.inputs, &vec![ quote! { self }, quote! { event }, quote! { context } ])?;
+                        let b = &c.body;
+
+                        Ok(quote! {
+                            #remap
+                            { #b }
+                        })
+                    },
+                    None => Ok(TokenStream::new())
+                }
+            };
+
+            for ty_event in event_actions {
+                let on_entry_with = remap_event_closure(&state.on_entry_with, ty_event)?;
+                let on_exit_with = remap_event_closure(&state.on_exit_with, ty_event)?;
+
+                states.append_all(quote! {
+                    impl #fsm_generics_impl finny::FsmStateEventActions<#fsm_ty #fsm_generics_type, #ty_event> for #ty #fsm_generics_where {
+                        fn on_entry_with<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, event: & #ty_event, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) {
+                            #on_entry_with
+                        }
 
+                        fn on_exit_with<'fsm_event, Q: finny::FsmEventQueue<#fsm_ty #fsm_generics_type>>(&mut self, event: & #ty_event, context: &mut finny::EventContext<'fsm_event, #fsm_ty #fsm_generics_type, Q>) {
+                            #on_exit_with
+                        }
+                    }
+                });
+            }
         }
 
         states
     };
 
     let builder = {
+        let fsm_serde_derive = {
+            let backend_ty = quote! { finny::FsmBackendImpl< #fsm_ty #fsm_generics_type > };
+            generate_serde_derive(fsm, Some((
+                quote! { #backend_ty: finny::bundled::serde::Serialize }.to_string(),
+                quote! { #backend_ty: finny::bundled::serde::Deserialize<'de> }.to_string()
+            )))
+        };
 
         quote! {
 
             /// A Finny Finite State Machine.
+            #fsm_serde_derive
             pub struct #fsm_ty #fsm_generics_type #fsm_generics_where {
                 backend: finny::FsmBackendImpl<#fsm_ty #fsm_generics_type >
             }
@@ -840,6 +1946,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
     let timers = {
 
         let mut code = TokenStream::new();
+        let timer_serde_derive = generate_serde_derive(fsm, Some((String::new(), String::new())));
 
         let mut enum_variants = vec![];
         let mut submachines = vec![];
@@ -884,7 +1991,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
                 code.append_all(quote! {
 
                     #[doc = #timer_doc ]
-                                        
+                    #timer_serde_derive
                     pub struct #timer_ty #fsm_generics_type #fsm_generics_where {
                         instance: Option<finny::TimerInstance < #fsm_ty #fsm_generics_type > >
                     }
@@ -935,6 +2042,7 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
 
         code.append_all(quote! {
             #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+            #serde_derive
             pub enum #timers_enum_ty {
                 #variants
             }
@@ -1157,7 +2265,43 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
         }
     };
 
-    let fsm_meta = generate_fsm_meta(&fsm);
+    // the entry and exit points, used by the parent machines
+    let entry_exit_points = {
+        let mut q = TokenStream::new();
+
+        for region in &fsm.fsm.regions {
+            for transition in &region.transitions {
+                if let FsmTransitionType::StateTransition(FsmStateTransition { event: FsmTransitionEvent::EntryPoint(ty_entry), .. }) = &transition.ty {
+                    q.append_all(quote! {
+                        impl #fsm_generics_impl finny::FsmEntryPoint< #ty_entry > for #fsm_ty #fsm_generics_type #fsm_generics_where {
+                            fn entry_event() -> <Self as finny::FsmBackend>::Events {
+                                #event_enum_ty :: #ty_entry (Default::default())
+                            }
+                        }
+                    });
+                }
+            }
+
+            for state in region.states.iter().filter(|s| s.kind == FsmStateKind::ExitPoint) {
+                let ty_exit = &state.ty;
+                q.append_all(quote! {
+                    impl #fsm_generics_impl finny::FsmExitPoint< #ty_exit > for #fsm_ty #fsm_generics_type #fsm_generics_where {
+                        fn get_exit_point(event: &<Self as finny::FsmBackend>::Events) -> Option<& #ty_exit> {
+                            #[allow(unreachable_patterns)]
+                            match event {
+                                #event_enum_ty :: #ty_exit (ev) => Some(ev),
+                                _ => None
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
+        q
+    };
+
+    let fsm_meta = generate_fsm_meta(&fsm)?;
 
     let mut q = quote! {
         #states_store
@@ -1176,6 +2320,8 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
 
         #sub_restart
 
+        #entry_exit_points
+
         #fsm_meta
     };
 
@@ -1190,4 +2336,270 @@ pub fn generate_fsm_code(fsm: &FsmFnInput, _attr: TokenStream, _input: TokenStre
     */
 
     Ok(q.into())
-}
\ No newline at end of file
+}
+
+/// Inline the handler of the unhandled events, `unhandled_event` has to be in scope.
+fn generate_unhandled_handler(handler: &syn::ExprClosure, region: TokenStream) -> syn::Result<TokenStream> {
+    let remap = remap_closure_inputs(&handler.inputs, &[quote! { unhandled_event }, quote! { &mut unhandled_context }])?;
+    let body = &handler.body;
+
+    Ok(quote! {
+        inspect_event_ctx.info("Executing the handler for the unhandled event.");
+        let mut unhandled_context = finny::EventContext {
+            context: &mut ctx.backend.context,
+            queue: &mut *ctx.queue,
+            region: #region
+        };
+        {
+            #remap
+            { #body }
+        }
+    })
+}
+
+/// Builds the entered state and executes the transition with the enum storage, where the entered state replaces
+/// the exited one in the region. None if the transition doesn't replace any of the stored states.
+fn generate_enum_transition(fsm: &FsmFnInput, transition: &crate::parse::FsmTransition, region_id: usize, transition_idx: usize) -> syn::Result<Option<(TokenStream, TokenStream)>> {
+    let fsm_ty = &fsm.base.fsm_ty;
+    let (_, fsm_generics_type, _) = fsm.base.fsm_generics.split_for_impl();
+    let states_enum_ty = ty_append(fsm_ty, "CurrentState");
+    let transition_ty = &transition.transition_ty;
+    let region_field = get_region_field(region_id);
+    let region_enum_ty = get_region_enum_ty(fsm, region_id);
+
+    let build = |state: &FsmState, event_ty: Option<&syn::Type>| {
+        let new_state = generate_new_state(fsm, state, event_ty);
+        quote! { let entered_state = #new_state; }
+    };
+    let variant = |state: &FsmState| FsmTypes::new(&state.ty, &fsm.base.fsm_generics).get_fsm_no_generics_ty().clone();
+
+    let q = match &transition.ty {
+        FsmTransitionType::InternalTransition(_) => None,
+        FsmTransitionType::SelfTransition(s) => {
+            let state = s.state.get_fsm_state()?;
+            let event_ty = &s.event.get_event()?.ty;
+
+            match get_state_slot(fsm, state) {
+                Some(_) => {
+                    let state_ty = &state.ty;
+                    let state_variant = variant(state);
+
+                    Some((build(state, Some(event_ty)), quote! {
+                        inspect_event_ctx.for_transition::< #transition_ty >();
+
+                        <#state_ty>::execute_on_exit(&mut ctx, #region_id);
+                        ctx.backend.states. #region_field = #region_enum_ty :: #state_variant(entered_state);
+
+                        {
+                            let mut event_context = finny::EventContext {
+                                context: &mut ctx.backend.context,
+                                queue: ctx.queue,
+                                region: #region_id
+                            };
+                            let state: &mut #state_ty = match ctx.backend.states. #region_field {
+                                #region_enum_ty :: #state_variant(ref mut state) => state,
+                                _ => unreachable!()
+                            };
+                            < #transition_ty as finny::FsmAction<#fsm_ty #fsm_generics_type, #event_ty, #state_ty> >::action(&ev, &mut event_context, state);
+                        }
+
+                        <#state_ty>::execute_on_entry(&mut ctx, #region_id);
+                    }))
+                },
+                None => None
+            }
+        },
+        FsmTransitionType::StateTransition(s @ FsmStateTransition { state_from: FsmTransitionState::None, .. }) => {
+            let state = s.state_to.get_fsm_state()?;
+
+            match get_state_slot(fsm, state) {
+                Some(_) => {
+                    let state_ty = &state.ty;
+                    let state_variant = variant(state);
+
+                    Some((build(state, None), quote! {
+                        inspect_event_ctx.for_transition::< #transition_ty >();
+
+                        ctx.backend.states. #region_field = #region_enum_ty :: #state_variant(entered_state);
+                        <#state_ty>::execute_on_entry(&mut ctx, #region_id);
+
+                        ctx.backend.current_states[#region_id] = finny::FsmCurrentState::State(#states_enum_ty :: #state_variant);
+                    }))
+                },
+                None => None
+            }
+        },
+        FsmTransitionType::StateTransition(s) => {
+            let event_ty = s.event.get_event_ty()?;
+            let state_from = s.state_from.get_fsm_state()?;
+            let state_to = s.state_to.get_fsm_state()?;
+            let state_from_ty = &state_from.ty;
+            let state_to_ty = &state_to.ty;
+            let from_variant = variant(state_from);
+            let to_variant = variant(state_to);
+
+            let (from_slot, to_slot) = (get_state_slot(fsm, state_from).is_some(), get_state_slot(fsm, state_to).is_some());
+            if !from_slot && !to_slot {
+                return Ok(None);
+            }
+
+            let build_state = if to_slot { build(state_to, Some(&event_ty)) } else { TokenStream::new() };
+
+            // the exited state is taken out of the region until the end of the action
+            let entered = if to_slot {
+                quote! { #region_enum_ty :: #to_variant(entered_state) }
+            } else {
+                quote! { #region_enum_ty :: Empty(core::marker::PhantomData::default()) }
+            };
+            let replace = if from_slot {
+                quote! {
+                    let mut exited_state = match core::mem::replace(&mut ctx.backend.states. #region_field, #entered) {
+                        #region_enum_ty :: #from_variant(state) => state,
+                        _ => unreachable!()
+                    };
+                }
+            } else {
+                quote! {
+                    ctx.backend.states. #region_field = #entered;
+                }
+            };
+
+            let state_from_field = &state_from.state_storage_field;
+            let state_to_field = &state_to.state_storage_field;
+            let from = if from_slot {
+                quote! { &mut exited_state }
+            } else {
+                quote! { &mut ctx.backend.states. #state_from_field }
+            };
+            let to = if to_slot {
+                quote! {
+                    match ctx.backend.states. #region_field {
+                        #region_enum_ty :: #to_variant(ref mut state) => state,
+                        _ => unreachable!()
+                    }
+                }
+            } else {
+                quote! { &mut ctx.backend.states. #state_to_field }
+            };
+            let action_states = quote! {
+                inspect_ctx.on_action::< #transition_ty >();
+
+                let mut event_context = finny::EventContext {
+                    context: &mut ctx.backend.context,
+                    queue: ctx.queue,
+                    region: #region_id
+                };
+                let from: &mut #state_from_ty = #from;
+                let to: &mut #state_to_ty = #to;
+            };
+
+            let execute = if s.action.is_async {
+                quote! {
+                    let inspect_ctx = inspect_event_ctx.for_transition::< #transition_ty >();
+
+                    <#state_from_ty>::execute_on_exit(&mut ctx, #region_id);
+
+                    // the exited state is dropped at the end of the block
+                    let action = {
+                        #replace
+                        #action_states
+                        < #transition_ty as finny::FsmTransitionActionAsync<#fsm_ty #fsm_generics_type, #event_ty, #state_from_ty, #state_to_ty> >::action_async(&ev, &mut event_context, from, to)
+                    };
+
+                    ctx.queue.spawn_action(finny::FsmTransitionCompleted::new(#region_id, #transition_idx), action)?;
+                    inspect_ctx.info("Waiting for the async action to complete.");
+
+                    ctx.backend.current_states[#region_id] = finny::FsmCurrentState::InTransition(#states_enum_ty :: #to_variant);
+                }
+            } else {
+                quote! {
+                    let inspect_ctx = inspect_event_ctx.for_transition::< #transition_ty >();
+
+                    <#state_from_ty>::execute_on_exit(&mut ctx, #region_id);
+
+                    // the exited state is dropped at the end of the block
+                    {
+                        #replace
+                        #action_states
+                        < #transition_ty as finny::FsmTransitionAction<#fsm_ty #fsm_generics_type, #event_ty, #state_from_ty, #state_to_ty> >::action(&ev, &mut event_context, from, to);
+                    }
+
+                    <#state_to_ty>::execute_on_entry(&mut ctx, #region_id);
+                    ctx.backend.current_states[#region_id] = finny::FsmCurrentState::State(#states_enum_ty :: #to_variant);
+                }
+            };
+
+            Some((build_state, execute))
+        }
+    };
+
+    Ok(q)
+}
+
+/// The expression that builds the entered state, from the event if the state is constructed from it. The
+/// failures are returned from the dispatch, `ctx` and `ev` have to be in scope.
+fn generate_new_state(fsm: &FsmFnInput, state: &FsmState, event_ty: Option<&syn::Type>) -> TokenStream {
+    let fsm_ty = &fsm.base.fsm_ty;
+    let (_, fsm_generics_type, _) = fsm.base.fsm_generics.split_for_impl();
+    let state_ty = &state.ty;
+    let state = fsm.fsm.states.get(state_ty).unwrap_or(state);
+
+    match event_ty {
+        Some(event_ty) if state.construct_on_entry_from.contains(event_ty) => quote! {
+            < #state_ty as finny::FsmStateFromEvent<#fsm_ty #fsm_generics_type, #event_ty> >::from_event(&ev, &ctx.backend.context)
+        },
+        _ => quote! {
+            < #state_ty as finny::FsmStateFactory<#fsm_ty #fsm_generics_type> >::new_state(&ctx.backend.context)?
+        }
+    }
+}
+
+/// The region that stores the state, with the enum storage. The submachines are always stored in their own fields.
+fn get_state_slot(fsm: &FsmFnInput, state: &FsmState) -> Option<usize> {
+    if !fsm.fsm.codegen_options.enum_storage {
+        return None;
+    }
+
+    match state.kind {
+        FsmStateKind::SubMachine(_) => None,
+        _ => fsm.fsm.regions.iter().find(|r| r.states.iter().any(|s| s.ty == state.ty)).map(|r| r.region_id)
+    }
+}
+
+fn get_region_field(region_id: usize) -> syn::Ident {
+    syn::Ident::new(&format!("region_{}", region_id), Span::call_site())
+}
+
+fn get_region_enum_ty(fsm: &FsmFnInput, region_id: usize) -> syn::Type {
+    ty_append(&fsm.base.fsm_ty, &format!("Region{}State", region_id))
+}
+
+/// The history of a submachine, as an `Option<finny::History>` expression.
+fn generate_history(history: Option<FsmHistory>) -> TokenStream {
+    match history {
+        Some(FsmHistory::Shallow) => quote! { Some(finny::History::Shallow) },
+        Some(FsmHistory::Deep) => quote! { Some(finny::History::Deep) },
+        None => quote! { None }
+    }
+}
+
+/// The derives for serializing the generated types, for the machines with `serde_snapshots`. The optional
+/// bounds replace the ones that are inferred by serde.
+fn generate_serde_derive(fsm: &FsmFnInput, bounds: Option<(String, String)>) -> TokenStream {
+    if !fsm.fsm.codegen_options.serde_snapshots {
+        return TokenStream::new();
+    }
+
+    let bounds = match bounds {
+        Some((serialize, deserialize)) => quote! {
+            #[serde(bound(serialize = #serialize, deserialize = #deserialize))]
+        },
+        None => TokenStream::new()
+    };
+
+    quote! {
+        #[derive(finny::bundled::serde::Serialize, finny::bundled::serde::Deserialize)]
+        #[serde(crate = "finny::bundled::serde")]
+        #bounds
+    }
+}
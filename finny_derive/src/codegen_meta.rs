@@ -4,9 +4,11 @@ use proc_macro2::TokenStream;
 
 use crate::{meta::{
         FinnyEvent, FinnyFsm, FinnyRegion, FinnyState, FinnyStateKind, FinnyTimer, FinnyTransition,
-        FinnyTransitionKind, FinnyTransitionNormal,
-    }, parse::{FsmFnInput, FsmState, FsmStateKind, FsmTransitionState}, utils::{strip_generics, tokens_to_string}};
+        FinnyTransitionKind, FinnyTransitionNormal, FinnyUnhandledPolicy,
+    }, parse::{FsmFnInput, FsmState, FsmStateKind, FsmTransitionState, FsmUnhandledPolicy}, utils::{strip_generics, tokens_to_string}};
 use quote::quote;
+#[cfg(any(feature="generate_plantuml", feature="generate_dot", feature="generate_mermaid", feature="meta"))]
+use quote::TokenStreamExt;
 
 fn ty_to_string(ty: &syn::Type) -> String {
     let ty = ty.clone();
@@ -14,28 +16,53 @@ fn ty_to_string(ty: &syn::Type) -> String {
     tokens_to_string(&ty)
 }
 
+/// The unhandled handler is named after its state or machine.
+fn to_info_unhandled(unhandled: &FsmUnhandledPolicy, owner_id: &str) -> FinnyUnhandledPolicy {
+    FinnyUnhandledPolicy {
+        ignored_events: unhandled.ignored_events.iter().map(tokens_to_string).collect(),
+        ignore_all: unhandled.ignore_all,
+        handler: unhandled.handler.as_ref().map(|_| format!("{}::on_unhandled", owner_id)),
+    }
+}
+
 fn to_info_state(s: &FsmTransitionState, fsm: &FsmFnInput) -> FinnyStateKind {
     match s {
         FsmTransitionState::None => FinnyStateKind::Stopped,
-        FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::Normal, .. }) => FinnyStateKind::State(FinnyState {
-            state_id: ty_to_string(&s.ty),
-            timers: s
-                .timers
-                .iter()
-                .map(|t| FinnyTimer {
-                    timer_id: tokens_to_string(&t.get_ty(&fsm.base)),
-                })
-                .collect(),
-        }),
-        FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(_), .. }) => FinnyStateKind::SubMachine(ty_to_string(&s.ty))
+        FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::Normal, .. }) => {
+            let state_id = ty_to_string(&s.ty);
+
+            FinnyStateKind::State(FinnyState {
+                timers: s
+                    .timers
+                    .iter()
+                    .map(|t| FinnyTimer {
+                        timer_id: tokens_to_string(&t.get_ty(&fsm.base)),
+                    })
+                    .collect(),
+                is_final: s.is_final,
+                on_entry: s.on_entry_closure.as_ref().map(|_| format!("{}::on_entry", state_id)),
+                on_exit: s.on_exit_closure.as_ref().map(|_| format!("{}::on_exit", state_id)),
+                deferred_events: s.deferred_events.iter().map(tokens_to_string).collect(),
+                unhandled: to_info_unhandled(&s.unhandled, &state_id),
+                state_id,
+            })
+        },
+        FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::SubMachine(_), .. }) => FinnyStateKind::SubMachine(ty_to_string(&s.ty)),
+        FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::Choice, .. }) => FinnyStateKind::Choice(ty_to_string(&s.ty)),
+        FsmTransitionState::State(s @ FsmState { kind: FsmStateKind::ExitPoint, .. }) => FinnyStateKind::ExitPoint(ty_to_string(&s.ty))
     }
 }
 
 fn to_info(fsm: &FsmFnInput) -> FinnyFsm {
     let stopped_state = FinnyStateKind::Stopped;
 
+    let fsm_id = tokens_to_string(&fsm.base.fsm_ty);
+
     let finny_fsm = FinnyFsm {
-        fsm_id: tokens_to_string(&fsm.base.fsm_ty),
+        schema_version: crate::meta::FINNY_META_SCHEMA_VERSION,
+        unhandled: to_info_unhandled(&fsm.fsm.unhandled, &fsm_id),
+        type_names: HashMap::new(),
+        fsm_id,
         context_id: tokens_to_string(&fsm.base.context_ty),
         regions: fsm
             .fsm
@@ -63,18 +90,20 @@ fn to_info(fsm: &FsmFnInput) -> FinnyFsm {
                             .map(|transition| {
                                 let transition_id = tokens_to_string(&transition.transition_ty);
 
-                                let (event, transition_ty) = match transition.ty {
+                                let (event, transition_ty, action) = match transition.ty {
                                     crate::parse::FsmTransitionType::InternalTransition(
                                         ref internal,
                                     ) => (
                                         internal.event.clone(),
                                         FinnyTransitionKind::InternalTransition { state_id: to_info_state(&internal.state, fsm).get_state_id() },
+                                        &internal.action,
                                     ),
                                     crate::parse::FsmTransitionType::SelfTransition(
                                         ref self_transition,
                                     ) => (
                                         self_transition.event.clone(),
                                         FinnyTransitionKind::SelfTransition { state_id: to_info_state(&self_transition.state, fsm).get_state_id() },
+                                        &self_transition.action,
                                     ),
                                     crate::parse::FsmTransitionType::StateTransition(ref st) => (
                                         st.event.clone(),
@@ -84,27 +113,32 @@ fn to_info(fsm: &FsmFnInput) -> FinnyFsm {
                                                     .get_state_id(),
                                                 to_state: to_info_state(&st.state_to, fsm)
                                                     .get_state_id(),
+                                                entry_point: st.action.entry_point.as_ref().map(ty_to_string),
                                             },
                                         ),
+                                        &st.action,
                                     ),
                                 };
 
                                 let event = match event {
                                     crate::parse::FsmTransitionEvent::Stop => FinnyEvent::Stop,
                                     crate::parse::FsmTransitionEvent::Start => FinnyEvent::Start,
+                                    crate::parse::FsmTransitionEvent::Completion | crate::parse::FsmTransitionEvent::SubCompleted(_) => FinnyEvent::Completion,
+                                    crate::parse::FsmTransitionEvent::EntryPoint(ty) => FinnyEvent::EntryPoint(ty_to_string(&ty)),
+                                    crate::parse::FsmTransitionEvent::ExitPoint(ty) => FinnyEvent::ExitPoint(ty_to_string(&ty)),
                                     crate::parse::FsmTransitionEvent::Event(ev) => {
                                         FinnyEvent::Event(tokens_to_string(&ev.ty))
                                     }
                                 };
 
-                                (
-                                    transition_id.clone(),
-                                    FinnyTransition {
-                                        transition_id,
-                                        event,
-                                        transition: transition_ty,
-                                    },
-                                )
+                                // the closures are named after their transition
+                                FinnyTransition {
+                                    guard: action.guard.as_ref().map(|_| format!("{}::guard", transition_id)),
+                                    action: action.action.as_ref().map(|_| format!("{}::action", transition_id)),
+                                    transition_id,
+                                    event,
+                                    transition: transition_ty,
+                                }
                             })
                             .collect(),
                     },
@@ -116,97 +150,222 @@ fn to_info(fsm: &FsmFnInput) -> FinnyFsm {
     finny_fsm
 }
 
-pub fn generate_fsm_meta(fsm: &FsmFnInput) -> TokenStream {
-    let info = to_info(fsm);
+/// Writes the JSON description into the directory from `FINNY_META_DIR`, or into `OUT_DIR` of the
+/// crate's build script. Only for the machines that opted in with `meta_json`. The file is named after
+/// the machine and the hash of its description, so the machines with the same name in different modules
+/// don't overwrite each other. Returns the file's name.
+#[cfg(feature="generate_meta_json")]
+fn write_meta_json(info: &FinnyFsm, fsm_name: &str) -> syn::Result<String> {
+    use std::hash::{Hash, Hasher};
+
+    let err = |msg: String| syn::Error::new(proc_macro2::Span::call_site(), msg);
+
+    let json = serde_json::to_string_pretty(info).map_err(|e| err(format!("Failed to serialize the FSM description: {}", e)))?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    json.hash(&mut hasher);
+    let file_name = format!("{}_{:016x}.json", fsm_name, hasher.finish());
+
+    // without a directory, the description is skipped instead of failing the build
+    let dir = match std::env::var("FINNY_META_DIR").or_else(|_| std::env::var("OUT_DIR")) {
+        Ok(dir) => dir,
+        Err(_) => {
+            eprintln!("warning: The JSON description of {} isn't written, set the FINNY_META_DIR environment variable or add a build script to the crate.", info.fsm_id);
+            return Ok(file_name);
+        }
+    };
+
+    let path = std::path::Path::new(&dir).join(&file_name);
+    std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&path, json))
+        .map_err(|e| err(format!("Failed to write the FSM description to '{}': {}", path.display(), e)))?;
 
-    //let json = serde_json::to_string_pretty(&info).expect("Failed to serialize the FSM info JSON!");
+    Ok(file_name)
+}
 
+/// The methods of the info struct that return the diagram, and a test that writes it into a file.
+#[cfg(any(feature="generate_plantuml", feature="generate_dot", feature="generate_mermaid"))]
+fn generate_diagram(fsm: &FsmFnInput, info: &FinnyFsm, kind: crate::meta::diagrams::DiagramKind) -> TokenStream {
     let fsm_ty = &fsm.base.fsm_ty;
-    let fsm_ty_name = tokens_to_string(&strip_generics(fsm_ty.clone()));
     let fsm_info_ty = &fsm.base.fsm_info_ty;
-    let fsm_ty_name_snake = crate::utils::to_snake_case(&tokens_to_string(&fsm_ty));
-    let (fsm_generics_impl, fsm_generics_type, fsm_generics_where) =
-        fsm.base.fsm_generics.split_for_impl();
+    let file_name = format!("{}.{}", crate::utils::to_snake_case(&tokens_to_string(&fsm_ty)), kind.extension());
 
-    let plant_uml_test_build = {
-        #[cfg(not(feature="generate_plantuml"))]
-        { TokenStream::new() }
-        #[cfg(feature="generate_plantuml")]
-        {
-            let (plant_uml_str, additional) = crate::meta::plantuml::to_plant_uml(&info).expect("PlantUML syntax generation error!");
-
-            let test_fn_name = crate::utils::to_field_name(&crate::utils::ty_append(&fsm_ty, "_plantuml"));
-            
-            quote! {
-                #[test]
-                #[cfg(test)]
-                fn #test_fn_name () {
-                    use std::io::prelude::*;
-                    use std::fs;
-
-                    let contents = < #fsm_info_ty > :: plantuml();
-
-                    let mut f = fs::File::create(&format!("{}.plantuml", #fsm_ty_name_snake )).unwrap();
-                    f.write_all(contents.as_bytes()).unwrap();
-                }
+    let mut body = crate::meta::DiagramOutput::new();
+    crate::meta::diagrams::write_diagram_body(kind, info, None, &mut body).expect("Diagram syntax generation error!");
+    let inner_body = body.into_body();
+    let header = kind.header(&tokens_to_string(&strip_generics(fsm_ty.clone())));
+    let footer = kind.footer();
 
-                #[derive(Default)]
-                pub struct #fsm_info_ty;
+    let test_fn_name = crate::utils::to_field_name(&crate::utils::ty_append(&fsm_ty, &format!("_{}", kind.name())));
+    let method = syn::Ident::new(kind.name(), proc_macro2::Span::call_site());
+    let method_inner = syn::Ident::new(&format!("{}_inner", kind.name()), proc_macro2::Span::call_site());
 
-                impl #fsm_info_ty {
-                    pub fn plantuml_inner() -> String {
-                        use std::fmt::Write;
+    quote! {
+        #[test]
+        #[cfg(test)]
+        fn #test_fn_name () {
+            std::fs::write(#file_name, < #fsm_info_ty > :: #method ()).unwrap();
+        }
 
-                        let mut output = ( #plant_uml_str ).to_string();
+        impl #fsm_info_ty {
+            pub fn #method_inner () -> String {
+                #inner_body
+            }
 
-                        #additional
+            pub fn #method () -> String {
+                let mut output = String::new();
+                output.push_str(#header);
+                output.push_str(&Self:: #method_inner ());
+                output.push_str(#footer);
+                output
+            }
+        }
+    }
+}
 
-                        output
-                    }
+/// The description of the machine that is embedded into the binary, used to draw the live machine.
+#[cfg(feature="meta")]
+fn generate_meta_impl(fsm: &FsmFnInput, info: &FinnyFsm) -> syn::Result<TokenStream> {
+    let json = serde_json::to_string(info).map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("Failed to serialize the FSM description: {}", e)))?;
 
-                    pub fn plantuml() -> String {
-                        use std::fmt::Write;
+    let fsm_ty = &fsm.base.fsm_ty;
+    let (fsm_generics_impl, fsm_generics_type, fsm_generics_where) =
+        fsm.base.fsm_generics.split_for_impl();
+    let states_enum_ty = crate::utils::ty_append(fsm_ty, "CurrentState");
+
+    // the types are only named at runtime, with their modules and the machine's generics
+    let mut type_names = TokenStream::new();
+    let mut add_type_name = |id: String, ty: TokenStream| type_names.append_all(quote! {
+        fsm.type_names.insert(#id.to_string(), core::any::type_name::< #ty >().to_string());
+    });
+    for state in fsm.fsm.states.values() {
+        let ty = &state.ty;
+        add_type_name(ty_to_string(ty), quote! { #ty });
+
+        for timer in &state.timers {
+            let timer_ty = timer.get_ty(&fsm.base);
+            add_type_name(tokens_to_string(&timer_ty), quote! { #timer_ty #fsm_generics_type });
+        }
+    }
+    for region in &fsm.fsm.regions {
+        for transition in &region.transitions {
+            let ty = &transition.transition_ty;
+            add_type_name(tokens_to_string(ty), quote! { #ty });
+        }
+    }
 
-                        let mut output = String::new();
+    // the submachines are described and highlighted with their own implementations
+    let mut sub_metas = TokenStream::new();
+    let mut sub_highlights = TokenStream::new();
+    for state in fsm.fsm.states.values() {
+        if let FsmStateKind::SubMachine(_) = state.kind {
+            let state_types = crate::fsm::FsmTypes::new(&state.ty, &fsm.base.fsm_generics);
+            let ty = state_types.get_fsm_ty();
+            let variant = state_types.get_fsm_no_generics_ty();
+            let sub_id = ty_to_string(&state.ty);
+
+            sub_metas.append_all(quote! {
+                submachines.insert(#sub_id.to_string(), <#ty as finny::FsmMeta>::fsm_meta());
+                <#ty as finny::FsmMeta>::fsm_sub_meta(submachines);
+            });
+            sub_highlights.append_all(quote! {
+                if *state == #states_enum_ty :: #variant {
+                    let sub: & #ty = backend.states.as_ref();
+                    return Some(sub.diagram_highlight(inspect));
+                }
+            });
+        }
+    }
 
-                        writeln!(&mut output, "@startuml {}", #fsm_ty_name );
+    // without submachines, the arguments aren't used
+    let (sub_meta_args, sub_highlight_args) = if sub_metas.is_empty() {
+        (
+            quote! { _submachines: &mut std::collections::HashMap<String, finny::bundled::finny_meta::FinnyFsm> },
+            quote! { _backend: &finny::FsmBackendImpl<Self>, _state: & #states_enum_ty, _inspect: Option<&finny::inspect::highlight::InspectHighlight> }
+        )
+    } else {
+        (
+            quote! { submachines: &mut std::collections::HashMap<String, finny::bundled::finny_meta::FinnyFsm> },
+            quote! { backend: &finny::FsmBackendImpl<Self>, state: & #states_enum_ty, inspect: Option<&finny::inspect::highlight::InspectHighlight> }
+        )
+    };
 
-                        writeln!(&mut output, "{}", Self::plantuml_inner());
+    Ok(quote! {
+        impl #fsm_generics_impl finny::FsmMeta for #fsm_ty #fsm_generics_type #fsm_generics_where {
+            fn fsm_meta() -> finny::bundled::finny_meta::FinnyFsm {
+                let mut fsm = finny::bundled::finny_meta::FinnyFsm::from_json(#json).expect("The embedded FSM description is invalid");
+                #type_names
+                fsm
+            }
 
-                        writeln!(&mut output, "@enduml");
+            fn fsm_sub_meta(#sub_meta_args) {
+                #sub_metas
+            }
 
-                        output
-                    }
-                }
+            fn sub_diagram_highlight(#sub_highlight_args) -> Option<finny::bundled::finny_meta::diagrams::DiagramHighlight> {
+                #sub_highlights
+                None
             }
         }
-    };
+    })
+}
 
-    
-    
+pub fn generate_fsm_meta(fsm: &FsmFnInput) -> syn::Result<TokenStream> {
+    let info = to_info(fsm);
 
+    #[allow(unused_mut)]
+    let mut meta_json = TokenStream::new();
 
+    #[cfg(feature="generate_meta_json")]
+    {
+        if fsm.fsm.codegen_options.meta_json {
+            let file_name = write_meta_json(&info, &crate::utils::to_snake_case(&tokens_to_string(&strip_generics(fsm.base.fsm_ty.clone()))))?;
+            let fsm_ty = &fsm.base.fsm_ty;
+            let (fsm_generics_impl, fsm_generics_type, fsm_generics_where) = fsm.base.fsm_generics.split_for_impl();
 
-    /*
-    quote! {
+            meta_json.append_all(quote! {
+                impl #fsm_generics_impl #fsm_ty #fsm_generics_type #fsm_generics_where {
+                    /// The name of the file with the JSON description of the machine.
+                    pub const META_JSON_FILE: &'static str = #file_name;
+                }
+            });
+        }
+    }
 
-        #[derive(Default)]
-        pub struct #fsm_meta_ty;
+    let fsm_info_ty = &fsm.base.fsm_info_ty;
 
-        impl finny::FsmMetaJson for #fsm_info_ty {
-            fn get_json_meta() -> &'static str {
-                #json
-            }
+    #[allow(unused_mut)]
+    let mut diagrams = TokenStream::new();
 
-            fn get_plantuml_meta() -> &'static str {
+    #[cfg(feature="generate_plantuml")]
+    diagrams.append_all(generate_diagram(fsm, &info, crate::meta::diagrams::DiagramKind::PlantUml));
 
-            }
+    #[cfg(feature="generate_dot")]
+    diagrams.append_all(generate_diagram(fsm, &info, crate::meta::diagrams::DiagramKind::Dot));
+
+    #[cfg(feature="generate_mermaid")]
+    diagrams.append_all(generate_diagram(fsm, &info, crate::meta::diagrams::DiagramKind::Mermaid));
+
+    let info_ty = if diagrams.is_empty() {
+        TokenStream::new()
+    } else {
+        quote! {
+            #[derive(Default)]
+            pub struct #fsm_info_ty;
         }
+    };
 
-    }
-    */
+    #[cfg(feature="meta")]
+    let meta_impl = generate_meta_impl(fsm, &info)?;
+    #[cfg(not(feature="meta"))]
+    let meta_impl = TokenStream::new();
 
-    quote! {
-        #plant_uml_test_build
-    }
+    Ok(quote! {
+        #info_ty
+
+        #diagrams
+
+        #meta_json
+
+        #meta_impl
+    })
 }
@@ -28,6 +28,10 @@ impl FsmTypes {
         ty_append(&self.fsm_no_generics, "Events")
     }
 
+    pub fn get_fsm_event_kind_ty(&self) -> syn::Type {
+        ty_append(&self.fsm_no_generics, "EventKind")
+    }
+
     pub fn get_fsm_timers_ty(&self) -> syn::Type {
         ty_append(&self.fsm_no_generics, "Timers")
     }
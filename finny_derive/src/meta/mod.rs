@@ -1,77 +1,77 @@
-//! Structures that describe the FSM. Used by inspection frontends and documentation.
+//! The description of the FSM and the diagram generators, shared with the `finny_meta` crate.
 
-use std::collections::HashMap;
-use serde::{Serialize, Deserialize};
+pub use finny_meta::*;
 
-pub mod plantuml;
+#[cfg(any(feature="generate_plantuml", feature="generate_dot", feature="generate_mermaid"))]
+pub use self::diagram_output::*;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FinnyFsm {
-    pub fsm_id: String,
-    pub context_id: String,
-    pub regions: HashMap<usize, FinnyRegion>
-}
+#[cfg(any(feature="generate_plantuml", feature="generate_dot", feature="generate_mermaid"))]
+mod diagram_output {
+    use proc_macro2::{Span, TokenStream};
+    use quote::{quote, TokenStreamExt};
+    use std::fmt;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FinnyRegion {
-    pub region_id: usize,
-    pub states: HashMap<String, FinnyStateKind>,
-    pub transitions: HashMap<String, FinnyTransition>
-}
+    use finny_meta::diagrams::{DiagramHighlight, DiagramKind, DiagramWriter};
 
+    /// The source of a diagram. The diagrams of the submachines are inserted in place, by calling
+    /// their generated methods at runtime.
+    pub struct DiagramOutput {
+        tokens: TokenStream,
+        pending: String
+    }
 
+    impl DiagramOutput {
+        pub fn new() -> Self {
+            DiagramOutput {
+                tokens: TokenStream::new(),
+                pending: String::new()
+            }
+        }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub enum FinnyStateKind {
-    Stopped,
-    State(FinnyState),
-    SubMachine(String)
-}
+        /// Insert the output of the `method` of the submachine's info struct.
+        pub fn append_sub(&mut self, sub_id: &str, method: &str) {
+            self.flush();
 
-impl FinnyStateKind {
-    pub fn get_state_id(&self) -> String {
-        match self {
-            FinnyStateKind::Stopped => "Stopped".into(),
-            FinnyStateKind::State(s) => s.state_id.clone(),
-            FinnyStateKind::SubMachine(id) => id.clone()
+            let info_ty = syn::parse_str::<syn::Type>(&format!("{}Info", sub_id)).unwrap();
+            let method = syn::Ident::new(method, Span::call_site());
+            self.tokens.append_all(quote! {
+                output.push_str(&< #info_ty > :: #method ());
+            });
         }
-    }
-}
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FinnyState {
-    pub state_id: String,
-    pub timers: Vec<FinnyTimer>
-}
+        fn flush(&mut self) {
+            if !self.pending.is_empty() {
+                let pending = std::mem::take(&mut self.pending);
+                self.tokens.append_all(quote! {
+                    output.push_str(#pending);
+                });
+            }
+        }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FinnyTransition {
-    pub transition_id: String,
-    pub event: FinnyEvent,
-    pub transition: FinnyTransitionKind
-}
+        /// The body of the function that returns the diagram.
+        pub fn into_body(mut self) -> TokenStream {
+            self.flush();
+            let tokens = self.tokens;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub enum FinnyEvent {
-    Start,
-    Stop,
-    Event(String)
-}
+            quote! {
+                let mut output = String::new();
+                #tokens
+                output
+            }
+        }
+    }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub enum FinnyTransitionKind {
-    SelfTransition { state_id: String },
-    InternalTransition { state_id: String },
-    NormalTransition(FinnyTransitionNormal)
-}
+    impl DiagramWriter for DiagramOutput {
+        fn write_sub(&mut self, kind: DiagramKind, sub_id: &str, _highlight: Option<&DiagramHighlight>) -> fmt::Result {
+            self.append_sub(sub_id, &format!("{}_inner", kind.name()));
+            Ok(())
+        }
+    }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FinnyTransitionNormal {
-    pub from_state: String,
-    pub to_state: String
+    impl fmt::Write for DiagramOutput {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.pending.push_str(s);
+            Ok(())
+        }
+    }
 }
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct FinnyTimer {
-    pub timer_id: String
-}
\ No newline at end of file
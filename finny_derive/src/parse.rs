@@ -131,7 +131,8 @@ pub struct FsmDeclarations {
     pub initial_states: Vec<syn::Type>,
     pub states: HashMap<syn::Type, FsmState>,
     pub events: HashMap<syn::Type, FsmEvent>,
-    pub transitions: Vec<FsmTransition>
+    pub transitions: Vec<FsmTransition>,
+    pub unhandled: FsmUnhandledPolicy
 }
 
 #[derive(Debug)]
@@ -139,7 +140,8 @@ pub struct ValidatedFsm {
     pub codegen_options: FsmCodegenOptions,
     pub regions: Vec<FsmRegion>,
     pub states: HashMap<syn::Type, FsmState>,
-    pub events: HashMap<syn::Type, FsmEvent>
+    pub events: HashMap<syn::Type, FsmEvent>,
+    pub unhandled: FsmUnhandledPolicy
 }
 
 #[derive(Debug)]
@@ -160,7 +162,15 @@ pub enum FsmTransitionState {
 pub enum FsmTransitionEvent {
     Stop,
     Start,
-    Event(FsmEvent)
+    Event(FsmEvent),
+    /// Triggered automatically after entering the state.
+    Completion,
+    /// Enters the machine directly into a state, instead of starting it in the initial state.
+    EntryPoint(syn::Type),
+    /// The submachine has reached this exit point.
+    ExitPoint(syn::Type),
+    /// All the regions of this submachine have reached their final states.
+    SubCompleted(syn::Type)
 }
 
 impl FsmTransitionEvent {
@@ -170,6 +180,22 @@ impl FsmTransitionEvent {
             _ => Err(syn::Error::new(Span::call_site(), "Missing event here, codegen bug!"))
         }
     }
+
+    /// The type of the event that is passed to the guards and actions.
+    pub fn get_event_ty(&self) -> syn::Result<syn::Type> {
+        match self {
+            FsmTransitionEvent::Event(ev) => Ok(ev.ty.clone()),
+            FsmTransitionEvent::Completion => Ok(syn::parse_quote! { finny::FsmCompletionEvent }),
+            FsmTransitionEvent::ExitPoint(ty) => Ok(ty.clone()),
+            FsmTransitionEvent::SubCompleted(ty) => Ok(syn::parse_quote! { finny::SubCompleted<#ty> }),
+            _ => Err(syn::Error::new(Span::call_site(), "Missing event here, codegen bug!"))
+        }
+    }
+
+    /// Checked by the machine itself after entering the state, instead of being dispatched.
+    pub fn is_completion(&self) -> bool {
+        matches!(self, FsmTransitionEvent::Completion | FsmTransitionEvent::SubCompleted(_))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -229,12 +255,23 @@ pub struct FsmStateTransition {
 #[derive(Debug, Clone, PartialEq)]
 pub enum FsmStateKind {
     Normal,
-    SubMachine(FsmSubMachineOptions)
+    SubMachine(FsmSubMachineOptions),
+    /// A transient pseudo-state, left immediately through one of its completion transitions.
+    Choice,
+    /// Reaching this pseudo-state notifies the parent machine, which can then leave the submachine.
+    ExitPoint
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct FsmSubMachineOptions {
-    pub context_constructor: Option<syn::ExprClosure>
+    pub context_constructor: Option<syn::ExprClosure>,
+    pub history: Option<FsmHistory>
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FsmHistory {
+    Shallow,
+    Deep
 }
 
 #[derive(Debug, Clone)]
@@ -244,7 +281,27 @@ pub struct FsmState {
     pub state_storage_field: syn::Ident,
     pub on_entry_closure: Option<syn::ExprClosure>,
     pub on_exit_closure: Option<syn::ExprClosure>,
-    pub timers: Vec<FsmTimer>
+    /// The entry actions that receive the event which triggered the transition.
+    pub on_entry_with: Vec<(syn::Type, syn::ExprClosure)>,
+    /// The exit actions that receive the event which triggered the transition.
+    pub on_exit_with: Vec<(syn::Type, syn::ExprClosure)>,
+    /// Rebuild the state every time it is entered.
+    pub construct_on_entry: bool,
+    /// The events from which the state is built, when it is entered by them.
+    pub construct_on_entry_from: Vec<syn::Type>,
+    pub timers: Vec<FsmTimer>,
+    pub unhandled: FsmUnhandledPolicy,
+    pub deferred_events: Vec<syn::Type>,
+    /// Reaching this state completes the region.
+    pub is_final: bool
+}
+
+/// What to do with the events that none of the transitions match.
+#[derive(Default, Debug, Clone)]
+pub struct FsmUnhandledPolicy {
+    pub ignored_events: Vec<syn::Type>,
+    pub ignore_all: bool,
+    pub handler: Option<syn::ExprClosure>
 }
 
 #[derive(Debug, Clone)]
@@ -289,7 +346,11 @@ pub enum FsmEventTransition {
 pub struct EventGuardAction{
     pub guard: Option<syn::ExprClosure>,
     pub action: Option<syn::ExprClosure>,
-    pub type_hint: Option<syn::Type>
+    /// The action returns a future, the target state is entered once it completes.
+    pub is_async: bool,
+    pub type_hint: Option<syn::Type>,
+    /// Enter the target submachine through this entry point.
+    pub entry_point: Option<syn::Type>
 }
 
 impl FsmDeclarations {
@@ -3,17 +3,26 @@ use std::collections::HashMap;
 use proc_macro2::Span;
 use syn::{ExprMethodCall, ItemFn, Type, spanned::Spanned};
 
-use crate::{parse::{EventGuardAction, FsmDeclarations, FsmEvent, FsmEventTransition, FsmFnBase, FsmState, FsmStateAction, FsmStateKind, FsmStateTransition, FsmSubMachineOptions, FsmTimer, FsmTransition, FsmTransitionEvent, FsmTransitionState, FsmTransitionType, ValidatedFsm}, parse_blocks::{FsmBlock, get_generics}, utils::{assert_no_generics, to_field_name, get_closure}, validation::create_regions};
+use crate::{parse::{EventGuardAction, FsmDeclarations, FsmEvent, FsmEventTransition, FsmFnBase, FsmHistory, FsmState, FsmStateAction, FsmStateKind, FsmStateTransition, FsmSubMachineOptions, FsmTimer, FsmTransition, FsmTransitionEvent, FsmTransitionState, FsmTransitionType, FsmUnhandledPolicy, ValidatedFsm}, parse_blocks::{FsmBlock, get_generics}, utils::{assert_no_generics, to_field_name, get_closure}, validation::create_regions};
 
 #[derive(Copy, Clone, Debug)]
 pub struct FsmCodegenOptions {
-    pub event_debug: bool
+    pub event_debug: bool,
+    /// Store only the active state of each region.
+    pub enum_storage: bool,
+    /// Derive the serialization for the snapshots.
+    pub serde_snapshots: bool,
+    /// Write the JSON description of the machine.
+    pub meta_json: bool
 }
 
 impl FsmCodegenOptions {
     pub fn new() -> Self {
         Self {
-            event_debug: false
+            event_debug: false,
+            enum_storage: false,
+            serde_snapshots: false,
+            meta_json: false
         }
     }
 }
@@ -24,7 +33,12 @@ pub struct FsmParser {
     events: HashMap<Type, FsmEvent>,
     options: FsmCodegenOptions,
     base: FsmFnBase,
-    timer_id: usize
+    timer_id: usize,
+    unhandled: FsmUnhandledPolicy,
+    completions: Vec<(FsmTransitionEvent, FsmEventTransition)>,
+    choices: Vec<(Type, Option<FsmEventTransition>)>,
+    entry_points: Vec<(Type, Type)>,
+    exit_points: Vec<(Type, Type, Type, EventGuardAction)>
 }
 
 impl FsmParser {
@@ -35,7 +49,12 @@ impl FsmParser {
             events: HashMap::new(),
             options: FsmCodegenOptions::new(),
             base,
-            timer_id: 1
+            timer_id: 1,
+            unhandled: FsmUnhandledPolicy::default(),
+            completions: vec![],
+            choices: vec![],
+            entry_points: vec![],
+            exit_points: vec![]
         }
     }
 
@@ -58,6 +77,21 @@ impl FsmParser {
                         [MethodOverviewRef { name: "events_debug", generics: [], .. }] => {
                             self.options.event_debug = true;
                         },
+                        [MethodOverviewRef { name: "enum_state_storage", generics: [], .. }] => {
+                            self.options.enum_storage = true;
+                        },
+                        [m @ MethodOverviewRef { name: "serde_snapshots", generics: [], .. }] => {
+                            if !cfg!(feature = "serde") {
+                                return Err(syn::Error::new(m.call.span(), "The snapshots require the 'serde' feature of finny."));
+                            }
+                            self.options.serde_snapshots = true;
+                        },
+                        [m @ MethodOverviewRef { name: "meta_json", generics: [], .. }] => {
+                            if !cfg!(feature = "generate_meta_json") {
+                                return Err(syn::Error::new(m.call.span(), "The JSON description requires the 'generate_meta_json' feature of finny."));
+                            }
+                            self.options.meta_json = true;
+                        },
                         [MethodOverviewRef { name: "initial_state", generics: [ty], .. }] => {
                             assert_no_generics(ty)?;
                             if self.initial_states.len() > 0 { return Err(syn::Error::new(ty.span(), "Duplicate initial_state!")); }
@@ -78,6 +112,12 @@ impl FsmParser {
                             }
                         },
 
+                        [m @ MethodOverviewRef { name: "ignore_event", .. }] |
+                        [m @ MethodOverviewRef { name: "ignore_all_unhandled", .. }] |
+                        [m @ MethodOverviewRef { name: "on_unhandled", .. }] => {
+                            Self::parse_unhandled(&mut self.unhandled, &mut self.events, m)?;
+                        },
+
                         [MethodOverviewRef { name: "sub_machine", generics: [ty_sub_fsm], ..}, st @ .. ] => {
 
                             //assert_no_generics(ty_sub_fsm)?;
@@ -90,29 +130,45 @@ impl FsmParser {
                                     state_storage_field: field_name,
                                     on_entry_closure: None,
                                     on_exit_closure: None,
+                                    on_entry_with: vec![],
+                                    on_exit_with: vec![],
+                                    construct_on_entry: false,
+                                    construct_on_entry_from: vec![],
                                     kind: FsmStateKind::SubMachine(FsmSubMachineOptions::default()),
-                                    timers: vec![]
+                                    timers: vec![],
+                                    unhandled: FsmUnhandledPolicy::default(),
+                                    deferred_events: vec![],
+                                    is_final: false
                                 });
                             let mut sub_options = match state.kind {                                
                                 FsmStateKind::SubMachine(ref sub) => sub.clone(),
                                 _ => { return Err(syn::Error::new(ty_sub_fsm.span(), "Internal error with sub machines.")); }
                             };
 
-                            match st {
-                                [with_context @ MethodOverviewRef { name: "with_context", .. }, st @ .. ] => {
-                                    let closure = get_closure(&with_context.call)?;
-                                    if sub_options.context_constructor.is_some() {
-                                        return Err(syn::Error::new(closure.span(), "Duplicate constructor for the context!"));
+                            let mut st = st;
+                            loop {
+                                match st {
+                                    [with_context @ MethodOverviewRef { name: "with_context", .. }, rest @ .. ] => {
+                                        let closure = get_closure(&with_context.call)?;
+                                        if sub_options.context_constructor.is_some() {
+                                            return Err(syn::Error::new(closure.span(), "Duplicate constructor for the context!"));
+                                        }
+                                        sub_options.context_constructor = Some(closure.clone());
+                                        st = rest;
+                                    },
+                                    [with_history @ MethodOverviewRef { name: "with_history", generics: [], .. }, rest @ .. ] => {
+                                        if sub_options.history.is_some() {
+                                            return Err(syn::Error::new(with_history.call.span(), "Duplicate 'with_history'!"));
+                                        }
+                                        sub_options.history = Some(Self::parse_history(with_history)?);
+                                        st = rest;
+                                    },
+                                    [st @ ..] => {
+                                        self.state_builder_parser(&ty_sub_fsm, st, true)?;
+                                        break;
                                     }
-                                    sub_options.context_constructor = Some(closure.clone());
-
-                                    self.state_builder_parser(&ty_sub_fsm, st, true)?;
-                                },
-                                [st @ ..] => {
-                                    self.state_builder_parser(&ty_sub_fsm, st, true)?;
-                                },
-                                _ => { return Err(syn::Error::new(ty_sub_fsm.span(), "Missing with_context?")); }
-                            }                          
+                                }
+                            }
 
                             // update the options
                             self.states.entry(ty_sub_fsm.clone()).and_modify(|s| {
@@ -121,6 +177,60 @@ impl FsmParser {
                             
                         },
 
+                        [MethodOverviewRef { name: "entry_point", generics: [ty_entry], .. }, MethodOverviewRef { name: "transition_to", generics: [ty_to], .. }] => {
+                            assert_no_generics(ty_entry)?;
+                            if self.entry_points.iter().any(|(ty, _)| ty == ty_entry) {
+                                return Err(syn::Error::new(ty_entry.span(), "Duplicate 'entry_point'!"));
+                            }
+                            self.entry_points.push((ty_entry.clone(), ty_to.clone()));
+                            self.events
+                                .entry(ty_entry.clone())
+                                .or_insert(FsmEvent { ty: ty_entry.clone(), transitions: vec![] });
+                        },
+
+                        [MethodOverviewRef { name: "exit_point", generics: [ty_exit], .. }] => {
+                            assert_no_generics(ty_exit)?;
+                            if self.states.contains_key(ty_exit) {
+                                return Err(syn::Error::new(ty_exit.span(), "Duplicate 'exit_point'!"));
+                            }
+                            self.states.insert(ty_exit.clone(), FsmState {
+                                ty: ty_exit.clone(),
+                                on_entry_closure: None,
+                                on_exit_closure: None,
+                                on_entry_with: vec![],
+                                on_exit_with: vec![],
+                                construct_on_entry: false,
+                                construct_on_entry_from: vec![],
+                                state_storage_field: to_field_name(ty_exit),
+                                kind: FsmStateKind::ExitPoint,
+                                timers: vec![],
+                                unhandled: FsmUnhandledPolicy::default(),
+                                deferred_events: vec![],
+                                is_final: false
+                            });
+                            self.events
+                                .entry(ty_exit.clone())
+                                .or_insert(FsmEvent { ty: ty_exit.clone(), transitions: vec![] });
+                        },
+
+                        [MethodOverviewRef { name: "final_state", generics: [ty_state], .. }, st @ .. ] => {
+
+                            self.state_builder_parser(ty_state, st, false)?;
+
+                            let state = self.states.get_mut(ty_state).unwrap();
+                            if state.is_final {
+                                return Err(syn::Error::new(ty_state.span(), "Duplicate 'final_state'!"));
+                            }
+                            state.is_final = true;
+
+                        },
+
+                        [MethodOverviewRef { name: "choice", generics: [ty_choice], .. }, st @ .. ] => {
+
+                            self.choice_builder_parser(ty_choice, st)?;
+
+                        },
+
                         [MethodOverviewRef { name: "state", generics: [ty_state], .. }, st @ .. ] => {
 
                             self.state_builder_parser(ty_state, st, false)?;
@@ -139,7 +249,7 @@ impl FsmParser {
     }
 
     fn parse_event_guard_action(event_method_calls: &[MethodOverviewRef]) -> syn::Result<EventGuardAction> {
-        let mut guard_action = EventGuardAction { guard: None, action: None, type_hint: None };
+        let mut guard_action = EventGuardAction { guard: None, action: None, is_async: false, type_hint: None, entry_point: None };
         
         for method in event_method_calls {
             match method {
@@ -152,7 +262,7 @@ impl FsmParser {
 
                     guard_action.guard = Some(closure.clone());
                 },
-                MethodOverviewRef { name: "action", .. } => {
+                MethodOverviewRef { name: name @ ("action" | "action_async"), .. } => {
                     let closure = get_closure(method.call)?;
 
                     if guard_action.action.is_some() {
@@ -160,6 +270,7 @@ impl FsmParser {
                     }
 
                     guard_action.action = Some(closure.clone());
+                    guard_action.is_async = *name == "action_async";
                 },
                 MethodOverviewRef { name: "with_transition_ty", generics: [transition_ty], ..}  => {
 
@@ -170,6 +281,15 @@ impl FsmParser {
                     guard_action.type_hint = Some(transition_ty.clone());
 
                 },
+                MethodOverviewRef { name: "entry_point", generics: [entry_point_ty], ..}  => {
+                    assert_no_generics(entry_point_ty)?;
+
+                    if guard_action.entry_point.is_some() {
+                        return Err(syn::Error::new(method.call.span(), "Duplicate 'entry_point'!"));
+                    }
+
+                    guard_action.entry_point = Some(entry_point_ty.clone());
+                },
                 _ => { return Err(syn::Error::new(method.call.span(), "Unsupported method.")); }
             }
         }
@@ -177,6 +297,53 @@ impl FsmParser {
         Ok(guard_action)
     }
 
+    fn parse_history(method: &MethodOverviewRef) -> syn::Result<FsmHistory> {
+        let variant = match method.call.args.iter().collect::<Vec<_>>().as_slice() {
+            [syn::Expr::Path(p)] => p.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None
+        };
+
+        match variant.as_deref() {
+            Some("Shallow") => Ok(FsmHistory::Shallow),
+            Some("Deep") => Ok(FsmHistory::Deep),
+            _ => Err(syn::Error::new(method.call.args.span(), "Expected 'History::Shallow' or 'History::Deep'."))
+        }
+    }
+
+    fn parse_unhandled(unhandled: &mut FsmUnhandledPolicy, events: &mut HashMap<Type, FsmEvent>, method: &MethodOverviewRef) -> syn::Result<()> {
+        match method {
+            MethodOverviewRef { name: "ignore_event", generics: [ty_event], .. } => {
+                assert_no_generics(ty_event)?;
+                if unhandled.ignored_events.contains(ty_event) {
+                    return Err(syn::Error::new(ty_event.span(), "Duplicate 'ignore_event'!"));
+                }
+                unhandled.ignored_events.push(ty_event.clone());
+                events
+                    .entry(ty_event.clone())
+                    .or_insert(FsmEvent { ty: ty_event.clone(), transitions: vec![] });
+            },
+            MethodOverviewRef { name: "ignore_all_unhandled", generics: [], .. } => {
+                if unhandled.handler.is_some() {
+                    return Err(syn::Error::new(method.call.span(), "Use either 'ignore_all_unhandled' or 'on_unhandled'!"));
+                }
+                unhandled.ignore_all = true;
+            },
+            MethodOverviewRef { name: "on_unhandled", generics: [], .. } => {
+                let closure = get_closure(method.call)?;
+                if unhandled.handler.is_some() {
+                    return Err(syn::Error::new(closure.span(), "Duplicate 'on_unhandled'!"));
+                }
+                if unhandled.ignore_all {
+                    return Err(syn::Error::new(closure.span(), "Use either 'ignore_all_unhandled' or 'on_unhandled'!"));
+                }
+                unhandled.handler = Some(closure.clone());
+            },
+            _ => { return Err(syn::Error::new(method.call.span(), "Unsupported method.")); }
+        }
+
+        Ok(())
+    }
+
     fn parse_state_on_event(state: &FsmState, event: &mut FsmEvent, method_calls: &[MethodOverviewRef]) -> syn::Result<()> {
         match method_calls {
             [MethodOverviewRef { name: "transition_to", generics: [ty_to], .. }, ev @ .. ] => {
@@ -275,13 +442,71 @@ impl FsmParser {
                     }
                 }
             }
+
+            // entering the machine through the entry points
+            for (ty_entry, ty_to) in &self.entry_points {
+                let to = self.states.get(ty_to).ok_or(syn::Error::new(ty_to.span(), "State not found."))?;
+
+                transitions.push(FsmTransition {
+                    transition_ty: generate_transition_ty(&self.base, &mut i, &None),
+                    ty: FsmTransitionType::StateTransition(FsmStateTransition {
+                        action: EventGuardAction::default(),
+                        event: FsmTransitionEvent::EntryPoint(ty_entry.clone()),
+                        state_from: FsmTransitionState::None,
+                        state_to: FsmTransitionState::State(to.clone())
+                    })
+                });
+            }
+
+            // leaving the submachines through their exit points
+            for (ty_sub, ty_exit, ty_to, action) in &self.exit_points {
+                let from = self.states.get(ty_sub).ok_or(syn::Error::new(ty_sub.span(), "State not found."))?;
+                let to = self.states.get(ty_to).ok_or(syn::Error::new(ty_to.span(), "State not found."))?;
+
+                transitions.push(FsmTransition {
+                    transition_ty: generate_transition_ty(&self.base, &mut i, &action.type_hint),
+                    ty: FsmTransitionType::StateTransition(FsmStateTransition {
+                        action: action.clone(),
+                        event: FsmTransitionEvent::ExitPoint(ty_exit.clone()),
+                        state_from: FsmTransitionState::State(from.clone()),
+                        state_to: FsmTransitionState::State(to.clone())
+                    })
+                });
+            }
+
+            // the fallback transitions of the choices are checked last
+            for (ty_choice, otherwise) in &self.choices {
+                match otherwise {
+                    Some(t) => self.completions.push((FsmTransitionEvent::Completion, t.clone())),
+                    None => { return Err(syn::Error::new(ty_choice.span(), "The choice requires an 'otherwise' transition!")); }
+                }
+            }
+
+            // completion transitions, in the declaration order
+            for (event, t) in &self.completions {
+                if let FsmEventTransition::State(from, to, action) = t {
+                    let from = self.states.get(from).ok_or(syn::Error::new(from.span(), "State not found."))?;
+                    let to = self.states.get(to).ok_or(syn::Error::new(to.span(), "State not found."))?;
+
+                    transitions.push(FsmTransition {
+                        transition_ty: generate_transition_ty(&self.base, &mut i, &action.type_hint),
+                        ty: FsmTransitionType::StateTransition(FsmStateTransition {
+                            action: action.clone(),
+                            state_from: FsmTransitionState::State(from.clone()),
+                            state_to: FsmTransitionState::State(to.clone()),
+                            event: event.clone()
+                        })
+                    });
+                }
+            }
         }
                 
         let dec = FsmDeclarations {
             initial_states: self.initial_states,
             states: self.states,
             events: self.events,
-            transitions
+            transitions,
+            unhandled: self.unhandled
         };
 
         let regions = create_regions(dec, self.options)?;
@@ -289,6 +514,58 @@ impl FsmParser {
         Ok(regions)
     }
 
+    fn choice_builder_parser(&mut self, ty_choice: &syn::Type, st: &[MethodOverviewRef]) -> syn::Result<()> {
+        assert_no_generics(ty_choice)?;
+        let field_name = to_field_name(&ty_choice);
+        let choice = self.states
+            .entry(ty_choice.clone())
+            .or_insert(FsmState {
+                ty: ty_choice.clone(),
+                on_entry_closure: None,
+                on_exit_closure: None,
+                on_entry_with: vec![],
+                on_exit_with: vec![],
+                construct_on_entry: false,
+                construct_on_entry_from: vec![],
+                state_storage_field: field_name,
+                kind: FsmStateKind::Choice,
+                timers: vec![],
+                unhandled: FsmUnhandledPolicy::default(),
+                deferred_events: vec![],
+                is_final: false
+            });
+
+        if choice.kind != FsmStateKind::Choice {
+            return Err(syn::Error::new(ty_choice.span(), "This type is already used as a state!"));
+        }
+
+        if !self.choices.iter().any(|(ty, _)| ty == ty_choice) {
+            self.choices.push((ty_choice.clone(), None));
+        }
+
+        match st {
+            [MethodOverviewRef { name: "transition_to", generics: [ty_to], .. }, ev @ .. ] => {
+                self.completions.push((FsmTransitionEvent::Completion, FsmEventTransition::State(ty_choice.clone(), ty_to.clone(), Self::parse_event_guard_action(ev)?)));
+            },
+            [m @ MethodOverviewRef { name: "otherwise", generics: [ty_to], .. }, ev @ .. ] => {
+                let action = Self::parse_event_guard_action(ev)?;
+                if let Some(guard) = &action.guard {
+                    return Err(syn::Error::new(guard.span(), "The 'otherwise' transition can't have a guard!"));
+                }
+
+                let otherwise = self.choices.iter_mut().find(|(ty, _)| ty == ty_choice).map(|(_, o)| o).unwrap();
+                if otherwise.is_some() {
+                    return Err(syn::Error::new(m.call.span(), "Duplicate 'otherwise'!"));
+                }
+                *otherwise = Some(FsmEventTransition::State(ty_choice.clone(), ty_to.clone(), action));
+            },
+            [] => (),
+            _ => { return Err(syn::Error::new(st[0].call.span(), "Only 'transition_to' and 'otherwise' are supported for the choices.")); }
+        }
+
+        Ok(())
+    }
+
     fn state_builder_parser(&mut self, ty_state: &syn::Type, st: &[MethodOverviewRef], is_sub_fsm: bool) -> syn::Result<()> {
         if !is_sub_fsm { assert_no_generics(ty_state)?; }
         let field_name = to_field_name(&ty_state);
@@ -298,11 +575,22 @@ impl FsmParser {
                 ty: ty_state.clone(),
                 on_entry_closure: None,
                 on_exit_closure: None,
+                on_entry_with: vec![],
+                on_exit_with: vec![],
+                construct_on_entry: false,
+                construct_on_entry_from: vec![],
                 state_storage_field: field_name,
                 kind: FsmStateKind::Normal,
-                timers: vec![]
+                timers: vec![],
+                unhandled: FsmUnhandledPolicy::default(),
+                deferred_events: vec![],
+                is_final: false
             });
 
+        if state.kind == FsmStateKind::Choice {
+            return Err(syn::Error::new(ty_state.span(), "This type is already used as a choice!"));
+        }
+
             
         let mut timer = None;
 
@@ -324,6 +612,69 @@ impl FsmParser {
                     }
                     state.on_exit_closure = Some(closure.clone());
                 },
+                MethodOverviewRef { name: "on_entry_with", generics: [ty_event], .. } => {
+                    assert_no_generics(ty_event)?;
+                    let closure = get_closure(&method.call)?;
+
+                    if state.on_entry_with.iter().any(|(ty, _)| ty == ty_event) {
+                        return Err(syn::Error::new(closure.span(), "Duplicate 'on_entry_with' for this event!"));
+                    }
+                    state.on_entry_with.push((ty_event.clone(), closure.clone()));
+                },
+                MethodOverviewRef { name: "on_exit_with", generics: [ty_event], .. } => {
+                    assert_no_generics(ty_event)?;
+                    let closure = get_closure(&method.call)?;
+
+                    if state.on_exit_with.iter().any(|(ty, _)| ty == ty_event) {
+                        return Err(syn::Error::new(closure.span(), "Duplicate 'on_exit_with' for this event!"));
+                    }
+                    state.on_exit_with.push((ty_event.clone(), closure.clone()));
+                },
+                MethodOverviewRef { name: "construct_on_entry", .. } |
+                MethodOverviewRef { name: "construct_on_entry_from", .. } if is_sub_fsm => {
+                    return Err(syn::Error::new(method.call.span(), "The submachines can't be constructed on entry."));
+                },
+                MethodOverviewRef { name: "construct_on_entry", .. } => {
+                    state.construct_on_entry = true;
+                },
+                MethodOverviewRef { name: "construct_on_entry_from", generics: [ty_event], .. } => {
+                    assert_no_generics(ty_event)?;
+                    if state.construct_on_entry_from.contains(ty_event) {
+                        return Err(syn::Error::new(ty_event.span(), "Duplicate 'construct_on_entry_from'!"));
+                    }
+                    state.construct_on_entry = true;
+                    state.construct_on_entry_from.push(ty_event.clone());
+                },
+                MethodOverviewRef { name: "defer_event", generics: [ty_event], .. } => {
+                    assert_no_generics(ty_event)?;
+                    if state.deferred_events.contains(ty_event) {
+                        return Err(syn::Error::new(ty_event.span(), "Duplicate 'defer_event'!"));
+                    }
+                    state.deferred_events.push(ty_event.clone());
+                    self.events
+                        .entry(ty_event.clone())
+                        .or_insert(FsmEvent { ty: ty_event.clone(), transitions: vec![] });
+                },
+                MethodOverviewRef { name: "ignore_event", .. } |
+                MethodOverviewRef { name: "ignore_all_unhandled", .. } |
+                MethodOverviewRef { name: "on_unhandled", .. } => {
+                    Self::parse_unhandled(&mut state.unhandled, &mut self.events, method)?;
+                },
+                MethodOverviewRef { name: "on_event", generics: [ty_event], .. } if get_sub_completed_ty(ty_event).is_some() => {
+                    let ty_sub = get_sub_completed_ty(ty_event).unwrap();
+                    if !is_sub_fsm || ty_sub != state.ty {
+                        return Err(syn::Error::new(ty_event.span(), "Only the submachine's own completion is supported here."));
+                    }
+
+                    match &st[(i+1)..] {
+                        [MethodOverviewRef { name: "transition_to", generics: [ty_to], .. }, ev @ .. ] => {
+                            self.completions.push((FsmTransitionEvent::SubCompleted(ty_sub), FsmEventTransition::State(state.ty.clone(), ty_to.clone(), Self::parse_event_guard_action(ev)?)));
+                        },
+                        _ => { return Err(syn::Error::new(method.call.span(), "Only 'transition_to' is supported for the completion transitions.")); }
+                    }
+
+                    break;
+                },
                 MethodOverviewRef { name: "on_event", generics: [ty_event], .. } => {
                     assert_no_generics(ty_event)?;
 
@@ -336,6 +687,27 @@ impl FsmParser {
 
                     break;
                 },
+                MethodOverviewRef { name: "on_exit_point", generics: [ty_exit], .. } if is_sub_fsm => {
+                    assert_no_generics(ty_exit)?;
+                    match &st[(i+1)..] {
+                        [MethodOverviewRef { name: "transition_to", generics: [ty_to], .. }, ev @ .. ] => {
+                            self.exit_points.push((state.ty.clone(), ty_exit.clone(), ty_to.clone(), Self::parse_event_guard_action(ev)?));
+                        },
+                        _ => { return Err(syn::Error::new(method.call.span(), "Only 'transition_to' is supported for the exit points.")); }
+                    }
+
+                    break;
+                },
+                MethodOverviewRef { name: "on_completion", generics: [], .. } => {
+                    match &st[(i+1)..] {
+                        [MethodOverviewRef { name: "transition_to", generics: [ty_to], .. }, ev @ .. ] => {
+                            self.completions.push((FsmTransitionEvent::Completion, FsmEventTransition::State(state.ty.clone(), ty_to.clone(), Self::parse_event_guard_action(ev)?)));
+                        },
+                        _ => { return Err(syn::Error::new(method.call.span(), "Only 'transition_to' is supported for the completion transitions.")); }
+                    }
+
+                    break;
+                },
                 MethodOverviewRef { name: "on_entry_start_timer", generics: [], .. } => {
 
                     let call_args: Vec<_> = method.call.args.iter().collect();
@@ -383,6 +755,24 @@ impl FsmParser {
 }
 
 
+/// The submachine of the `SubCompleted<F>` event.
+fn get_sub_completed_ty(ty: &syn::Type) -> Option<syn::Type> {
+    let segment = match ty {
+        Type::Path(tp) => tp.path.segments.last()?,
+        _ => return None
+    };
+
+    if segment.ident != "SubCompleted" { return None; }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first() {
+            Some(syn::GenericArgument::Type(ty_sub)) => Some(ty_sub.clone()),
+            _ => None
+        },
+        _ => None
+    }
+}
+
 struct MethodOverview {
     name: String,
     generics: Vec<syn::Type>,
@@ -4,7 +4,7 @@ use petgraph::{Graph, graph::NodeIndex, visit::Dfs};
 use proc_macro2::Span;
 use syn::spanned::Spanned;
 
-use crate::{parse::{FsmDeclarations, FsmRegion, ValidatedFsm}, parse_fsm::FsmCodegenOptions, utils::tokens_to_string};
+use crate::{parse::{EventGuardAction, FsmDeclarations, FsmRegion, FsmStateAction, FsmStateKind, FsmStateTransition, FsmTransitionEvent, FsmTransitionState, FsmTransitionType, ValidatedFsm}, parse_fsm::FsmCodegenOptions, utils::tokens_to_string};
 
 #[derive(Debug)]
 struct TypeNode {
@@ -57,12 +57,81 @@ pub fn create_regions(decl: FsmDeclarations, options: FsmCodegenOptions) -> syn:
         }
     }
 
+    // the states that are only reachable through an entry point
+    for transition in &decl.transitions {
+        if let FsmTransitionType::StateTransition(FsmStateTransition { event: FsmTransitionEvent::EntryPoint(ty_entry), state_to: FsmTransitionState::State(st), .. }) = &transition.ty {
+            let start_node = get_or_add_node(&mut nodes, &mut graph, &st.ty);
+            if graph[start_node].region.is_some() { continue; }
+
+            if decl.initial_states.len() > 1 {
+                return Err(syn::Error::new(ty_entry.span(), &format!("The state '{}' of this entry point has to be reachable from one of the initial states, as the machine has multiple regions!",
+                    tokens_to_string(&st.ty))));
+            }
+
+            let mut dfs = Dfs::new(&graph, start_node);
+            while let Some(idx) = dfs.next(&graph) {
+                graph[idx].region = Some(0);
+            }
+        }
+    }
+
     for node in graph.raw_nodes() {
         if node.weight.region == None {
             return Err(syn::Error::new(node.weight.state.span(), "Unreachable state! Add some transitions that will make this state reachable!"));
         }
     }
 
+    for transition in &decl.transitions {
+        match &transition.ty {
+            // nothing leaves the exit points, the parent machine has to react to them
+            FsmTransitionType::InternalTransition(FsmStateAction { state: FsmTransitionState::State(st), .. }) |
+            FsmTransitionType::SelfTransition(FsmStateAction { state: FsmTransitionState::State(st), .. }) |
+            FsmTransitionType::StateTransition(FsmStateTransition { state_from: FsmTransitionState::State(st), .. }) if st.kind == FsmStateKind::ExitPoint => {
+                return Err(syn::Error::new(st.ty.span(), &format!("The exit point '{}' can't have any transitions!", tokens_to_string(&st.ty))));
+            },
+            FsmTransitionType::InternalTransition(FsmStateAction { action: EventGuardAction { action: Some(action), is_async: true, .. }, .. }) |
+            FsmTransitionType::SelfTransition(FsmStateAction { action: EventGuardAction { action: Some(action), is_async: true, .. }, .. }) => {
+                return Err(syn::Error::new(action.span(), "The async actions are only supported on the transitions between two states!"));
+            },
+            // the final states are the last ones in their region
+            FsmTransitionType::InternalTransition(FsmStateAction { state: FsmTransitionState::State(st), .. }) |
+            FsmTransitionType::SelfTransition(FsmStateAction { state: FsmTransitionState::State(st), .. }) |
+            FsmTransitionType::StateTransition(FsmStateTransition { state_from: FsmTransitionState::State(st), .. }) if decl.states.get(&st.ty).map(|s| s.is_final).unwrap_or(false) => {
+                return Err(syn::Error::new(st.ty.span(), &format!("The final state '{}' can't have any transitions!", tokens_to_string(&st.ty))));
+            },
+            FsmTransitionType::StateTransition(FsmStateTransition { state_to: FsmTransitionState::State(st), action: EventGuardAction { entry_point: Some(ty_entry), .. }, .. }) => {
+                if let FsmStateKind::SubMachine(_) = st.kind { } else {
+                    return Err(syn::Error::new(ty_entry.span(), &format!("The entry points are only supported for the submachines, '{}' isn't one.", tokens_to_string(&st.ty))));
+                }
+            },
+            _ => ()
+        }
+    }
+
+    // the futures are only driven for the async transition actions
+    for state in decl.states.values() {
+        let closures = state.on_entry_closure.iter()
+            .chain(state.on_exit_closure.iter())
+            .chain(state.on_entry_with.iter().map(|(_, c)| c))
+            .chain(state.on_exit_with.iter().map(|(_, c)| c));
+        for closure in closures {
+            assert_not_async(closure)?;
+        }
+    }
+    for transition in &decl.transitions {
+        let action = match &transition.ty {
+            FsmTransitionType::InternalTransition(s) | FsmTransitionType::SelfTransition(s) => &s.action,
+            FsmTransitionType::StateTransition(s) => &s.action
+        };
+        if let Some(ref guard) = action.guard {
+            assert_not_async(guard)?;
+        }
+        match action.action {
+            Some(ref closure) if !action.is_async => assert_not_async(closure)?,
+            _ => ()
+        }
+    }
+
     // build the regions
     let mut regions = vec![];
     for (region_id, initial_state) in decl.initial_states.iter().enumerate() {
@@ -103,6 +172,22 @@ pub fn create_regions(decl: FsmDeclarations, options: FsmCodegenOptions) -> syn:
         events: decl.events,
         states: decl.states,
         regions,
-        codegen_options: options
+        codegen_options: options,
+        unhandled: decl.unhandled
     })
-}
\ No newline at end of file
+}
+
+/// Rejects the closures that return a future, which would never be polled.
+fn assert_not_async(closure: &syn::ExprClosure) -> syn::Result<()> {
+    let returns_future = closure.asyncness.is_some() || match &*closure.body {
+        syn::Expr::Async(_) => true,
+        syn::Expr::Block(b) => matches!(b.block.stmts.last(), Some(syn::Stmt::Expr(syn::Expr::Async(_)))),
+        _ => false
+    };
+
+    if returns_future {
+        return Err(syn::Error::new(closure.span(), "Only the actions of the transitions between two states can be async, with `action_async`!"));
+    }
+
+    Ok(())
+}
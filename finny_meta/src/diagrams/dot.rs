@@ -0,0 +1,91 @@
+//! The Graphviz DOT diagram of the machine. The regions and the submachines are drawn as clusters.
+
+use std::fmt;
+
+use super::{DiagramHighlight, DiagramKind, DiagramWriter, node_id, sorted_regions, transition_label};
+use crate::{FinnyEvent, FinnyFsm, FinnyStateKind, FinnyTransitionKind};
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn write_dot<W: DiagramWriter>(fsm: &FinnyFsm, highlight: Option<&DiagramHighlight>, output: &mut W) -> fmt::Result {
+    let is_current = |state_id: &str| highlight.map(|h| h.is_current(state_id)).unwrap_or(false);
+
+    for (region, states) in sorted_regions(fsm) {
+        let initial = node_id(&fsm.fsm_id, &format!("region_{}_initial", region.region_id));
+
+        writeln!(output, "subgraph cluster_{} {{", node_id(&fsm.fsm_id, &format!("region_{}", region.region_id)))?;
+        writeln!(output, "label=\"Region {}\";", region.region_id)?;
+        writeln!(output, "{} [shape=point];", initial)?;
+
+        for (state_id, state) in states {
+            let node = node_id(&fsm.fsm_id, state_id);
+            let style = if is_current(state_id) { "\"rounded,filled\", fillcolor=palegreen" } else { "rounded" };
+
+            match state {
+                FinnyStateKind::Stopped => (),
+                FinnyStateKind::State(state) => {
+                    let label: Vec<_> = Some(escape(&state.state_id)).into_iter()
+                        .chain(state.timers.iter().map(|t| format!("Timer {}", escape(&t.timer_id))))
+                        .collect();
+                    let peripheries = if state.is_final { ", peripheries=2" } else { "" };
+                    writeln!(output, "{} [label=\"{}\", shape=box, style={}{}];", node, label.join("\\n"), style, peripheries)?;
+                },
+                FinnyStateKind::Choice(choice_id) => {
+                    writeln!(output, "{} [label=\"{}\", shape=diamond];", node, escape(choice_id))?;
+                },
+                FinnyStateKind::ExitPoint(exit_id) => {
+                    writeln!(output, "{} [label=\"{}\", shape=Mcircle];", node, escape(exit_id))?;
+                },
+                FinnyStateKind::SubMachine(sub_id) => {
+                    writeln!(output, "subgraph cluster_{} {{", node)?;
+                    writeln!(output, "label=\"{}\";", escape(sub_id))?;
+                    if is_current(state_id) {
+                        writeln!(output, "style=filled;\nfillcolor=palegreen;")?;
+                    }
+                    writeln!(output, "{} [shape=point, style=invis];", node)?;
+                    output.write_sub(DiagramKind::Dot, sub_id, highlight.and_then(|h| h.sub_highlight(state_id)))?;
+                    writeln!(output, "}}")?;
+                }
+            }
+        }
+
+        for transition in &region.transitions {
+            let (from, to) = match &transition.transition {
+                FinnyTransitionKind::SelfTransition { state_id } | FinnyTransitionKind::InternalTransition { state_id } => (state_id, state_id),
+                FinnyTransitionKind::NormalTransition(t) => (&t.from_state, &t.to_state)
+            };
+
+            let from_node = match (&transition.event, region.states.get(from)) {
+                (FinnyEvent::EntryPoint(entry_id), _) => {
+                    let entry = node_id(&fsm.fsm_id, entry_id);
+                    writeln!(output, "{} [label=\"{}\", shape=circle];", entry, escape(entry_id))?;
+                    entry
+                },
+                (_, Some(FinnyStateKind::Stopped)) => initial.clone(),
+                _ => node_id(&fsm.fsm_id, from)
+            };
+            let to_node = node_id(&fsm.fsm_id, to);
+
+            let mut attributes = vec![format!("label=\"{}\"", escape(&transition_label(transition)))];
+            if let Some(FinnyStateKind::SubMachine(_)) = region.states.get(from) {
+                attributes.push(format!("ltail=cluster_{}", from_node));
+            }
+            if let Some(FinnyStateKind::SubMachine(_)) = region.states.get(to) {
+                attributes.push(format!("lhead=cluster_{}", to_node));
+            }
+            match highlight.and_then(|h| h.transition_age(&transition.transition_id)) {
+                Some(0) => attributes.push("color=red, fontcolor=red, penwidth=2".to_string()),
+                Some(_) => attributes.push("color=orange, fontcolor=orange".to_string()),
+                None => ()
+            }
+
+            writeln!(output, "{} -> {} [{}];", from_node, to_node, attributes.join(", "))?;
+        }
+
+        writeln!(output, "}}")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! The Mermaid state diagram of the machine. The regions are separated as concurrent states and the
+//! submachines are drawn as composite states.
+
+use std::{collections::HashMap, fmt};
+
+use super::{DiagramHighlight, DiagramKind, DiagramWriter, node_id, sorted_regions, transition_label};
+use crate::{FinnyEvent, FinnyFsm, FinnyStateKind, FinnyTransitionKind};
+
+fn escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+pub fn write_mermaid<W: DiagramWriter>(fsm: &FinnyFsm, highlight: Option<&DiagramHighlight>, output: &mut W) -> fmt::Result {
+    for (i, (region, states)) in sorted_regions(fsm).into_iter().enumerate() {
+        if i > 0 {
+            writeln!(output, "--")?;
+        }
+
+        for (state_id, state) in states {
+            let node = node_id(&fsm.fsm_id, state_id);
+
+            match state {
+                FinnyStateKind::Stopped => (),
+                FinnyStateKind::State(state) => {
+                    writeln!(output, "state \"{}\" as {}", escape(&state.state_id), node)?;
+                    for timer in &state.timers {
+                        writeln!(output, "note right of {} : Timer {}", node, timer.timer_id)?;
+                    }
+                    if state.is_final {
+                        writeln!(output, "{} --> [*]", node)?;
+                    }
+                },
+                FinnyStateKind::Choice(_) => {
+                    writeln!(output, "state {} <<choice>>", node)?;
+                },
+                FinnyStateKind::ExitPoint(exit_id) => {
+                    writeln!(output, "state \"{} (Exit point)\" as {}", escape(exit_id), node)?;
+                },
+                FinnyStateKind::SubMachine(sub_id) => {
+                    writeln!(output, "state {} {{", node)?;
+                    output.write_sub(DiagramKind::Mermaid, sub_id, highlight.and_then(|h| h.sub_highlight(state_id)))?;
+                    writeln!(output, "}}")?;
+                }
+            }
+        }
+
+        for transition in &region.transitions {
+            let (from, to) = match &transition.transition {
+                FinnyTransitionKind::SelfTransition { state_id } | FinnyTransitionKind::InternalTransition { state_id } => (state_id, state_id),
+                FinnyTransitionKind::NormalTransition(t) => (&t.from_state, &t.to_state)
+            };
+
+            let from_node = match (&transition.event, region.states.get(from)) {
+                (FinnyEvent::EntryPoint(_), _) | (_, Some(FinnyStateKind::Stopped)) => "[*]".to_string(),
+                _ => node_id(&fsm.fsm_id, from)
+            };
+            let to_node = node_id(&fsm.fsm_id, to);
+
+            // the edges can't be styled, the recent transitions are marked in their labels
+            let mut label = transition_label(transition);
+            match highlight.and_then(|h| h.transition_age(&transition.transition_id)) {
+                Some(0) => label = format!("{} (last)", label).trim().to_string(),
+                Some(_) => label = format!("{} (recent)", label).trim().to_string(),
+                None => ()
+            }
+
+            match label {
+                label if label.is_empty() => writeln!(output, "{} --> {}", from_node, to_node)?,
+                label => writeln!(output, "{} --> {} : {}", from_node, to_node, label)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The styles of the current states, written after the end of the diagram's body.
+pub fn write_highlight_classes<W: fmt::Write>(fsm: &FinnyFsm, submachines: &HashMap<String, FinnyFsm>, highlight: &DiagramHighlight, output: &mut W) -> fmt::Result {
+    writeln!(output, "classDef current fill:#98fb98,stroke:#2e8b57,stroke-width:2px")?;
+    write_current_classes(fsm, submachines, highlight, output)
+}
+
+fn write_current_classes<W: fmt::Write>(fsm: &FinnyFsm, submachines: &HashMap<String, FinnyFsm>, highlight: &DiagramHighlight, output: &mut W) -> fmt::Result {
+    for (_, states) in sorted_regions(fsm) {
+        for (state_id, state) in states {
+            match state {
+                FinnyStateKind::State(_) | FinnyStateKind::SubMachine(_) if highlight.is_current(state_id) => {
+                    writeln!(output, "class {} current", node_id(&fsm.fsm_id, state_id))?;
+                },
+                _ => ()
+            }
+
+            if let (FinnyStateKind::SubMachine(sub_id), Some(sub_highlight)) = (state, highlight.sub_highlight(state_id)) {
+                if let Some(sub) = submachines.get(sub_id) {
+                    write_current_classes(sub, submachines, sub_highlight, output)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
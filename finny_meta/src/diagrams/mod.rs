@@ -0,0 +1,203 @@
+//! The diagrams of the machines, as PlantUML, Graphviz DOT or Mermaid text. The code generator uses them
+//! for the static diagrams, and a live machine renders them with its current states highlighted.
+
+use std::{collections::HashMap, fmt::{self, Write}};
+
+use crate::{FinnyEvent, FinnyFsm, FinnyRegion, FinnyStateKind, FinnyTransition, FinnyTransitionKind};
+
+mod dot;
+mod mermaid;
+mod plantuml;
+
+/// The supported diagram formats.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DiagramKind {
+    PlantUml,
+    Dot,
+    Mermaid
+}
+
+impl DiagramKind {
+    /// The name of the format, used for the generated methods.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiagramKind::PlantUml => "plantuml",
+            DiagramKind::Dot => "dot",
+            DiagramKind::Mermaid => "mermaid"
+        }
+    }
+
+    /// The extension of the diagram's file.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DiagramKind::PlantUml => "plantuml",
+            DiagramKind::Dot => "dot",
+            DiagramKind::Mermaid => "mmd"
+        }
+    }
+
+    /// The start of the diagram, before the body of the top level machine.
+    pub fn header(&self, fsm_id: &str) -> String {
+        match self {
+            DiagramKind::PlantUml => format!("@startuml {}\n", fsm_id),
+            DiagramKind::Dot => format!("digraph \"{}\" {{\ncompound=true;\n", fsm_id.replace('"', "\\\"")),
+            DiagramKind::Mermaid => format!("stateDiagram-v2\nstate {} {{\n", diagram_id(fsm_id))
+        }
+    }
+
+    /// The end of the diagram.
+    pub fn footer(&self) -> &'static str {
+        match self {
+            DiagramKind::PlantUml => "@enduml\n",
+            DiagramKind::Dot | DiagramKind::Mermaid => "}\n"
+        }
+    }
+}
+
+/// The output of the diagram's body.
+pub trait DiagramWriter: Write {
+    /// Write the body of the submachine's diagram, nested within its state. The highlight is the
+    /// submachine's own, when it is active.
+    fn write_sub(&mut self, kind: DiagramKind, sub_id: &str, highlight: Option<&DiagramHighlight>) -> fmt::Result;
+}
+
+/// The description of the submachines isn't known, they are drawn as empty states.
+impl DiagramWriter for String {
+    fn write_sub(&mut self, _kind: DiagramKind, _sub_id: &str, _highlight: Option<&DiagramHighlight>) -> fmt::Result {
+        Ok(())
+    }
+}
+
+/// Nests the bodies of the described submachines into the diagram.
+struct SubmachinesWriter<'a> {
+    output: &'a mut String,
+    submachines: &'a HashMap<String, FinnyFsm>
+}
+
+impl<'a> DiagramWriter for SubmachinesWriter<'a> {
+    fn write_sub(&mut self, kind: DiagramKind, sub_id: &str, highlight: Option<&DiagramHighlight>) -> fmt::Result {
+        match self.submachines.get(sub_id) {
+            Some(sub) => write_diagram_body(kind, sub, highlight, self),
+            None => Ok(())
+        }
+    }
+}
+
+impl<'a> Write for SubmachinesWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.output.write_str(s)
+    }
+}
+
+/// The current states of the live machine and the transitions that it recently executed.
+#[derive(Clone, Debug, Default)]
+pub struct DiagramHighlight {
+    /// The ids of the active states, one for each of the running regions.
+    pub current_states: Vec<String>,
+    /// The ids of the executed transitions, the most recent one first.
+    pub recent_transitions: Vec<String>,
+    /// The highlights of the active submachines, by the ids of their states.
+    pub submachines: HashMap<String, DiagramHighlight>
+}
+
+impl DiagramHighlight {
+    fn is_current(&self, state_id: &str) -> bool {
+        self.current_states.iter().any(|s| s == state_id)
+    }
+
+    fn sub_highlight(&self, state_id: &str) -> Option<&DiagramHighlight> {
+        self.submachines.get(state_id)
+    }
+
+    /// How many transitions were executed after this one, if it is among the recent ones.
+    fn transition_age(&self, transition_id: &str) -> Option<usize> {
+        let transition_id = short_type_name(transition_id);
+        self.recent_transitions.iter().position(|t| short_type_name(t) == transition_id)
+    }
+}
+
+/// Write the body of the diagram, without its header and footer.
+pub fn write_diagram_body<W: DiagramWriter>(kind: DiagramKind, fsm: &FinnyFsm, highlight: Option<&DiagramHighlight>, output: &mut W) -> fmt::Result {
+    match kind {
+        DiagramKind::PlantUml => plantuml::write_plant_uml(fsm, highlight, output),
+        DiagramKind::Dot => dot::write_dot(fsm, highlight, output),
+        DiagramKind::Mermaid => mermaid::write_mermaid(fsm, highlight, output)
+    }
+}
+
+/// The complete diagram of the machine. The submachines are nested with their descriptions, by their ids,
+/// and the missing ones are drawn as empty states. The current states and the recent transitions are
+/// highlighted, when they are known.
+pub fn render_diagram(kind: DiagramKind, fsm: &FinnyFsm, submachines: &HashMap<String, FinnyFsm>, highlight: Option<&DiagramHighlight>) -> String {
+    let mut output = kind.header(&fsm.fsm_id);
+    {
+        let mut writer = SubmachinesWriter { output: &mut output, submachines };
+        write_diagram_body(kind, fsm, highlight, &mut writer).expect("Writing into a string can't fail");
+    }
+    output.push_str(kind.footer());
+
+    if let (DiagramKind::Mermaid, Some(highlight)) = (kind, highlight) {
+        mermaid::write_highlight_classes(fsm, submachines, highlight, &mut output).expect("Writing into a string can't fail");
+    }
+
+    output
+}
+
+/// The name of the type without its module path and generics.
+pub fn short_type_name(name: &str) -> &str {
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name).trim()
+}
+
+/// The label of the event on the diagrams.
+pub fn event_label(event: &FinnyEvent) -> String {
+    match event {
+        FinnyEvent::Start => "Start".to_string(),
+        FinnyEvent::Stop => "Stop".to_string(),
+        FinnyEvent::Completion => "(Completion)".to_string(),
+        FinnyEvent::EntryPoint(ep) => ep.clone(),
+        FinnyEvent::ExitPoint(ep) => format!("{} (Exit point)", ep),
+        FinnyEvent::Event(ev) => ev.clone()
+    }
+}
+
+/// The label of the transition's edge, with its guard and action. Empty for the unguarded start transitions.
+pub fn transition_label(transition: &FinnyTransition) -> String {
+    let mut label = match (&transition.event, &transition.transition) {
+        (FinnyEvent::Start, _) => String::new(),
+        (event, FinnyTransitionKind::SelfTransition { .. }) => format!("{} (Self)", event_label(event)),
+        (event, FinnyTransitionKind::InternalTransition { .. }) => format!("{} (Internal)", event_label(event)),
+        (event, FinnyTransitionKind::NormalTransition(_)) => event_label(event)
+    };
+
+    if let Some(ref guard) = transition.guard {
+        label.push_str(&format!(" [{}]", guard));
+    }
+    if let Some(ref action) = transition.action {
+        label.push_str(&format!(" / {}", action));
+    }
+
+    label.trim().to_string()
+}
+
+/// The regions in their order and their states sorted by the ids, for a stable output.
+pub fn sorted_regions(fsm: &FinnyFsm) -> Vec<(&FinnyRegion, Vec<(&String, &FinnyStateKind)>)> {
+    let mut regions: Vec<_> = fsm.regions.values().collect();
+    regions.sort_by_key(|r| r.region_id);
+
+    regions.into_iter().map(|region| {
+        let mut states: Vec<_> = region.states.iter().collect();
+        states.sort_by_key(|(id, _)| *id);
+        (region, states)
+    }).collect()
+}
+
+/// An identifier that is valid in the diagrams.
+pub fn diagram_id(s: &str) -> String {
+    s.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+/// An identifier of the node that is unique across the machine and its submachines.
+pub fn node_id(fsm_id: &str, state_id: &str) -> String {
+    diagram_id(&format!("{}_{}", fsm_id, state_id))
+}
@@ -0,0 +1,87 @@
+//! The PlantUML state diagram of the machine.
+
+use std::fmt;
+
+use super::{DiagramHighlight, DiagramKind, DiagramWriter, event_label, sorted_regions};
+use crate::{FinnyEvent, FinnyFsm, FinnyStateKind, FinnyTransitionKind};
+
+pub fn write_plant_uml<W: DiagramWriter>(fsm: &FinnyFsm, highlight: Option<&DiagramHighlight>, output: &mut W) -> fmt::Result {
+    let color = |state_id: &str| match highlight {
+        Some(h) if h.is_current(state_id) => " #PaleGreen",
+        _ => ""
+    };
+
+    for (region, states) in sorted_regions(fsm) {
+        for (state_id, state) in states {
+            match state {
+                FinnyStateKind::Stopped => {
+
+                }
+                FinnyStateKind::State(state) => {
+
+                    writeln!(output, "state {}{} {{", state.state_id, color(state_id))?;
+                    writeln!(output, "}}")?;
+
+                    for timer in &state.timers {
+                        writeln!(output, "state {} : Timer {}", state.state_id, timer.timer_id)?;
+                    }
+
+                    if state.is_final {
+                        writeln!(output, "{} --> [*]", state.state_id)?;
+                    }
+                },
+                FinnyStateKind::Choice(choice_id) => {
+                    writeln!(output, "state {} <<choice>>", choice_id)?;
+                },
+                FinnyStateKind::ExitPoint(exit_id) => {
+                    writeln!(output, "state {} <<exitPoint>>", exit_id)?;
+                },
+                FinnyStateKind::SubMachine(sub_id) => {
+                    writeln!(output, "state {}{} {{", sub_id, color(state_id))?;
+                    output.write_sub(DiagramKind::PlantUml, sub_id, highlight.and_then(|h| h.sub_highlight(state_id)))?;
+                    writeln!(output, "}}")?;
+                }
+            }
+        }
+
+        for transition in &region.transitions {
+
+            let event = event_label(&transition.event);
+            let arrow = match highlight.and_then(|h| h.transition_age(&transition.transition_id)) {
+                Some(0) => "-[#Red,bold]->",
+                Some(_) => "-[#Orange]->",
+                None => "-->"
+            };
+
+            match &transition.transition {
+                FinnyTransitionKind::SelfTransition { state_id } => {
+                    writeln!(output, "{state} {arrow} {state} : {event} (Self)", state = state_id, arrow = arrow, event = event)?;
+                    writeln!(output, "note on link: {}", transition.transition_id)?;
+                }
+                FinnyTransitionKind::InternalTransition { state_id } => {
+                    writeln!(output, "{state} {arrow} {state} : {event} (Internal)", state = state_id, arrow = arrow, event = event)?;
+                    writeln!(output, "note on link: {}", transition.transition_id)?;
+                }
+                FinnyTransitionKind::NormalTransition(t) if matches!(transition.event, FinnyEvent::EntryPoint(_)) => {
+                    writeln!(output, "state {} <<entryPoint>>", event)?;
+                    writeln!(output, "{entry} {arrow} {state_to}", entry = event, arrow = arrow, state_to = t.to_state)?;
+                    writeln!(output, "note on link: {}", transition.transition_id)?;
+                }
+                FinnyTransitionKind::NormalTransition(t) => {
+                    let state_from = match t.from_state.as_str() {
+                        "Stopped" => "[*]",
+                        _ => &t.from_state
+                    };
+
+                    match t.entry_point {
+                        Some(ref entry_point) => writeln!(output, "{state_from} {arrow} {state_to} : {event} (via {entry_point})", state_from = state_from, arrow = arrow, state_to = t.to_state, event = event, entry_point = entry_point)?,
+                        None => writeln!(output, "{state_from} {arrow} {state_to} : {event}", state_from = state_from, arrow = arrow, state_to = t.to_state, event = event)?
+                    }
+                    writeln!(output, "note on link: {}", transition.transition_id)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,509 @@
+//! Runs the machines that are described with the `FinnyFsm` model, for example loaded from JSON, without
+//! recompiling. The events and the states are identified by their names, the guards and the actions are
+//! registered by name in the `FsmRegistry`. The dispatching follows the generated machines: the regions
+//! are run in their order, the first transition whose guard passes is executed and the events enqueued by
+//! the actions are run to completition. The deferred events and the policies for the unhandled events are
+//! applied just like in the generated machines, the unhandled handlers are registered as actions.
+//!
+//! Submachines, entry and exit points and timers aren't supported by the interpreter.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde_json::Value;
+
+use crate::{FINNY_META_SCHEMA_VERSION, FinnyEvent, FinnyFsm, FinnyRegion, FinnyStateKind, FinnyTransition, FinnyTransitionKind, FinnyUnhandledPolicy};
+
+/// The name of the stopped state in the description.
+pub const STOPPED_STATE_ID: &str = "Stopped";
+
+pub type FsmInterpreterResult<T> = Result<T, FsmInterpreterError>;
+
+/// The errors of the interpreter.
+#[derive(Debug)]
+pub enum FsmInterpreterError {
+    /// The description couldn't be deserialized.
+    Json(serde_json::Error),
+    /// The description uses an unsupported schema version.
+    SchemaVersion(u32),
+    /// The description is inconsistent, for example a transition refers to an unknown state.
+    InvalidDescription(String),
+    /// The description uses a feature that the interpreter doesn't support.
+    NotSupported(String),
+    /// The guard or the action with this name isn't registered.
+    NotRegistered(String),
+    /// None of the regions had a transition for the event.
+    NoTransition { event: String, states: Vec<String> }
+}
+
+impl fmt::Display for FsmInterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsmInterpreterError::Json(e) => write!(f, "Failed to parse the description: {}", e),
+            FsmInterpreterError::SchemaVersion(v) => write!(f, "Unsupported schema version {}", v),
+            FsmInterpreterError::InvalidDescription(msg) => write!(f, "Invalid description: {}", msg),
+            FsmInterpreterError::NotSupported(msg) => write!(f, "Not supported by the interpreter: {}", msg),
+            FsmInterpreterError::NotRegistered(name) => write!(f, "The guard or action '{}' isn't registered", name),
+            FsmInterpreterError::NoTransition { event, states } => write!(f, "No transition for the event '{}', states: [{}]", event, states.join(", "))
+        }
+    }
+}
+
+impl std::error::Error for FsmInterpreterError { }
+
+/// An event of the interpreted machine, identified by its name. The optional data is available to
+/// the guards and the actions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FsmDynEvent {
+    pub name: String,
+    pub data: Value
+}
+
+impl FsmDynEvent {
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self::with_data(name, Value::Null)
+    }
+
+    pub fn with_data<N: Into<String>>(name: N, data: Value) -> Self {
+        FsmDynEvent {
+            name: name.into(),
+            data
+        }
+    }
+}
+
+impl From<&str> for FsmDynEvent {
+    fn from(name: &str) -> Self {
+        FsmDynEvent::new(name)
+    }
+}
+
+/// The context that is passed to the actions. Can enqueue new events.
+pub struct FsmDynContext<'a, C> {
+    pub context: &'a mut C,
+    queue: &'a mut VecDeque<FsmDynEvent>
+}
+
+impl<'a, C> FsmDynContext<'a, C> {
+    /// Enqueue the event, it is dispatched once the current event is handled.
+    pub fn enqueue<E: Into<FsmDynEvent>>(&mut self, event: E) {
+        self.queue.push_back(event.into());
+    }
+}
+
+impl<'a, C> Deref for FsmDynContext<'a, C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        self.context
+    }
+}
+
+impl<'a, C> DerefMut for FsmDynContext<'a, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.context
+    }
+}
+
+pub type FsmDynGuard<C> = Box<dyn Fn(&FsmDynEvent, &C) -> bool>;
+pub type FsmDynAction<C> = Box<dyn Fn(&FsmDynEvent, &mut FsmDynContext<C>)>;
+
+/// The guards and the actions, by their names in the description. The entry and the exit actions of the
+/// states are registered as actions and receive the event that caused the transition.
+pub struct FsmRegistry<C> {
+    guards: HashMap<String, FsmDynGuard<C>>,
+    actions: HashMap<String, FsmDynAction<C>>
+}
+
+impl<C> FsmRegistry<C> {
+    pub fn new() -> Self {
+        FsmRegistry {
+            guards: HashMap::new(),
+            actions: HashMap::new()
+        }
+    }
+
+    pub fn guard<G: Fn(&FsmDynEvent, &C) -> bool + 'static>(&mut self, name: &str, guard: G) -> &mut Self {
+        self.guards.insert(name.into(), Box::new(guard));
+        self
+    }
+
+    pub fn action<A: Fn(&FsmDynEvent, &mut FsmDynContext<C>) + 'static>(&mut self, name: &str, action: A) -> &mut Self {
+        self.actions.insert(name.into(), Box::new(action));
+        self
+    }
+}
+
+impl<C> Default for FsmRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A machine that is executed from its description.
+pub struct FsmInterpreter<C> {
+    fsm_id: String,
+    regions: Vec<FinnyRegion>,
+    unhandled: FinnyUnhandledPolicy,
+    registry: FsmRegistry<C>,
+    pub context: C,
+    current_states: Vec<String>,
+    queue: VecDeque<FsmDynEvent>,
+    /// The events that were deferred by the states, redelivered once the active states change.
+    deferred: VecDeque<FsmDynEvent>
+}
+
+impl<C> FsmInterpreter<C> {
+    /// Validate the description and create the stopped machine.
+    pub fn new(fsm: FinnyFsm, registry: FsmRegistry<C>, context: C) -> FsmInterpreterResult<Self> {
+        if fsm.schema_version != FINNY_META_SCHEMA_VERSION {
+            return Err(FsmInterpreterError::SchemaVersion(fsm.schema_version));
+        }
+
+        let mut regions: Vec<_> = fsm.regions.into_values().collect();
+        regions.sort_by_key(|r| r.region_id);
+
+        for region in &regions {
+            validate_region(region, &registry)?;
+        }
+        validate_action(&fsm.unhandled.handler, &registry)?;
+
+        Ok(FsmInterpreter {
+            fsm_id: fsm.fsm_id,
+            current_states: regions.iter().map(|_| STOPPED_STATE_ID.to_string()).collect(),
+            regions,
+            unhandled: fsm.unhandled,
+            registry,
+            context,
+            queue: VecDeque::new(),
+            deferred: VecDeque::new()
+        })
+    }
+
+    /// Load the JSON description of the machine.
+    pub fn from_json(json: &str, registry: FsmRegistry<C>, context: C) -> FsmInterpreterResult<Self> {
+        let fsm: FinnyFsm = serde_json::from_str(json).map_err(FsmInterpreterError::Json)?;
+        Self::new(fsm, registry, context)
+    }
+
+    pub fn get_fsm_id(&self) -> &str {
+        &self.fsm_id
+    }
+
+    /// The active states of the regions, `Stopped` for the stopped ones.
+    pub fn get_current_states(&self) -> &[String] {
+        &self.current_states
+    }
+
+    /// All the regions of the machine have reached one of their final states.
+    pub fn is_completed(&self) -> bool {
+        self.regions.iter().zip(&self.current_states).all(|(region, state)| {
+            matches!(region.states.get(state), Some(FinnyStateKind::State(s)) if s.is_final)
+        })
+    }
+
+    /// Start the machine, enters the initial states of the regions.
+    pub fn start(&mut self) -> FsmInterpreterResult<()> {
+        self.dispatch_single_event(&FinnyEvent::Start, &FsmDynEvent::new("Fsm::Start"))?;
+        self.dispatch_queue()
+    }
+
+    /// Stop the machine. Executes the exit actions of the active states.
+    pub fn stop(&mut self) -> FsmInterpreterResult<()> {
+        let event = FsmDynEvent::new("Fsm::Stop");
+        for region in 0..self.regions.len() {
+            if self.current_states[region] != STOPPED_STATE_ID {
+                let state = self.current_states[region].clone();
+                self.exit_state(region, &state, &event);
+                self.current_states[region] = STOPPED_STATE_ID.to_string();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Dispatch this event and run it to completition.
+    pub fn dispatch<E: Into<FsmDynEvent>>(&mut self, event: E) -> FsmInterpreterResult<()> {
+        self.dispatch_event(&event.into())?;
+        self.dispatch_queue()
+    }
+
+    /// The events that are waiting in the deferred queue.
+    pub fn get_deferred_events(&self) -> impl Iterator<Item = &FsmDynEvent> {
+        self.deferred.iter()
+    }
+
+    fn dispatch_queue(&mut self) -> FsmInterpreterResult<()> {
+        while let Some(event) = self.queue.pop_front() {
+            self.dispatch_event(&event)?;
+        }
+
+        Ok(())
+    }
+
+    /// Dispatch the event. If the machine has left the states that deferred the events, they are redelivered.
+    fn dispatch_event(&mut self, event: &FsmDynEvent) -> FsmInterpreterResult<()> {
+        let current_states = if self.deferred.is_empty() { None } else { Some(self.current_states.clone()) };
+        self.dispatch_single_event(&FinnyEvent::Event(event.name.clone()), event)?;
+
+        if let Some(current_states) = current_states {
+            if self.current_states != current_states {
+                self.redeliver_deferred()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Dispatch the deferred events again, in their original order. Repeats while the events change
+    /// the active states. Stops at the first error, the failed event is dropped.
+    fn redeliver_deferred(&mut self) -> FsmInterpreterResult<()> {
+        loop {
+            let current_states = self.current_states.clone();
+
+            for _ in 0..self.deferred.len() {
+                if let Some(event) = self.deferred.pop_front() {
+                    self.dispatch_single_event(&FinnyEvent::Event(event.name.clone()), &event)?;
+                }
+            }
+
+            if self.deferred.is_empty() || self.current_states == current_states {
+                return Ok(());
+            }
+        }
+    }
+
+    fn dispatch_single_event(&mut self, trigger: &FinnyEvent, event: &FsmDynEvent) -> FsmInterpreterResult<()> {
+        let mut transition_misses = 0;
+        let mut deferrals = 0;
+
+        for region in 0..self.regions.len() {
+            if let Some(transition) = self.find_transition(region, trigger, event) {
+                self.execute_transition(region, transition, event)?;
+                continue;
+            }
+
+            // the policies of the current state only apply to the events
+            let state = match (trigger, self.regions[region].states.get(&self.current_states[region])) {
+                (FinnyEvent::Event(_), Some(FinnyStateKind::State(state))) => Some(state),
+                _ => None
+            };
+
+            match state {
+                Some(state) if state.deferred_events.contains(&event.name) => deferrals += 1,
+                Some(state) => {
+                    let unhandled = state.unhandled.clone();
+                    if !self.apply_unhandled(&unhandled, event) {
+                        transition_misses += 1;
+                    }
+                },
+                None => transition_misses += 1
+            }
+        }
+
+        let region_count = self.regions.len();
+        if deferrals > 0 && transition_misses + deferrals == region_count {
+            self.deferred.push_back(event.clone());
+            return Ok(());
+        }
+
+        if transition_misses < region_count {
+            return Ok(());
+        }
+
+        if let FinnyEvent::Event(_) = trigger {
+            let unhandled = self.unhandled.clone();
+            if self.apply_unhandled(&unhandled, event) {
+                return Ok(());
+            }
+        }
+
+        Err(FsmInterpreterError::NoTransition {
+            event: event.name.clone(),
+            states: self.current_states.clone()
+        })
+    }
+
+    /// Ignores the event or passes it to the handler, returns `false` if the policy doesn't cover it.
+    fn apply_unhandled(&mut self, unhandled: &FinnyUnhandledPolicy, event: &FsmDynEvent) -> bool {
+        if unhandled.ignored_events.contains(&event.name) {
+            return true;
+        }
+
+        match unhandled.handler {
+            Some(ref handler) => {
+                self.run_action(Some(handler), event);
+                true
+            },
+            None => unhandled.ignore_all
+        }
+    }
+
+    /// The index of the first transition from the current state whose guard passes.
+    fn find_transition(&self, region: usize, trigger: &FinnyEvent, event: &FsmDynEvent) -> Option<usize> {
+        let current_state = &self.current_states[region];
+
+        self.regions[region].transitions.iter().position(|t| {
+            same_event(&t.event, trigger)
+                && get_from_state(t) == current_state
+                && t.guard.as_ref().is_none_or(|name| (self.registry.guards[name])(event, &self.context))
+        })
+    }
+
+    fn execute_transition(&mut self, region: usize, transition: usize, event: &FsmDynEvent) -> FsmInterpreterResult<()> {
+        let t = self.regions[region].transitions[transition].clone();
+        match t.transition {
+            FinnyTransitionKind::InternalTransition { .. } => {
+                self.run_action(t.action.as_ref(), event);
+            },
+            FinnyTransitionKind::SelfTransition { ref state_id } => {
+                self.exit_state(region, state_id, event);
+                self.run_action(t.action.as_ref(), event);
+                self.enter_state(region, state_id, event)?;
+            },
+            FinnyTransitionKind::NormalTransition(ref normal) => {
+                if normal.from_state != STOPPED_STATE_ID {
+                    self.exit_state(region, &normal.from_state, event);
+                }
+                self.run_action(t.action.as_ref(), event);
+                self.enter_state(region, &normal.to_state, event)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enter the state and run the completion transitions. The choices have to be left immediately.
+    fn enter_state(&mut self, region: usize, state_id: &str, event: &FsmDynEvent) -> FsmInterpreterResult<()> {
+        self.current_states[region] = state_id.to_string();
+        if let Some(FinnyStateKind::State(state)) = self.regions[region].states.get(state_id) {
+            let on_entry = state.on_entry.clone();
+            self.run_action(on_entry.as_ref(), event);
+        }
+
+        let completion = FsmDynEvent::new("Fsm::Completion");
+        match self.find_transition(region, &FinnyEvent::Completion, &completion) {
+            Some(transition) => self.execute_transition(region, transition, &completion),
+            None if matches!(self.regions[region].states.get(state_id), Some(FinnyStateKind::Choice(_))) => {
+                Err(FsmInterpreterError::NoTransition {
+                    event: completion.name,
+                    states: self.current_states.clone()
+                })
+            },
+            None => Ok(())
+        }
+    }
+
+    fn exit_state(&mut self, region: usize, state_id: &str, event: &FsmDynEvent) {
+        if let Some(FinnyStateKind::State(state)) = self.regions[region].states.get(state_id) {
+            let on_exit = state.on_exit.clone();
+            self.run_action(on_exit.as_ref(), event);
+        }
+    }
+
+    fn run_action(&mut self, name: Option<&String>, event: &FsmDynEvent) {
+        if let Some(name) = name {
+            let mut ctx = FsmDynContext {
+                context: &mut self.context,
+                queue: &mut self.queue
+            };
+            (self.registry.actions[name])(event, &mut ctx);
+        }
+    }
+}
+
+impl<C> Deref for FsmInterpreter<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
+}
+
+impl<C> DerefMut for FsmInterpreter<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.context
+    }
+}
+
+fn same_event(a: &FinnyEvent, b: &FinnyEvent) -> bool {
+    match (a, b) {
+        (FinnyEvent::Start, FinnyEvent::Start) | (FinnyEvent::Completion, FinnyEvent::Completion) => true,
+        (FinnyEvent::Event(a), FinnyEvent::Event(b)) => a == b,
+        _ => false
+    }
+}
+
+fn get_from_state(transition: &FinnyTransition) -> &str {
+    match transition.transition {
+        FinnyTransitionKind::SelfTransition { ref state_id } | FinnyTransitionKind::InternalTransition { ref state_id } => state_id,
+        FinnyTransitionKind::NormalTransition(ref normal) => &normal.from_state
+    }
+}
+
+fn validate_action<C>(name: &Option<String>, registry: &FsmRegistry<C>) -> FsmInterpreterResult<()> {
+    match name {
+        Some(name) if !registry.actions.contains_key(name) => Err(FsmInterpreterError::NotRegistered(name.clone())),
+        _ => Ok(())
+    }
+}
+
+fn validate_region<C>(region: &FinnyRegion, registry: &FsmRegistry<C>) -> FsmInterpreterResult<()> {
+    let check_action = |name: &Option<String>| validate_action(name, registry);
+
+    for (state_id, state) in &region.states {
+        match state {
+            FinnyStateKind::Stopped | FinnyStateKind::Choice(_) => (),
+            FinnyStateKind::State(state) => {
+                if !state.timers.is_empty() {
+                    return Err(FsmInterpreterError::NotSupported(format!("timers of the state '{}'", state_id)));
+                }
+                check_action(&state.on_entry)?;
+                check_action(&state.on_exit)?;
+                check_action(&state.unhandled.handler)?;
+            },
+            FinnyStateKind::SubMachine(_) => return Err(FsmInterpreterError::NotSupported(format!("submachine '{}'", state_id))),
+            FinnyStateKind::ExitPoint(_) => return Err(FsmInterpreterError::NotSupported(format!("exit point '{}'", state_id)))
+        }
+    }
+
+    let check_state = |state_id: &str| {
+        if state_id == STOPPED_STATE_ID || region.states.contains_key(state_id) {
+            Ok(())
+        } else {
+            Err(FsmInterpreterError::InvalidDescription(format!("unknown state '{}' in region {}", state_id, region.region_id)))
+        }
+    };
+
+    for t in &region.transitions {
+        match t.event {
+            FinnyEvent::Start | FinnyEvent::Completion | FinnyEvent::Event(_) => (),
+            _ => return Err(FsmInterpreterError::NotSupported(format!("the event of the transition '{}'", t.transition_id)))
+        }
+
+        match t.transition {
+            FinnyTransitionKind::SelfTransition { ref state_id } | FinnyTransitionKind::InternalTransition { ref state_id } => check_state(state_id)?,
+            FinnyTransitionKind::NormalTransition(ref normal) => {
+                if normal.entry_point.is_some() {
+                    return Err(FsmInterpreterError::NotSupported(format!("the entry point of the transition '{}'", t.transition_id)));
+                }
+                check_state(&normal.from_state)?;
+                check_state(&normal.to_state)?;
+            }
+        }
+
+        if let Some(ref guard) = t.guard {
+            if !registry.guards.contains_key(guard) {
+                return Err(FsmInterpreterError::NotRegistered(guard.clone()));
+            }
+        }
+        check_action(&t.action)?;
+    }
+
+    if !region.transitions.iter().any(|t| matches!(t.event, FinnyEvent::Start)) {
+        return Err(FsmInterpreterError::InvalidDescription(format!("region {} has no initial state", region.region_id)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! Structures that describe the FSM. Used by inspection frontends, documentation and by the
+//! interpreter of the machines that are defined at runtime.
+//!
+//! The `#[finny_fsm]` macro writes the description of the machines that call `fsm.meta_json()` as JSON, with
+//! the `generate_meta_json` feature of `finny`. The file is written into the directory from the `FINNY_META_DIR`
+//! environment variable, or into `OUT_DIR` of the crate's build script. The JSON schema is versioned with `FINNY_META_SCHEMA_VERSION`.
+//!
+//! The `diagrams` module draws the machines as PlantUML, Graphviz DOT or Mermaid diagrams.
+//!
+//! The closures of the generated machines are named after their states and transitions: `State::on_entry`,
+//! `State::on_exit`, `State::on_unhandled`, `Transition::guard` and `Transition::action`. The description
+//! isn't complete, it only names the submachines, the timers and the entry and exit points without their
+//! details. The actions that receive the triggering event and the states that are built on entry aren't
+//! described.
+
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+pub mod diagrams;
+pub mod interpreter;
+
+/// The version of the JSON schema, incremented with every incompatible change.
+pub const FINNY_META_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinnyFsm {
+    /// The version of the schema, see `FINNY_META_SCHEMA_VERSION`.
+    pub schema_version: u32,
+    pub fsm_id: String,
+    pub context_id: String,
+    pub regions: HashMap<usize, FinnyRegion>,
+    /// The machine-wide policy for the events that none of the regions handled.
+    #[serde(default)]
+    pub unhandled: FinnyUnhandledPolicy,
+    /// The fully qualified names of the types of the states, transitions and timers, by their ids. Only
+    /// known to the description that is embedded into the binary.
+    #[serde(skip)]
+    pub type_names: HashMap<String, String>
+}
+
+impl FinnyFsm {
+    /// Parse the JSON description of the machine.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinnyRegion {
+    pub region_id: usize,
+    pub states: HashMap<String, FinnyStateKind>,
+    /// The transitions, in the order in which they are checked.
+    pub transitions: Vec<FinnyTransition>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum FinnyStateKind {
+    Stopped,
+    State(FinnyState),
+    SubMachine(String),
+    Choice(String),
+    ExitPoint(String)
+}
+
+impl FinnyStateKind {
+    pub fn get_state_id(&self) -> String {
+        match self {
+            FinnyStateKind::Stopped => "Stopped".into(),
+            FinnyStateKind::State(s) => s.state_id.clone(),
+            FinnyStateKind::SubMachine(id) => id.clone(),
+            FinnyStateKind::Choice(id) => id.clone(),
+            FinnyStateKind::ExitPoint(id) => id.clone()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinnyState {
+    pub state_id: String,
+    #[serde(default)]
+    pub timers: Vec<FinnyTimer>,
+    #[serde(default)]
+    pub is_final: bool,
+    /// The name of the registered entry action, used by the interpreter.
+    #[serde(default)]
+    pub on_entry: Option<String>,
+    /// The name of the registered exit action, used by the interpreter.
+    #[serde(default)]
+    pub on_exit: Option<String>,
+    /// The events that are deferred in this state, if none of the transitions match them.
+    #[serde(default)]
+    pub deferred_events: Vec<String>,
+    /// The policy for the events that none of the transitions match in this state.
+    #[serde(default)]
+    pub unhandled: FinnyUnhandledPolicy
+}
+
+/// What to do with the events that none of the transitions match. Without any policy, such an event is an error.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FinnyUnhandledPolicy {
+    #[serde(default)]
+    pub ignored_events: Vec<String>,
+    #[serde(default)]
+    pub ignore_all: bool,
+    /// The name of the registered action that handles the events.
+    #[serde(default)]
+    pub handler: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinnyTransition {
+    pub transition_id: String,
+    pub event: FinnyEvent,
+    pub transition: FinnyTransitionKind,
+    /// The name of the registered guard, used by the interpreter.
+    #[serde(default)]
+    pub guard: Option<String>,
+    /// The name of the registered action, used by the interpreter.
+    #[serde(default)]
+    pub action: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum FinnyEvent {
+    Start,
+    Stop,
+    Completion,
+    EntryPoint(String),
+    ExitPoint(String),
+    Event(String)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum FinnyTransitionKind {
+    SelfTransition { state_id: String },
+    InternalTransition { state_id: String },
+    NormalTransition(FinnyTransitionNormal)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinnyTransitionNormal {
+    pub from_state: String,
+    pub to_state: String,
+    /// The submachine is entered through this entry point.
+    pub entry_point: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinnyTimer {
+    pub timer_id: String
+}
\ No newline at end of file
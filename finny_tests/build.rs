@@ -0,0 +1,4 @@
+// The JSON descriptions of the machines are written into OUT_DIR, unless FINNY_META_DIR is set.
+fn main() {
+    println!("cargo:rerun-if-env-changed=FINNY_META_DIR");
+}
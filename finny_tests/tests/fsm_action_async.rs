@@ -0,0 +1,293 @@
+extern crate finny;
+
+use std::{future::Future, pin::{Pin, pin}, sync::{Arc, Mutex}, task::{Context, Poll, Wake, Waker}, thread::{self, Thread}, time::Duration};
+
+use finny::{FsmAsyncSleep, FsmCurrentState, FsmEventQueueAsyncShared, FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::null::InspectNull, timers::std::TimersStd};
+
+/// A pending I/O operation that is completed by the test.
+#[derive(Clone, Default)]
+pub struct Operation {
+    state: Arc<Mutex<(Vec<&'static str>, bool, Option<Waker>)>>
+}
+
+impl Operation {
+    fn complete(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.1 = true;
+        if let Some(waker) = state.2.take() {
+            waker.wake();
+        }
+    }
+
+    fn log(&self) -> Vec<&'static str> {
+        self.state.lock().unwrap().0.clone()
+    }
+}
+
+impl Future for Operation {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.1 {
+            state.0.push("completed");
+            Poll::Ready(())
+        } else {
+            state.2 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ConfigContext {
+    operation: Operation,
+    pings: usize
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateSaved {
+    entries: usize
+}
+
+#[derive(Clone)]
+pub struct EventSave;
+#[derive(Clone)]
+pub struct EventPing;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<ConfigMachine, ConfigContext>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventSave>()
+        .transition_to::<StateSaved>()
+        .action_async(|_ev, ctx, _from, _to| {
+            let operation = ctx.operation.clone();
+            async move {
+                operation.await;
+            }
+        });
+
+    fsm.state::<StateSaved>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        })
+        .on_event::<EventPing>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.pings += 1;
+        });
+
+    fsm.build()
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park()
+        }
+    }
+}
+
+struct NoSleep;
+
+impl FsmAsyncSleep for NoSleep {
+    type Sleep = std::future::Pending<()>;
+
+    fn sleep(&mut self, _duration: Duration) -> Self::Sleep {
+        std::future::pending()
+    }
+}
+
+#[test]
+fn test_action_async() -> FsmResult<()> {
+    let operation = Operation::default();
+    let ctx = ConfigContext { operation: operation.clone(), pings: 0 };
+
+    let mut fsm = ConfigMachine::new_async_with(ctx, FsmEventQueueAsyncShared::new(), InspectNull::new(), TimersStd::new(), NoSleep)?;
+    fsm.start()?;
+
+    fsm.dispatch(EventSave)?;
+    assert_eq!(true, fsm.is_in_transition());
+    assert_eq!(FsmCurrentState::InTransition(ConfigMachineCurrentState::StateSaved), fsm.get_current_states()[0]);
+    let saved: &StateSaved = fsm.get_state();
+    assert_eq!(0, saved.entries);
+
+    // queued until the transition completes
+    fsm.dispatch(EventPing)?;
+    assert_eq!(0, fsm.pings);
+
+    let completer = {
+        let operation = operation.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            operation.complete();
+        })
+    };
+
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+    completer.join().unwrap();
+
+    assert_eq!(false, fsm.is_in_transition());
+    assert_eq!(vec!["completed"], operation.log());
+    assert_eq!(FsmCurrentState::State(ConfigMachineCurrentState::StateSaved), fsm.get_current_states()[0]);
+    let saved: &StateSaved = fsm.get_state();
+    assert_eq!(1, saved.entries);
+    assert_eq!(1, fsm.pings);
+
+    Ok(())
+}
+
+#[test]
+fn test_action_async_stop() -> FsmResult<()> {
+    let mut fsm = ConfigMachine::new_async_with(ConfigContext::default(), FsmEventQueueAsyncShared::new(), InspectNull::new(), TimersStd::new(), NoSleep)?;
+    fsm.start()?;
+
+    fsm.dispatch(EventSave)?;
+    assert_eq!(true, fsm.is_in_transition());
+
+    // the pending action is dropped, the target state is never entered
+    fsm.stop()?;
+    assert_eq!(false, fsm.is_in_transition());
+    assert_eq!(FsmCurrentState::Stopped, fsm.get_current_states()[0]);
+    let saved: &StateSaved = fsm.get_state();
+    assert_eq!(0, saved.entries);
+
+    fsm.start()?;
+    assert_eq!(FsmCurrentState::State(ConfigMachineCurrentState::StateIdle), fsm.get_current_states()[0]);
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct StationContext {
+    actions: Vec<&'static str>
+}
+
+#[derive(Default)]
+pub struct StationIdle;
+
+#[derive(Clone)]
+pub struct EventPowerOn;
+#[derive(Clone)]
+pub struct EventPrime;
+#[derive(Clone)]
+pub struct EventPowerOff;
+
+#[finny_fsm]
+fn build_station_fsm(mut fsm: FsmBuilder<StationMachine, StationContext>) -> BuiltFsm {
+    fsm.initial_state::<StationIdle>();
+
+    fsm.state::<StationIdle>()
+        .on_event::<EventPowerOn>()
+        .transition_to::<PumpMachine>()
+        .action_async(|_ev, ctx, _from, _to| {
+            ctx.actions.push("power");
+            async {}
+        });
+
+    // the same target, entered through its entry point
+    fsm.state::<StationIdle>()
+        .on_event::<EventPrime>()
+        .transition_to::<PumpMachine>()
+        .entry_point::<EntryPrime>()
+        .action_async(|_ev, ctx, _from, _to| {
+            ctx.actions.push("prime");
+            async {}
+        });
+
+    fsm.sub_machine::<PumpMachine>()
+        .on_event::<EventPowerOff>()
+        .transition_to::<StationIdle>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct PumpOff;
+#[derive(Default)]
+pub struct PumpPrimed;
+#[derive(Default)]
+pub struct PumpRunning {
+    entries: usize
+}
+
+#[derive(Default, Clone)]
+pub struct EntryPrime;
+#[derive(Clone)]
+pub struct EventRun;
+
+#[finny_fsm]
+fn build_pump_fsm(mut fsm: FsmBuilder<PumpMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<PumpOff>();
+    fsm.entry_point::<EntryPrime>().transition_to::<PumpPrimed>();
+
+    fsm.state::<PumpOff>()
+        .on_event::<EventRun>()
+        .transition_to::<PumpRunning>()
+        .action_async(|_ev, _ctx, _from, _to| {
+            async {}
+        });
+
+    fsm.state::<PumpRunning>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        });
+
+    fsm.state::<PumpPrimed>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_action_async_same_target() -> FsmResult<()> {
+    let mut fsm = StationMachine::new_async_with(StationContext::default(), FsmEventQueueAsyncShared::new(), InspectNull::new(), TimersStd::new(), NoSleep)?;
+    fsm.start()?;
+
+    // each transition enters the submachine its own way
+    fsm.dispatch(EventPrime)?;
+    assert_eq!(true, fsm.is_in_transition());
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+    assert!(fsm.is_in_state::<PumpPrimed, _>());
+
+    fsm.dispatch(EventPowerOff)?;
+    fsm.dispatch(EventPowerOn)?;
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+    assert!(fsm.is_in_state::<PumpOff, _>());
+    assert_eq!(vec!["prime", "power"], fsm.actions);
+
+    Ok(())
+}
+
+#[test]
+fn test_action_async_submachine() -> FsmResult<()> {
+    let mut fsm = StationMachine::new_async_with(StationContext::default(), FsmEventQueueAsyncShared::new(), InspectNull::new(), TimersStd::new(), NoSleep)?;
+    fsm.start()?;
+    fsm.dispatch(EventPowerOn)?;
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+
+    // the submachine's action is driven by the frontend of the outermost machine
+    fsm.dispatch(PumpMachineEvents::EventRun(EventRun))?;
+    assert_eq!(true, fsm.is_in_transition());
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+    assert_eq!(false, fsm.is_in_transition());
+    assert!(fsm.is_in_state::<PumpRunning, _>());
+    assert_eq!(Some(1), fsm.try_get_active_state::<PumpRunning, _>().map(|s| s.entries));
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+extern crate finny;
+
+use finny::{FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct StateParked {
+    visits: usize
+}
+#[derive(Default)]
+pub struct StateLightsOff;
+#[derive(Default)]
+pub struct StateLightsOn;
+
+#[derive(Clone)]
+pub struct EventDrive;
+#[derive(Clone)]
+pub struct EventPark;
+#[derive(Clone)]
+pub struct EventLights;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<CarMachine, ()>) -> BuiltFsm {
+    fsm.initial_states::<(StateParked, StateLightsOff)>();
+
+    fsm.state::<StateParked>()
+        .on_entry(|state, _ctx| {
+            state.visits += 1;
+        })
+        .on_event::<EventDrive>()
+        .transition_to::<GearboxMachine>();
+
+    fsm.sub_machine::<GearboxMachine>()
+        .on_event::<EventPark>()
+        .transition_to::<StateParked>();
+
+    fsm.state::<StateLightsOff>()
+        .on_event::<EventLights>()
+        .transition_to::<StateLightsOn>();
+
+    fsm.state::<StateLightsOn>()
+        .on_event::<EventLights>()
+        .transition_to::<StateLightsOff>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct StateFirstGear;
+#[derive(Default)]
+pub struct StateSecondGear {
+    shifts: usize
+}
+
+#[derive(Clone)]
+pub struct EventShiftUp;
+
+#[finny_fsm]
+fn build_gearbox_fsm(mut fsm: FsmBuilder<GearboxMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateFirstGear>();
+
+    fsm.state::<StateFirstGear>()
+        .on_event::<EventShiftUp>()
+        .transition_to::<StateSecondGear>()
+        .action(|_ev, _ctx, _from, to| {
+            to.shifts += 1;
+        });
+
+    fsm.state::<StateSecondGear>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_active_state() -> FsmResult<()> {
+    let mut fsm = CarMachine::new(())?;
+    assert!(!fsm.is_in_state::<StateParked, _>());
+
+    fsm.start()?;
+    assert!(fsm.is_in_state::<StateParked, _>());
+    assert!(fsm.is_in_state::<StateLightsOff, _>());
+    assert_eq!(Some(1), fsm.try_get_active_state::<StateParked, _>().map(|s| s.visits));
+
+    fsm.dispatch(EventLights)?;
+    assert!(fsm.is_in_state::<StateLightsOn, _>());
+    assert!(!fsm.is_in_state::<StateLightsOff, _>());
+
+    // the states of the submachine are checked as well
+    fsm.dispatch(EventDrive)?;
+    assert!(fsm.is_in_state::<GearboxMachine, _>());
+    assert!(fsm.is_in_state::<StateFirstGear, _>());
+    assert!(!fsm.is_in_state::<StateSecondGear, _>());
+    assert!(fsm.try_get_active_state::<StateParked, _>().is_none());
+    // still stored, but not active
+    assert_eq!(1, fsm.get_state::<StateParked>().visits);
+
+    fsm.dispatch(GearboxMachineEvents::EventShiftUp(EventShiftUp))?;
+    assert!(!fsm.is_in_state::<StateFirstGear, _>());
+    assert_eq!(Some(1), fsm.try_get_active_state::<StateSecondGear, _>().map(|s| s.shifts));
+
+    // the submachine isn't active anymore
+    fsm.dispatch(EventPark)?;
+    assert!(!fsm.is_in_state::<StateSecondGear, _>());
+    assert!(fsm.is_in_state::<StateParked, _>());
+    assert!(fsm.is_in_state::<StateLightsOn, _>());
+
+    Ok(())
+}
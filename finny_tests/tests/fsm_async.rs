@@ -0,0 +1,163 @@
+extern crate finny;
+
+use std::{future::Future, pin::pin, sync::{Arc, Mutex}, task::{Context, Poll, Wake, Waker}, thread::{self, Thread}, time::Duration};
+
+use finny::{AsyncFsmFrontend, FsmAsyncSleep, FsmCurrentState, FsmEventQueueAsyncShared, FsmEventQueueNoDeferral, FsmEventQueueSender, FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::null::InspectNull, timers::std::TimersStd};
+
+#[derive(Default)]
+pub struct AsyncContext {
+    ticks: usize
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateRunning;
+
+#[derive(Clone)]
+pub struct EventStart;
+#[derive(Clone)]
+pub struct EventTick;
+#[derive(Clone)]
+pub struct EventStop;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<AsyncMachine, AsyncContext>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventStart>()
+        .transition_to::<StateRunning>();
+
+    fsm.state::<StateRunning>()
+        .on_entry_start_timer(|_ctx, timer| {
+            timer.timeout = Duration::from_millis(20);
+            timer.renew = true;
+        }, |_ctx, _state| {
+            Some(EventTick.into())
+        })
+        .with_timer_ty::<TickTimer>()
+        .on_event::<EventTick>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.ticks += 1;
+        });
+
+    fsm.state::<StateRunning>()
+        .on_event::<EventStop>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+/// A minimal executor that parks the current thread until the future is woken up.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park()
+        }
+    }
+}
+
+/// Sleeps on a helper thread, which wakes the task once the duration has elapsed.
+struct ThreadSleep;
+
+struct ThreadSleepFuture {
+    duration: Duration,
+    state: Arc<Mutex<(bool, Option<Waker>)>>,
+    started: bool
+}
+
+impl Future for ThreadSleepFuture {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.0 {
+            return Poll::Ready(());
+        }
+        state.1 = Some(cx.waker().clone());
+        drop(state);
+
+        if !self.started {
+            self.started = true;
+            let duration = self.duration;
+            let state = self.state.clone();
+            thread::spawn(move || {
+                thread::sleep(duration);
+                let mut state = state.lock().unwrap();
+                state.0 = true;
+                if let Some(waker) = state.1.take() {
+                    waker.wake();
+                }
+            });
+        }
+
+        Poll::Pending
+    }
+}
+
+impl FsmAsyncSleep for ThreadSleep {
+    type Sleep = ThreadSleepFuture;
+
+    fn sleep(&mut self, duration: Duration) -> Self::Sleep {
+        ThreadSleepFuture { duration, state: Arc::new(Mutex::new((false, None))), started: false }
+    }
+}
+
+#[test]
+fn test_async_frontend() -> FsmResult<()> {
+    let queue = FsmEventQueueAsyncShared::new();
+    let frontend = AsyncMachine::new_with(AsyncContext::default(), queue.clone(), InspectNull::new(), TimersStd::new())?;
+    let mut fsm = AsyncFsmFrontend::new(frontend, ThreadSleep);
+    fsm.start()?;
+
+    let mut sender = queue.clone();
+    let producer = thread::spawn(move || {
+        sender.enqueue(EventStart).unwrap();
+        thread::sleep(Duration::from_millis(110));
+        sender.enqueue(EventStop).unwrap();
+        sender.close();
+    });
+
+    block_on(fsm.run())?;
+    producer.join().unwrap();
+
+    assert_eq!(FsmCurrentState::State(AsyncMachineCurrentState::StateIdle), fsm.get_current_states()[0]);
+    assert!(fsm.ticks >= 3, "ticks: {}", fsm.ticks);
+
+    Ok(())
+}
+
+#[test]
+fn test_async_dispatch_next() -> FsmResult<()> {
+    let mut queue = FsmEventQueueAsyncShared::new();
+    let frontend = AsyncMachine::new_with_deferred(AsyncContext::default(), queue.clone(), FsmEventQueueNoDeferral::new(), InspectNull::new(), TimersStd::new())?;
+    let mut fsm = AsyncFsmFrontend::new(frontend, ThreadSleep);
+    fsm.start()?;
+
+    queue.enqueue(EventStart)?;
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+    assert_eq!(FsmCurrentState::State(AsyncMachineCurrentState::StateRunning), fsm.get_current_states()[0]);
+
+    // nothing in the queue, the frontend wakes up for the timer
+    assert_eq!(true, block_on(fsm.dispatch_next())?);
+    assert_eq!(1, fsm.ticks);
+
+    queue.close();
+    assert_eq!(false, block_on(fsm.dispatch_next())?);
+
+    Ok(())
+}
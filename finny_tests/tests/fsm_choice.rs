@@ -0,0 +1,85 @@
+extern crate finny;
+
+use finny::{FsmCurrentState, FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct ChoiceContext {
+    temperature: i32,
+    actions: Vec<&'static str>
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateCold;
+#[derive(Default)]
+pub struct StateWarm;
+#[derive(Default)]
+pub struct StateHot;
+#[derive(Default)]
+pub struct ChoiceTemperature;
+
+#[derive(Clone)]
+pub struct EventMeasure { temperature: i32 }
+#[derive(Clone)]
+pub struct EventReset;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, ChoiceContext>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventMeasure>()
+        .transition_to::<ChoiceTemperature>()
+        .action(|ev, ctx, _, _| {
+            ctx.temperature = ev.temperature;
+        });
+
+    fsm.choice::<ChoiceTemperature>()
+        .transition_to::<StateCold>()
+        .guard(|_ev, ctx, _| ctx.temperature < 10);
+
+    fsm.choice::<ChoiceTemperature>()
+        .transition_to::<StateHot>()
+        .guard(|_ev, ctx, _| ctx.temperature > 30)
+        .action(|_ev, ctx, _, _| {
+            ctx.actions.push("hot");
+        });
+
+    fsm.choice::<ChoiceTemperature>()
+        .otherwise::<StateWarm>();
+
+    fsm.state::<StateCold>()
+        .on_event::<EventReset>()
+        .transition_to::<StateIdle>();
+
+    fsm.state::<StateWarm>()
+        .on_event::<EventReset>()
+        .transition_to::<StateIdle>();
+
+    fsm.state::<StateHot>()
+        .on_event::<EventReset>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_choice() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(ChoiceContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventMeasure { temperature: 5 })?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateCold), fsm.get_current_states()[0]);
+    fsm.dispatch(EventReset)?;
+
+    fsm.dispatch(EventMeasure { temperature: 20 })?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateWarm), fsm.get_current_states()[0]);
+    fsm.dispatch(EventReset)?;
+
+    fsm.dispatch(EventMeasure { temperature: 40 })?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateHot), fsm.get_current_states()[0]);
+    assert_eq!(vec!["hot"], fsm.actions);
+
+    Ok(())
+}
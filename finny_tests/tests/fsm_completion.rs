@@ -0,0 +1,86 @@
+extern crate finny;
+
+use finny::{FsmCurrentState, FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct CompletionContext {
+    value: usize,
+    actions: Vec<&'static str>
+}
+
+#[derive(Default)]
+pub struct StateInit;
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateDecide;
+#[derive(Default)]
+pub struct StateLow;
+#[derive(Default)]
+pub struct StateHigh;
+
+#[derive(Clone)]
+pub struct EventValue { value: usize }
+#[derive(Clone)]
+pub struct EventReset;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, CompletionContext>) -> BuiltFsm {
+    fsm.initial_state::<StateInit>();
+
+    // leaves the initial state right after the start
+    fsm.state::<StateInit>()
+        .on_completion()
+        .transition_to::<StateIdle>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.actions.push("init");
+        });
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventValue>()
+        .transition_to::<StateDecide>()
+        .action(|ev, ctx, _from, _to| {
+            ctx.value = ev.value;
+        });
+
+    // the first completion transition with a passing guard is taken
+    fsm.state::<StateDecide>()
+        .on_completion()
+        .transition_to::<StateHigh>()
+        .guard(|_ev, ctx, _| ctx.value > 10);
+
+    fsm.state::<StateDecide>()
+        .on_completion()
+        .transition_to::<StateLow>();
+
+    fsm.state::<StateLow>()
+        .on_event::<EventReset>()
+        .transition_to::<StateIdle>();
+
+    fsm.state::<StateHigh>()
+        .on_event::<EventReset>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_completion() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(CompletionContext::default())?;
+
+    fsm.start()?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateIdle), fsm.get_current_states()[0]);
+    assert_eq!(vec!["init"], fsm.actions);
+
+    fsm.dispatch(EventValue { value: 5 })?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateLow), fsm.get_current_states()[0]);
+
+    fsm.dispatch(EventReset)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateIdle), fsm.get_current_states()[0]);
+
+    fsm.dispatch(EventValue { value: 42 })?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateHigh), fsm.get_current_states()[0]);
+    assert_eq!(vec!["init"], fsm.actions);
+
+    Ok(())
+}
@@ -0,0 +1,224 @@
+extern crate finny;
+
+use finny::{FsmError, FsmFactory, FsmResult, FsmStateFactory, FsmStateFromEvent, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct TerminalContext {
+    logins: u32
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateCounting {
+    count: u32
+}
+#[derive(Default)]
+pub struct StateSession {
+    user: String,
+    requests: u32,
+    login: u32
+}
+
+impl FsmStateFromEvent<TerminalMachine, EventLogin> for StateSession {
+    fn from_event(event: &EventLogin, context: &TerminalContext) -> Self {
+        StateSession {
+            user: event.user.clone(),
+            requests: 0,
+            login: context.logins
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventCount;
+#[derive(Clone)]
+pub struct EventTick;
+#[derive(Clone)]
+pub struct EventRestart;
+#[derive(Clone)]
+pub struct EventLogin {
+    user: String
+}
+#[derive(Clone)]
+pub struct EventRequest;
+#[derive(Clone)]
+pub struct EventStop;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<TerminalMachine, TerminalContext>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventCount>()
+        .transition_to::<StateCounting>()
+        .action(|_ev, _ctx, _from, to| {
+            // already rebuilt for the action
+            assert_eq!(0, to.count);
+        });
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventLogin>()
+        .transition_to::<StateSession>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.logins += 1;
+        });
+
+    fsm.state::<StateCounting>()
+        .construct_on_entry()
+        .on_event::<EventTick>()
+        .internal_transition()
+        .action(|_ev, _ctx, state| {
+            state.count += 1;
+        });
+
+    fsm.state::<StateCounting>()
+        .on_event::<EventRestart>()
+        .self_transition();
+
+    fsm.state::<StateCounting>()
+        .on_event::<EventStop>()
+        .transition_to::<StateIdle>();
+
+    fsm.state::<StateSession>()
+        .construct_on_entry_from::<EventLogin>()
+        .on_event::<EventRequest>()
+        .internal_transition()
+        .action(|_ev, _ctx, state| {
+            state.requests += 1;
+        });
+
+    fsm.state::<StateSession>()
+        .on_event::<EventStop>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_construct_on_entry() -> FsmResult<()> {
+    let mut fsm = TerminalMachine::new(TerminalContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventCount)?;
+    fsm.dispatch(EventTick)?;
+    fsm.dispatch(EventTick)?;
+    let state: &StateCounting = fsm.get_state();
+    assert_eq!(2, state.count);
+
+    // the self transition re-enters the state
+    fsm.dispatch(EventRestart)?;
+    fsm.dispatch(EventTick)?;
+    let state: &StateCounting = fsm.get_state();
+    assert_eq!(1, state.count);
+
+    // nothing is kept from the earlier visit
+    fsm.dispatch(EventStop)?;
+    fsm.dispatch(EventCount)?;
+    let state: &StateCounting = fsm.get_state();
+    assert_eq!(0, state.count);
+
+    Ok(())
+}
+
+#[test]
+fn test_construct_on_entry_from_event() -> FsmResult<()> {
+    let mut fsm = TerminalMachine::new(TerminalContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventLogin { user: "alice".into() })?;
+    fsm.dispatch(EventRequest)?;
+    fsm.dispatch(EventRequest)?;
+    let state: &StateSession = fsm.get_state();
+    assert_eq!("alice", state.user);
+    assert_eq!(2, state.requests);
+    // built before the transition's action
+    assert_eq!(0, state.login);
+
+    fsm.dispatch(EventStop)?;
+    fsm.dispatch(EventLogin { user: "bob".into() })?;
+    let state: &StateSession = fsm.get_state();
+    assert_eq!("bob", state.user);
+    assert_eq!(0, state.requests);
+    assert_eq!(1, state.login);
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct MeterContext {
+    credits: u32,
+    exits: u32
+}
+
+#[derive(Default)]
+pub struct StateWaiting;
+pub struct StateMetered {
+    credits: u32
+}
+
+/// Can't be built without any credits.
+impl FsmStateFactory<MeterMachine> for StateMetered {
+    fn new_state(context: &MeterContext) -> FsmResult<Self> {
+        match context.credits {
+            0 => Err(FsmError::NotSupported),
+            credits => Ok(StateMetered { credits })
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventSpend;
+#[derive(Clone)]
+pub struct EventRefill;
+#[derive(Clone)]
+pub struct EventDone;
+
+#[finny_fsm]
+fn build_meter_fsm(mut fsm: FsmBuilder<MeterMachine, MeterContext>) -> BuiltFsm {
+    fsm.initial_state::<StateWaiting>();
+
+    fsm.state::<StateWaiting>()
+        .on_exit(|_state, ctx| {
+            ctx.exits += 1;
+        })
+        .on_event::<EventSpend>()
+        .transition_to::<StateMetered>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.credits = 0;
+        });
+
+    fsm.state::<StateWaiting>()
+        .on_event::<EventRefill>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.credits += 2;
+        });
+
+    fsm.state::<StateMetered>()
+        .construct_on_entry()
+        .on_event::<EventDone>()
+        .transition_to::<StateWaiting>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_construct_on_entry_error() -> FsmResult<()> {
+    let mut fsm = MeterMachine::new(MeterContext { credits: 1, exits: 0 })?;
+    fsm.start()?;
+
+    fsm.dispatch(EventRefill)?;
+    fsm.dispatch(EventSpend)?;
+    let state: &StateMetered = fsm.get_state();
+    assert_eq!(3, state.credits);
+    assert_eq!(1, fsm.get_context().exits);
+
+    // the state can't be built, the transition doesn't start
+    fsm.dispatch(EventDone)?;
+    assert!(matches!(fsm.dispatch(EventSpend), Err(FsmError::NotSupported)));
+    assert_eq!(1, fsm.get_context().exits);
+    assert!(fsm.is_in_state::<StateWaiting, _>());
+
+    Ok(())
+}
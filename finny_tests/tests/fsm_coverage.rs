@@ -0,0 +1,142 @@
+extern crate finny;
+
+use std::{any::type_name, time::Duration};
+
+use finny::{FsmEventQueueVec, FsmFactory, FsmResult, FsmTimersNull, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::coverage::InspectCoverage};
+
+#[derive(Default)]
+pub struct ValveContext {
+    pressure: u32
+}
+
+#[derive(Default)]
+pub struct StateClosed;
+#[derive(Default)]
+pub struct StateOpen;
+#[derive(Default)]
+pub struct StateVenting;
+
+#[derive(Clone)]
+pub struct EventOpen;
+#[derive(Clone)]
+pub struct EventClose;
+#[derive(Clone)]
+pub struct EventVent;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<ValveMachine, ValveContext>) -> BuiltFsm {
+    fsm.initial_state::<StateClosed>();
+
+    fsm.state::<StateClosed>()
+        .on_event::<EventOpen>()
+        .transition_to::<StateOpen>()
+        .with_transition_ty::<Open>()
+        .guard(|_ev, ctx, _states| ctx.pressure < 10);
+
+    fsm.state::<StateOpen>()
+        .on_event::<EventClose>()
+        .transition_to::<StateClosed>()
+        .with_transition_ty::<Close>()
+        .action(|_ev, ctx, _from, _to| { ctx.pressure += 10; });
+
+    fsm.state::<StateClosed>()
+        .on_event::<EventVent>()
+        .transition_to::<StateVenting>()
+        .with_transition_ty::<Vent>();
+
+    fsm.state::<StateVenting>()
+        .on_entry_start_timer(|_ctx, timer| {
+            timer.timeout = Duration::from_secs(60);
+        }, |_ctx, _state| {
+            Some(EventClose.into())
+        })
+        .with_timer_ty::<VentTimer>()
+        .on_event::<EventClose>()
+        .transition_to::<StateClosed>()
+        .with_transition_ty::<CloseVent>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_coverage() -> FsmResult<()> {
+    let coverage = InspectCoverage::new();
+    let mut fsm = ValveMachine::new_with(ValveContext::default(), FsmEventQueueVec::new(), coverage.clone(), FsmTimersNull)?;
+
+    fsm.start()?;
+    fsm.dispatch(EventOpen)?;
+    fsm.dispatch(EventClose)?;
+    // the pressure is too high, the guard rejects the transition
+    assert!(fsm.dispatch(EventOpen).is_err());
+
+    let data = coverage.data();
+    assert_eq!(Some(&1), data.transitions.get(type_name::<Open>()));
+    assert_eq!(Some(&1), data.guards_passed.get(type_name::<Open>()));
+    assert_eq!(Some(&1), data.guards_rejected.get(type_name::<Open>()));
+    assert_eq!(Some(&1), data.actions.get(type_name::<Close>()));
+    assert_eq!(Some(&2), data.state_entries.get(type_name::<StateClosed>()));
+    assert_eq!(Some(&1), data.state_exits.get(type_name::<StateOpen>()));
+    assert_eq!(None, data.state_entries.get("StateClosed"));
+
+    let report = coverage.report_for::<ValveMachine>();
+    assert_eq!(5, report.transitions);
+    assert_eq!(vec!["CloseVent", "Vent"], report.uncovered_transitions);
+    assert_eq!(60.0, report.transition_coverage());
+    assert!(report.uncovered_guard_outcomes.is_empty());
+    assert_eq!(100.0, report.action_coverage());
+    assert_eq!(vec!["StateVenting"], report.unvisited_states);
+    assert_eq!(vec!["VentTimer"], report.untriggered_timers);
+    assert!(!report.is_complete());
+
+    let text = report.to_string();
+    assert!(text.starts_with("Coverage of ValveMachine\nTransitions: 3/5 (60.0%)\n  not covered: CloseVent, Vent\n"));
+    assert!(text.contains("Guard outcomes: 2/2 (100.0%)\n"));
+
+    // the null timers never trigger, the timer and its event are dispatched directly
+    fsm.dispatch(EventVent)?;
+    fsm.dispatch_single_event(finny::FsmEvent::Timer(ValveMachineTimers::VentTimer))?;
+    fsm.dispatch(EventClose)?;
+    assert_eq!(Some(&1), coverage.data().timers.get(type_name::<VentTimer>()));
+
+    let report = coverage.report_for::<ValveMachine>();
+    assert!(report.is_complete(), "{}", report);
+    assert_eq!(100.0, report.state_coverage());
+
+    Ok(())
+}
+
+mod pipe {
+    use finny::{decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+    #[derive(Default)]
+    pub struct StateVenting;
+
+    #[finny_fsm]
+    fn build_fsm(mut fsm: FsmBuilder<PipeMachine, ()>) -> BuiltFsm {
+        fsm.initial_state::<StateVenting>();
+        fsm.state::<StateVenting>();
+        fsm.build()
+    }
+}
+
+#[test]
+fn test_coverage_same_names() -> FsmResult<()> {
+    let coverage = InspectCoverage::new();
+    let mut valve = ValveMachine::new_with(ValveContext::default(), FsmEventQueueVec::new(), coverage.clone(), FsmTimersNull)?;
+    let mut pipe = pipe::PipeMachine::new_with((), FsmEventQueueVec::new(), coverage.clone(), FsmTimersNull)?;
+
+    valve.start()?;
+    pipe.start()?;
+
+    // the state of the other machine has the same name, but a different type
+    let report = coverage.report_for::<ValveMachine>();
+    assert_eq!(vec!["StateOpen", "StateVenting"], report.unvisited_states);
+    assert!(coverage.report_for::<pipe::PipeMachine>().is_complete());
+
+    // a description without the types matches the names without their modules
+    let mut fsm = <ValveMachine as finny::FsmMeta>::fsm_meta();
+    fsm.type_names.clear();
+    assert_eq!(vec!["StateOpen"], coverage.report(&fsm).unvisited_states);
+
+    Ok(())
+}
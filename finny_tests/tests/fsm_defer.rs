@@ -0,0 +1,152 @@
+extern crate finny;
+
+use std::cell::Cell;
+
+use finny::{FsmCurrentState, FsmEventQueue, FsmEventQueueVec, FsmFactory, FsmResult, FsmTimersNull, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::null::InspectNull};
+
+#[derive(Default)]
+pub struct DeferContext {
+    handled: Vec<usize>,
+    /// How many times the work was offered to state A.
+    offered: Cell<usize>
+}
+
+#[derive(Default)]
+pub struct StateA;
+#[derive(Default)]
+pub struct StateB;
+#[derive(Default)]
+pub struct StateC;
+
+#[derive(Clone)]
+pub struct EventGo;
+#[derive(Clone)]
+pub struct EventWork { n: usize }
+#[derive(Clone)]
+pub struct EventLate;
+#[derive(Clone)]
+pub struct EventPing;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, DeferContext>) -> BuiltFsm {
+    fsm.initial_state::<StateA>();
+
+    // the late event can't be handled in state C anymore
+    fsm.state::<StateA>()
+        .defer_event::<EventWork>()
+        .defer_event::<EventLate>()
+        .on_event::<EventGo>()
+        .transition_to::<StateB>();
+
+    // the work is deferred when the guard rejects it
+    fsm.state::<StateA>()
+        .on_event::<EventWork>()
+        .transition_to::<StateC>()
+        .guard(|_ev, ctx, _| {
+            ctx.offered.set(ctx.offered.get() + 1);
+            false
+        });
+
+    fsm.state::<StateA>()
+        .on_event::<EventPing>()
+        .internal_transition();
+
+    // the work is deferred in state B as well, until the next state change
+    fsm.state::<StateB>()
+        .defer_event::<EventWork>()
+        .defer_event::<EventLate>()
+        .on_event::<EventGo>()
+        .transition_to::<StateC>();
+
+    fsm.state::<StateC>()
+        .on_event::<EventWork>()
+        .internal_transition()
+        .action(|ev, ctx, _| {
+            ctx.handled.push(ev.n);
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_defer() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(DeferContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventWork { n: 1 })?;
+    fsm.dispatch(EventWork { n: 2 })?;
+    assert_eq!(2, fsm.deferred.len());
+    assert!(fsm.handled.is_empty());
+
+    fsm.dispatch(EventGo)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateB), fsm.get_current_states()[0]);
+    assert_eq!(2, fsm.deferred.len());
+    assert!(fsm.handled.is_empty());
+
+    fsm.dispatch(EventGo)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateC), fsm.get_current_states()[0]);
+    assert_eq!(0, fsm.deferred.len());
+    assert_eq!(vec![1, 2], fsm.handled);
+
+    fsm.dispatch(EventWork { n: 3 })?;
+    assert_eq!(vec![1, 2, 3], fsm.handled);
+
+    Ok(())
+}
+
+#[test]
+fn test_defer_same_state() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(DeferContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventWork { n: 1 })?;
+    assert_eq!(1, fsm.offered.get());
+
+    // the machine stays in the deferring state, the work isn't redelivered
+    fsm.dispatch(EventPing)?;
+    fsm.dispatch(EventPing)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateA), fsm.get_current_states()[0]);
+    assert_eq!(1, fsm.offered.get());
+    assert_eq!(1, fsm.deferred.len());
+
+    fsm.dispatch(EventGo)?;
+    fsm.dispatch(EventGo)?;
+    assert_eq!(1, fsm.offered.get());
+    assert_eq!(vec![1], fsm.handled);
+
+    Ok(())
+}
+
+#[test]
+fn test_defer_redelivery_error() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(DeferContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventLate)?;
+    fsm.dispatch(EventWork { n: 1 })?;
+    fsm.dispatch(EventGo)?;
+    fsm.dispatch(EventGo)?;
+
+    // the late event is dropped, without failing the dispatch or the redelivery of the other events
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateC), fsm.get_current_states()[0]);
+    assert_eq!(0, fsm.deferred.len());
+    assert_eq!(vec![1], fsm.handled);
+
+    Ok(())
+}
+
+#[test]
+fn test_defer_with_deferred_queue() -> FsmResult<()> {
+    // the machine defers the events, it can't be built with `new_with`
+    let mut fsm = StateMachine::new_with_deferred(DeferContext::default(), FsmEventQueueVec::new(), FsmEventQueueVec::new(), InspectNull::new(), FsmTimersNull)?;
+    fsm.start()?;
+
+    fsm.dispatch(EventWork { n: 1 })?;
+    assert_eq!(1, fsm.deferred.len());
+
+    fsm.dispatch(EventGo)?;
+    fsm.dispatch(EventGo)?;
+    assert_eq!(vec![1], fsm.handled);
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+extern crate finny;
+
+use finny::{FsmEventQueueVec, FsmFactory, FsmResult, FsmTimersNull, bundled::finny_meta::diagrams::DiagramKind, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::highlight::InspectHighlight};
+
+#[derive(Default)]
+pub struct DoorContext;
+
+#[derive(Default)]
+pub struct StateClosed;
+#[derive(Default)]
+pub struct StateOpen;
+#[derive(Default)]
+pub struct StateUnlocked;
+#[derive(Default)]
+pub struct StateLocked;
+
+#[derive(Clone)]
+pub struct EventOpen;
+#[derive(Clone)]
+pub struct EventClose;
+#[derive(Clone)]
+pub struct EventLock;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<DoorMachine, DoorContext>) -> BuiltFsm {
+    fsm.initial_states::<(StateClosed, StateUnlocked)>();
+
+    fsm.state::<StateClosed>()
+        .on_event::<EventOpen>()
+        .transition_to::<StateOpen>()
+        .with_transition_ty::<Open>();
+
+    fsm.state::<StateOpen>()
+        .on_event::<EventClose>()
+        .transition_to::<StateClosed>()
+        .with_transition_ty::<Close>();
+
+    fsm.state::<StateUnlocked>()
+        .on_event::<EventLock>()
+        .transition_to::<StateLocked>()
+        .with_transition_ty::<Lock>();
+
+    fsm.state::<StateLocked>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_diagram_highlight() -> FsmResult<()> {
+    let inspect = InspectHighlight::new(2);
+    let mut fsm = DoorMachine::new_with(DoorContext::default(), FsmEventQueueVec::new(), inspect.clone(), FsmTimersNull)?;
+
+    // nothing is highlighted on the stopped machine
+    let dot = fsm.render_diagram(DiagramKind::Dot, Some(&inspect));
+    assert!(dot.starts_with("digraph \"DoorMachine\" {\ncompound=true;\n"));
+    assert!(!dot.contains("palegreen"));
+
+    fsm.start()?;
+    fsm.dispatch(EventOpen)?;
+    fsm.dispatch(EventLock)?;
+    assert_eq!(vec!["Lock", "Open"], inspect.recent_transitions());
+
+    let dot = fsm.render_diagram(DiagramKind::Dot, Some(&inspect));
+    assert!(dot.contains("DoorMachine_StateOpen [label=\"StateOpen\", shape=box, style=\"rounded,filled\", fillcolor=palegreen];"));
+    assert!(dot.contains("DoorMachine_StateLocked [label=\"StateLocked\", shape=box, style=\"rounded,filled\", fillcolor=palegreen];"));
+    assert!(dot.contains("DoorMachine_StateClosed [label=\"StateClosed\", shape=box, style=rounded];"));
+    assert!(dot.contains("DoorMachine_StateUnlocked -> DoorMachine_StateLocked [label=\"EventLock\", color=red, fontcolor=red, penwidth=2];"));
+    assert!(dot.contains("DoorMachine_StateClosed -> DoorMachine_StateOpen [label=\"EventOpen\", color=orange, fontcolor=orange];"));
+    assert!(dot.contains("DoorMachine_StateOpen -> DoorMachine_StateClosed [label=\"EventClose\"];"));
+
+    let plantuml = fsm.render_diagram(DiagramKind::PlantUml, Some(&inspect));
+    assert!(plantuml.starts_with("@startuml DoorMachine\n"));
+    assert!(plantuml.contains("state StateOpen #PaleGreen {\n"));
+    assert!(plantuml.contains("StateUnlocked -[#Red,bold]-> StateLocked : EventLock\n"));
+    assert!(plantuml.contains("StateClosed -[#Orange]-> StateOpen : EventOpen\n"));
+    assert!(plantuml.ends_with("@enduml\n"));
+
+    // the start transitions dropped out of the recorded history
+    fsm.dispatch(EventClose)?;
+    let mermaid = fsm.render_diagram(DiagramKind::Mermaid, Some(&inspect));
+    assert!(mermaid.contains("DoorMachine_StateOpen --> DoorMachine_StateClosed : EventClose (last)\n"));
+    assert!(mermaid.contains("DoorMachine_StateUnlocked --> DoorMachine_StateLocked : EventLock (recent)\n"));
+    assert!(mermaid.contains("DoorMachine_StateClosed --> DoorMachine_StateOpen : EventOpen\n"));
+    assert!(mermaid.contains("}\nclassDef current "));
+    assert!(mermaid.contains("class DoorMachine_StateClosed current\n"));
+    assert!(mermaid.contains("class DoorMachine_StateLocked current\n"));
+    assert!(!mermaid.contains("class DoorMachine_StateOpen current\n"));
+
+    // without the inspector only the current states are highlighted
+    let dot = fsm.render_diagram(DiagramKind::Dot, None);
+    assert!(dot.contains("DoorMachine_StateClosed [label=\"StateClosed\", shape=box, style=\"rounded,filled\", fillcolor=palegreen];"));
+    assert!(!dot.contains("color=red"));
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct StateParked;
+#[derive(Default)]
+pub struct StateFirstGear;
+#[derive(Default)]
+pub struct StateSecondGear;
+
+#[derive(Clone)]
+pub struct EventDrive;
+#[derive(Clone)]
+pub struct EventShiftUp;
+
+#[finny_fsm]
+fn build_car_fsm(mut fsm: FsmBuilder<CarMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateParked>();
+
+    fsm.state::<StateParked>()
+        .on_event::<EventDrive>()
+        .transition_to::<GearboxMachine>();
+
+    fsm.sub_machine::<GearboxMachine>();
+
+    fsm.build()
+}
+
+#[finny_fsm]
+fn build_gearbox_fsm(mut fsm: FsmBuilder<GearboxMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateFirstGear>();
+
+    fsm.state::<StateFirstGear>()
+        .on_event::<EventShiftUp>()
+        .transition_to::<StateSecondGear>()
+        .with_transition_ty::<ShiftUp>();
+
+    fsm.state::<StateSecondGear>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_diagram_highlight_submachine() -> FsmResult<()> {
+    let inspect = InspectHighlight::new(2);
+    let mut fsm = CarMachine::new_with((), FsmEventQueueVec::new(), inspect.clone(), FsmTimersNull)?;
+
+    // the inactive submachine is drawn without highlights
+    fsm.start()?;
+    let dot = fsm.render_diagram(DiagramKind::Dot, Some(&inspect));
+    assert!(dot.contains("GearboxMachine_StateFirstGear [label=\"StateFirstGear\", shape=box, style=rounded];"));
+
+    fsm.dispatch(EventDrive)?;
+    fsm.dispatch(GearboxMachineEvents::EventShiftUp(EventShiftUp))?;
+
+    let dot = fsm.render_diagram(DiagramKind::Dot, Some(&inspect));
+    assert!(dot.contains("subgraph cluster_CarMachine_GearboxMachine {\nlabel=\"GearboxMachine\";\nstyle=filled;\nfillcolor=palegreen;\n"));
+    assert!(dot.contains("GearboxMachine_StateSecondGear [label=\"StateSecondGear\", shape=box, style=\"rounded,filled\", fillcolor=palegreen];"));
+    assert!(dot.contains("GearboxMachine_StateFirstGear [label=\"StateFirstGear\", shape=box, style=rounded];"));
+    assert!(dot.contains("GearboxMachine_StateFirstGear -> GearboxMachine_StateSecondGear [label=\"EventShiftUp\", color=red, fontcolor=red, penwidth=2];"));
+
+    let plantuml = fsm.render_diagram(DiagramKind::PlantUml, Some(&inspect));
+    assert!(plantuml.contains("state GearboxMachine #PaleGreen {\n"));
+    assert!(plantuml.contains("state StateSecondGear #PaleGreen {\n"));
+
+    let mermaid = fsm.render_diagram(DiagramKind::Mermaid, Some(&inspect));
+    assert!(mermaid.contains("class CarMachine_GearboxMachine current\n"));
+    assert!(mermaid.contains("class GearboxMachine_StateSecondGear current\n"));
+    assert!(!mermaid.contains("class GearboxMachine_StateFirstGear current\n"));
+
+    Ok(())
+}
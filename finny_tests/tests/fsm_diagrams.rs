@@ -0,0 +1,112 @@
+#![cfg(feature = "diagrams")]
+
+extern crate finny;
+
+use std::time::Duration;
+
+use finny::{decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct PlayerContext {
+    tracks: usize
+}
+
+#[derive(Default)]
+pub struct StateStopped;
+#[derive(Default)]
+pub struct StateEjected;
+#[derive(Default)]
+pub struct StateLightOff;
+#[derive(Default)]
+pub struct StateLightOn;
+
+#[derive(Clone)]
+pub struct EventPlay;
+#[derive(Clone)]
+pub struct EventEject;
+#[derive(Clone)]
+pub struct EventToggle;
+#[derive(Clone)]
+pub struct EventNext;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<PlayerMachine, PlayerContext>) -> BuiltFsm {
+    fsm.initial_states::<(StateStopped, StateLightOff)>();
+
+    fsm.state::<StateStopped>()
+        .on_event::<EventPlay>()
+        .transition_to::<PlayingMachine>()
+        .with_transition_ty::<Play>()
+        .guard(|_ev, ctx, _states| ctx.tracks > 0);
+
+    fsm.sub_machine::<PlayingMachine>()
+        .on_event::<EventEject>()
+        .transition_to::<StateEjected>()
+        .with_transition_ty::<Eject>()
+        .action(|_ev, _ctx, _from, _to| { });
+
+    fsm.final_state::<StateEjected>();
+
+    fsm.state::<StateLightOff>()
+        .on_event::<EventToggle>()
+        .transition_to::<StateLightOn>();
+
+    fsm.state::<StateLightOn>()
+        .on_entry_start_timer(|_ctx, timer| {
+            timer.timeout = Duration::from_secs(10);
+        }, |_ctx, _state| {
+            Some(EventToggle.into())
+        })
+        .with_timer_ty::<LightTimer>()
+        .on_event::<EventToggle>()
+        .transition_to::<StateLightOff>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct StateTrack;
+
+#[finny_fsm]
+fn build_playing_fsm(mut fsm: FsmBuilder<PlayingMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateTrack>();
+
+    fsm.state::<StateTrack>()
+        .on_event::<EventNext>()
+        .self_transition();
+
+    fsm.build()
+}
+
+#[test]
+fn test_dot() {
+    let dot = PlayerMachineInfo::dot();
+
+    assert!(dot.starts_with("digraph \"PlayerMachine\" {\ncompound=true;\n"));
+    assert!(dot.contains("subgraph cluster_PlayerMachine_region_0 {"));
+    assert!(dot.contains("subgraph cluster_PlayerMachine_region_1 {"));
+    assert!(dot.contains("PlayerMachine_StateEjected [label=\"StateEjected\", shape=box, style=rounded, peripheries=2];"));
+    assert!(dot.contains("PlayerMachine_StateLightOn [label=\"StateLightOn\\nTimer LightTimer\", shape=box, style=rounded];"));
+    assert!(dot.contains("PlayerMachine_region_0_initial -> PlayerMachine_StateStopped [label=\"\"];"));
+
+    // the submachine is a nested cluster
+    assert!(dot.contains("subgraph cluster_PlayerMachine_PlayingMachine {\nlabel=\"PlayingMachine\";"));
+    assert!(dot.contains("PlayingMachine_StateTrack -> PlayingMachine_StateTrack [label=\"EventNext (Self)\"];"));
+
+    // the guards and the actions are named after their transitions
+    assert!(dot.contains("PlayerMachine_StateStopped -> PlayerMachine_PlayingMachine [label=\"EventPlay [Play::guard]\", lhead=cluster_PlayerMachine_PlayingMachine];"));
+    assert!(dot.contains("PlayerMachine_PlayingMachine -> PlayerMachine_StateEjected [label=\"EventEject / Eject::action\", ltail=cluster_PlayerMachine_PlayingMachine];"));
+}
+
+#[test]
+fn test_mermaid() {
+    let mermaid = PlayerMachineInfo::mermaid();
+
+    assert!(mermaid.starts_with("stateDiagram-v2\nstate PlayerMachine {\n"));
+    assert!(mermaid.contains("\n--\n"));
+    assert!(mermaid.contains("[*] --> PlayerMachine_StateStopped\n"));
+    assert!(mermaid.contains("PlayerMachine_StateEjected --> [*]\n"));
+    assert!(mermaid.contains("note right of PlayerMachine_StateLightOn : Timer LightTimer\n"));
+    assert!(mermaid.contains("state PlayerMachine_PlayingMachine {\nstate \"StateTrack\" as PlayingMachine_StateTrack\n"));
+    assert!(mermaid.contains("PlayerMachine_PlayingMachine --> PlayerMachine_StateEjected : EventEject / Eject::action\n"));
+}
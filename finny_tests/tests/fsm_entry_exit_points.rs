@@ -0,0 +1,140 @@
+extern crate finny;
+
+use finny::{FsmCurrentState, FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct MainContext {
+    faults: usize
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateFault;
+
+#[derive(Clone)]
+pub struct EventPower;
+#[derive(Clone)]
+pub struct EventAlarm;
+#[derive(Clone)]
+pub struct EventBooted;
+#[derive(Clone)]
+pub struct EventFail;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, MainContext>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventPower>()
+        .transition_to::<Device>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventAlarm>()
+        .transition_to::<Device>()
+        .entry_point::<EntryAlarm>();
+
+    fsm.sub_machine::<Device>()
+        .on_exit_point::<ExitFault>()
+        .transition_to::<StateFault>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.faults += 1;
+        });
+
+    fsm.state::<StateFault>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct DeviceBoot {
+    entries: usize
+}
+#[derive(Default)]
+pub struct DeviceRunning;
+#[derive(Default)]
+pub struct DeviceAlarm {
+    entries: usize
+}
+
+#[derive(Default, Clone)]
+pub struct EntryAlarm;
+#[derive(Default, Clone)]
+pub struct ExitFault;
+
+#[finny_fsm]
+fn build_device_fsm(mut fsm: FsmBuilder<Device, ()>) -> BuiltFsm {
+    fsm.initial_state::<DeviceBoot>();
+    fsm.entry_point::<EntryAlarm>().transition_to::<DeviceAlarm>();
+    fsm.exit_point::<ExitFault>();
+
+    fsm.state::<DeviceBoot>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        })
+        .on_event::<EventBooted>()
+        .transition_to::<DeviceRunning>();
+
+    fsm.state::<DeviceRunning>()
+        .on_event::<EventFail>()
+        .transition_to::<ExitFault>();
+
+    fsm.state::<DeviceAlarm>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        })
+        .on_event::<EventFail>()
+        .transition_to::<ExitFault>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_entry_point() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(MainContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventAlarm)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::Device), fsm.get_current_states()[0]);
+
+    let device: &Device = fsm.get_state();
+    assert_eq!(FsmCurrentState::State(DeviceCurrentState::DeviceAlarm), device.get_current_states()[0]);
+    let boot: &DeviceBoot = device.get_state();
+    assert_eq!(0, boot.entries);
+    let alarm: &DeviceAlarm = device.get_state();
+    assert_eq!(1, alarm.entries);
+
+    Ok(())
+}
+
+#[test]
+fn test_exit_point() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(MainContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventPower)?;
+    let device: &Device = fsm.get_state();
+    assert_eq!(FsmCurrentState::State(DeviceCurrentState::DeviceBoot), device.get_current_states()[0]);
+
+    let ev: DeviceEvents = EventBooted.into();
+    fsm.dispatch(ev)?;
+    let ev: DeviceEvents = EventFail.into();
+    fsm.dispatch(ev)?;
+
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateFault), fsm.get_current_states()[0]);
+    assert_eq!(1, fsm.faults);
+
+    Ok(())
+}
+
+#[test]
+fn test_exit_point_standalone() -> FsmResult<()> {
+    let mut device = Device::new(())?;
+    device.start()?;
+
+    device.dispatch(EventBooted)?;
+    device.dispatch(EventFail)?;
+    assert_eq!(FsmCurrentState::State(DeviceCurrentState::ExitFault), device.get_current_states()[0]);
+
+    Ok(())
+}
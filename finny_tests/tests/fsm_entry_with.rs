@@ -0,0 +1,103 @@
+extern crate finny;
+
+use finny::{FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct OrderContext {
+    log: Vec<String>
+}
+
+#[derive(Default)]
+pub struct StateCart;
+#[derive(Default)]
+pub struct StateCheckout {
+    order_id: u32,
+    items: usize
+}
+
+#[derive(Clone)]
+pub struct EventCheckout {
+    order_id: u32,
+    items: usize
+}
+#[derive(Clone)]
+pub struct EventPay {
+    amount: u32
+}
+#[derive(Clone)]
+pub struct EventCancel;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<OrderMachine, OrderContext>) -> BuiltFsm {
+    fsm.initial_state::<StateCart>();
+
+    fsm.state::<StateCart>()
+        .on_exit(|_state, ctx| {
+            ctx.log.push("exit cart".into());
+        })
+        .on_exit_with::<EventCheckout>(|_state, ev, ctx| {
+            ctx.log.push(format!("exit cart with order {}", ev.order_id));
+        })
+        .on_event::<EventCheckout>()
+        .transition_to::<StateCheckout>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.log.push("checkout".into());
+        });
+
+    fsm.state::<StateCheckout>()
+        .on_entry(|_state, ctx| {
+            ctx.log.push("enter checkout".into());
+        })
+        .on_entry_with::<EventCheckout>(|state, ev, ctx| {
+            state.order_id = ev.order_id;
+            state.items = ev.items;
+            ctx.log.push(format!("enter checkout with order {}", ev.order_id));
+        })
+        .on_exit_with::<EventPay>(|state, ev, ctx| {
+            ctx.log.push(format!("paid {} for order {}", ev.amount, state.order_id));
+        })
+        .on_event::<EventPay>()
+        .self_transition();
+
+    fsm.state::<StateCheckout>()
+        .on_event::<EventCancel>()
+        .transition_to::<StateCart>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_entry_with_event() -> FsmResult<()> {
+    let mut fsm = OrderMachine::new(OrderContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventCheckout { order_id: 42, items: 3 })?;
+    let state: &StateCheckout = fsm.get_state();
+    assert_eq!(42, state.order_id);
+    assert_eq!(3, state.items);
+
+    assert_eq!(vec![
+        "exit cart with order 42",
+        "exit cart",
+        "checkout",
+        "enter checkout",
+        "enter checkout with order 42"
+    ], fsm.log);
+    fsm.log.clear();
+
+    // the actions of the other events aren't executed
+    fsm.dispatch(EventPay { amount: 10 })?;
+    fsm.dispatch(EventCancel)?;
+    fsm.dispatch(EventCheckout { order_id: 43, items: 1 })?;
+    assert_eq!(vec![
+        "paid 10 for order 42",
+        "enter checkout",
+        "exit cart with order 43",
+        "exit cart",
+        "checkout",
+        "enter checkout",
+        "enter checkout with order 43"
+    ], fsm.log);
+
+    Ok(())
+}
@@ -0,0 +1,439 @@
+extern crate finny;
+
+use std::{future::Future, pin::pin, sync::atomic::{AtomicUsize, Ordering}, task::{Context, Poll, Waker}, thread::sleep, time::Duration};
+
+use finny::{FsmAsyncSleep, FsmCurrentState, FsmEventQueueAsyncShared, FsmEventQueueVec, FsmFactory, FsmResult, FsmStateFromEvent, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::null::InspectNull, timers::std::TimersStd};
+
+static RECORDINGS_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+pub struct RecorderContext {
+    uploads: usize
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+
+pub struct StateRecording {
+    samples: [u8; 256],
+    len: usize
+}
+
+impl Default for StateRecording {
+    fn default() -> Self {
+        StateRecording { samples: [0; 256], len: 0 }
+    }
+}
+
+impl Drop for StateRecording {
+    fn drop(&mut self) {
+        RECORDINGS_DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Default)]
+pub struct StateUploading {
+    target: String,
+    bytes: usize
+}
+
+impl FsmStateFromEvent<RecorderMachine, EventUpload> for StateUploading {
+    fn from_event(event: &EventUpload, _context: &RecorderContext) -> Self {
+        StateUploading {
+            target: event.target.clone(),
+            bytes: 0
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventRecord;
+#[derive(Clone)]
+pub struct EventSample(u8);
+#[derive(Clone)]
+pub struct EventRestart;
+#[derive(Clone)]
+pub struct EventUpload {
+    target: String
+}
+#[derive(Clone)]
+pub struct EventDone;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<RecorderMachine, RecorderContext>) -> BuiltFsm {
+    fsm.enum_state_storage();
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventRecord>()
+        .transition_to::<StateRecording>();
+
+    fsm.state::<StateRecording>()
+        .on_event::<EventSample>()
+        .internal_transition()
+        .action(|ev, _ctx, state| {
+            state.samples[state.len] = ev.0;
+            state.len += 1;
+        });
+
+    fsm.state::<StateRecording>()
+        .on_event::<EventRestart>()
+        .self_transition();
+
+    fsm.state::<StateRecording>()
+        .on_event::<EventUpload>()
+        .transition_to::<StateUploading>()
+        .action(|_ev, ctx, from, to| {
+            ctx.uploads += 1;
+            to.bytes = from.samples[..from.len].len();
+        });
+
+    fsm.state::<StateUploading>()
+        .construct_on_entry_from::<EventUpload>()
+        .on_event::<EventDone>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_enum_storage() -> FsmResult<()> {
+    // only the largest of the states takes up the space
+    assert!(std::mem::size_of::<RecorderMachineStates>() < std::mem::size_of::<StateRecording>() + std::mem::size_of::<StateUploading>());
+
+    let mut fsm = RecorderMachine::new(RecorderContext::default())?;
+    assert!(fsm.try_get_state::<StateIdle>().is_none());
+
+    fsm.start()?;
+    assert!(fsm.try_get_state::<StateIdle>().is_some());
+
+    fsm.dispatch(EventRecord)?;
+    assert!(fsm.try_get_state::<StateIdle>().is_none());
+    fsm.dispatch(EventSample(1))?;
+    fsm.dispatch(EventSample(2))?;
+    let state = fsm.try_get_state::<StateRecording>().unwrap();
+    assert_eq!(&[1, 2], &state.samples[..state.len]);
+
+    // the self transition rebuilds the state
+    fsm.dispatch(EventRestart)?;
+    assert_eq!(1, RECORDINGS_DROPPED.load(Ordering::SeqCst));
+    fsm.dispatch(EventSample(3))?;
+    assert_eq!(Some(1), fsm.try_get_state::<StateRecording>().map(|s| s.len));
+
+    // the exited state is available to the action and dropped afterwards
+    fsm.dispatch(EventUpload { target: "backup".into() })?;
+    assert_eq!(2, RECORDINGS_DROPPED.load(Ordering::SeqCst));
+    assert!(fsm.try_get_state::<StateRecording>().is_none());
+    let state = fsm.try_get_state::<StateUploading>().unwrap();
+    assert_eq!("backup", state.target);
+    assert_eq!(1, state.bytes);
+    assert_eq!(1, fsm.uploads);
+
+    fsm.dispatch(EventDone)?;
+    assert!(fsm.try_get_state::<StateUploading>().is_none());
+    assert!(fsm.try_get_state::<StateIdle>().is_some());
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct HeaterContext {
+    overheats: usize
+}
+
+#[derive(Default)]
+pub struct HeaterOff;
+#[derive(Default)]
+pub struct HeaterOn;
+
+#[derive(Clone)]
+pub struct EventOn;
+#[derive(Clone)]
+pub struct EventOff;
+#[derive(Clone)]
+pub struct EventOverheat;
+
+#[finny_fsm]
+fn build_heater_fsm(mut fsm: FsmBuilder<HeaterMachine, HeaterContext>) -> BuiltFsm {
+    fsm.enum_state_storage();
+    fsm.initial_state::<HeaterOff>();
+
+    fsm.state::<HeaterOff>()
+        .on_event::<EventOn>()
+        .transition_to::<HeaterOn>();
+
+    fsm.state::<HeaterOn>()
+        .on_entry_start_timer(|_ctx, timer| {
+            timer.timeout = Duration::from_millis(50);
+            timer.cancel_on_state_exit = false;
+        }, |_ctx, _state| {
+            Some(EventOverheat.into())
+        })
+        .with_timer_ty::<OverheatTimer>()
+        .on_event::<EventOff>()
+        .transition_to::<HeaterOff>();
+
+    fsm.state::<HeaterOff>()
+        .on_event::<EventOverheat>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.overheats += 1;
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_enum_storage_timers() -> FsmResult<()> {
+    let mut fsm = HeaterMachine::new_with(HeaterContext::default(), FsmEventQueueVec::new(), InspectNull::new(), TimersStd::new())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventOn)?;
+    fsm.dispatch(EventOff)?;
+
+    // the exited state isn't stored, its timer is cancelled even though it's kept running on the exit
+    sleep(Duration::from_millis(100));
+    fsm.dispatch_timer_events()?;
+    assert_eq!(0, fsm.overheats);
+    assert!(fsm.try_get_state::<HeaterOff>().is_some());
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct SorterContext {
+    weight: usize,
+    sorted: Vec<&'static str>
+}
+
+#[derive(Default)]
+pub struct SorterIdle;
+#[derive(Default)]
+pub struct SorterLight;
+#[derive(Default)]
+pub struct SorterHeavy;
+#[derive(Default)]
+pub struct ChoiceWeight;
+#[derive(Default)]
+pub struct SorterCounter {
+    items: usize
+}
+
+#[derive(Clone)]
+pub struct EventItem { weight: usize }
+
+#[finny_fsm]
+fn build_sorter_fsm(mut fsm: FsmBuilder<SorterMachine, SorterContext>) -> BuiltFsm {
+    fsm.enum_state_storage();
+    fsm.initial_states::<(SorterIdle, SorterCounter)>();
+
+    // region 1
+
+    fsm.state::<SorterIdle>()
+        .on_event::<EventItem>()
+        .transition_to::<ChoiceWeight>()
+        .action(|ev, ctx, _, _| {
+            ctx.weight = ev.weight;
+        });
+
+    fsm.choice::<ChoiceWeight>()
+        .transition_to::<SorterHeavy>()
+        .guard(|_ev, ctx, _| ctx.weight > 10);
+
+    fsm.choice::<ChoiceWeight>()
+        .otherwise::<SorterLight>();
+
+    fsm.state::<SorterLight>()
+        .on_completion()
+        .transition_to::<SorterIdle>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.sorted.push("light");
+        });
+
+    fsm.state::<SorterHeavy>()
+        .on_completion()
+        .transition_to::<SorterIdle>()
+        .action(|_ev, ctx, _from, _to| {
+            ctx.sorted.push("heavy");
+        });
+
+    // region 2
+
+    fsm.state::<SorterCounter>()
+        .on_event::<EventItem>()
+        .internal_transition()
+        .action(|_ev, _ctx, state| {
+            state.items += 1;
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_enum_storage_regions() -> FsmResult<()> {
+    let mut fsm = SorterMachine::new(SorterContext::default())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventItem { weight: 5 })?;
+    fsm.dispatch(EventItem { weight: 20 })?;
+    assert_eq!(vec!["light", "heavy"], fsm.sorted);
+    assert_eq!([FsmCurrentState::State(SorterMachineCurrentState::SorterIdle), FsmCurrentState::State(SorterMachineCurrentState::SorterCounter)], fsm.get_current_states());
+    assert!(fsm.try_get_state::<SorterIdle>().is_some());
+    assert_eq!(Some(2), fsm.try_get_state::<SorterCounter>().map(|s| s.items));
+
+    // the stop empties the regions, the start builds the initial states again
+    fsm.stop()?;
+    assert!(fsm.try_get_state::<SorterIdle>().is_none());
+    assert!(fsm.try_get_state::<SorterCounter>().is_none());
+
+    fsm.start()?;
+    assert_eq!(Some(0), fsm.try_get_state::<SorterCounter>().map(|s| s.items));
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct UploaderContext {
+    uploads: usize
+}
+
+#[derive(Default)]
+pub struct UploadIdle;
+#[derive(Default)]
+pub struct UploadSent {
+    entries: usize
+}
+
+#[derive(Clone)]
+pub struct EventSend;
+
+#[finny_fsm]
+fn build_uploader_fsm(mut fsm: FsmBuilder<UploaderMachine, UploaderContext>) -> BuiltFsm {
+    fsm.enum_state_storage();
+    fsm.initial_state::<UploadIdle>();
+
+    fsm.state::<UploadIdle>()
+        .on_event::<EventSend>()
+        .transition_to::<UploadSent>()
+        .action_async(|_ev, ctx, _from, _to| {
+            ctx.uploads += 1;
+            async {}
+        });
+
+    fsm.state::<UploadSent>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        });
+
+    fsm.build()
+}
+
+fn poll_ready<F: Future>(future: F) -> F::Output {
+    let mut cx = Context::from_waker(Waker::noop());
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("The future isn't ready.")
+    }
+}
+
+struct NoSleep;
+
+impl FsmAsyncSleep for NoSleep {
+    type Sleep = std::future::Pending<()>;
+
+    fn sleep(&mut self, _duration: Duration) -> Self::Sleep {
+        std::future::pending()
+    }
+}
+
+#[test]
+fn test_enum_storage_action_async() -> FsmResult<()> {
+    let mut fsm = UploaderMachine::new_async_with(UploaderContext::default(), FsmEventQueueAsyncShared::new(), InspectNull::new(), TimersStd::new(), NoSleep)?;
+    fsm.start()?;
+
+    fsm.dispatch(EventSend)?;
+    assert_eq!(true, fsm.is_in_transition());
+    assert_eq!(1, fsm.uploads);
+
+    assert_eq!(true, poll_ready(fsm.dispatch_next())?);
+    assert_eq!(false, fsm.is_in_transition());
+    assert!(fsm.try_get_state::<UploadIdle>().is_none());
+    assert_eq!(Some(1), fsm.try_get_state::<UploadSent>().map(|s| s.entries));
+
+    Ok(())
+}
+
+#[test]
+fn test_enum_storage_action_async_stop() -> FsmResult<()> {
+    let mut fsm = UploaderMachine::new_async_with(UploaderContext::default(), FsmEventQueueAsyncShared::new(), InspectNull::new(), TimersStd::new(), NoSleep)?;
+    fsm.start()?;
+
+    fsm.dispatch(EventSend)?;
+    assert_eq!(true, fsm.is_in_transition());
+
+    // the pending action is dropped together with the exited state
+    fsm.stop()?;
+    assert_eq!(FsmCurrentState::Stopped, fsm.get_current_states()[0]);
+    assert!(fsm.try_get_state::<UploadIdle>().is_none());
+    assert!(fsm.try_get_state::<UploadSent>().is_none());
+
+    fsm.start()?;
+    assert!(fsm.try_get_state::<UploadIdle>().is_some());
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+mod snapshots {
+    use finny::{FsmFactory, FsmResult, FsmSnapshot, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Default, Serialize, Deserialize)]
+    pub struct CounterIdle;
+    #[derive(Default, Serialize, Deserialize)]
+    pub struct CounterCounting {
+        count: usize
+    }
+
+    #[derive(Clone)]
+    pub struct EventCount;
+
+    #[finny_fsm]
+    fn build_counter_fsm(mut fsm: FsmBuilder<CounterMachine, ()>) -> BuiltFsm {
+        fsm.enum_state_storage();
+        fsm.serde_snapshots();
+        fsm.initial_state::<CounterIdle>();
+
+        fsm.state::<CounterIdle>()
+            .on_event::<EventCount>()
+            .transition_to::<CounterCounting>();
+
+        fsm.state::<CounterCounting>()
+            .on_event::<EventCount>()
+            .internal_transition()
+            .action(|_ev, _ctx, state| {
+                state.count += 1;
+            });
+
+        fsm.build()
+    }
+
+    #[test]
+    fn test_enum_storage_snapshot() -> FsmResult<()> {
+        let mut fsm = CounterMachine::new(())?;
+        fsm.start()?;
+        fsm.dispatch(EventCount)?;
+        fsm.dispatch(EventCount)?;
+
+        let json = serde_json::to_string(&fsm.snapshot()).unwrap();
+        let snapshot: FsmSnapshot<_> = serde_json::from_str(&json).unwrap();
+        let mut restored = CounterMachine::restore(snapshot)?;
+        assert!(restored.try_get_state::<CounterIdle>().is_none());
+        assert_eq!(Some(1), restored.try_get_state::<CounterCounting>().map(|s| s.count));
+
+        restored.dispatch(EventCount)?;
+        assert_eq!(Some(2), restored.try_get_state::<CounterCounting>().map(|s| s.count));
+
+        Ok(())
+    }
+}
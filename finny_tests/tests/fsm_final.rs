@@ -0,0 +1,113 @@
+extern crate finny;
+
+use finny::{FsmCurrentState, FsmFactory, FsmResult, SubCompleted, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateDone;
+#[derive(Default)]
+pub struct StateCleanup;
+
+#[derive(Clone)]
+pub struct EventStart;
+#[derive(Clone)]
+pub struct EventFinish;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventStart>()
+        .transition_to::<Job>();
+
+    fsm.sub_machine::<Job>()
+        .on_event::<SubCompleted<Job>>()
+        .transition_to::<StateDone>();
+
+    fsm.final_state::<StateDone>();
+
+    fsm.build()
+}
+
+#[finny_fsm]
+fn build_completion_fsm(mut fsm: FsmBuilder<CompletionMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventStart>()
+        .transition_to::<Job>();
+
+    fsm.sub_machine::<Job>()
+        .on_completion()
+        .transition_to::<StateCleanup>();
+
+    fsm.state::<StateCleanup>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct JobRunning;
+#[derive(Default)]
+pub struct JobFinished;
+
+#[finny_fsm]
+fn build_job_fsm(mut fsm: FsmBuilder<Job, ()>) -> BuiltFsm {
+    fsm.initial_state::<JobRunning>();
+
+    fsm.state::<JobRunning>()
+        .on_event::<EventFinish>()
+        .transition_to::<JobFinished>();
+
+    fsm.final_state::<JobFinished>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_is_completed() -> FsmResult<()> {
+    let mut job = Job::new(())?;
+    job.start()?;
+    assert_eq!(false, job.is_completed());
+
+    job.dispatch(EventFinish)?;
+    assert_eq!(FsmCurrentState::State(JobCurrentState::JobFinished), job.get_current_states()[0]);
+    assert_eq!(true, job.is_completed());
+
+    Ok(())
+}
+
+#[test]
+fn test_sub_completed() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventStart)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::Job), fsm.get_current_states()[0]);
+    assert_eq!(false, fsm.is_completed());
+
+    let ev: JobEvents = EventFinish.into();
+    fsm.dispatch(ev)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateDone), fsm.get_current_states()[0]);
+    assert_eq!(true, fsm.is_completed());
+
+    Ok(())
+}
+
+#[test]
+fn test_sub_on_completion() -> FsmResult<()> {
+    let mut fsm = CompletionMachine::new(())?;
+    fsm.start()?;
+
+    // the submachine isn't completed yet, the completion transition waits
+    fsm.dispatch(EventStart)?;
+    assert_eq!(FsmCurrentState::State(CompletionMachineCurrentState::Job), fsm.get_current_states()[0]);
+
+    let ev: JobEvents = EventFinish.into();
+    fsm.dispatch(ev)?;
+    assert_eq!(FsmCurrentState::State(CompletionMachineCurrentState::StateCleanup), fsm.get_current_states()[0]);
+
+    Ok(())
+}
@@ -92,7 +92,23 @@ fn test_fsm() -> FsmResult<()> {
     assert_eq!(1, state.enter);
 
     let ret = fsm.dispatch(EventClick { time: 99 });
-    assert_eq!(Err(FsmError::NoTransition), ret);
+    match ret {
+        Err(FsmError::NoTransition(ref ctx)) => {
+            assert_eq!("EventClick", ctx.event);
+            assert_eq!(std::any::type_name::<StateMachine>(), ctx.fsm);
+            let named: Vec<_> = ctx.states().collect();
+            assert_eq!(1, named.len());
+            assert_eq!(0, named[0].region);
+            assert_eq!(FsmCurrentState::State("StateA"), named[0].state);
+            let states = ctx.typed_states::<StateMachine>().unwrap();
+            assert_eq!(&[FsmCurrentState::State(StateMachineCurrentState::StateA)], states.as_slice());
+        },
+        _ => panic!("Expected NoTransition, got {:?}", ret)
+    }
+    let msg = ret.unwrap_err().to_string();
+    assert_eq!("No transition for the event 'EventClick' in 'fsm_fn::StateMachine', states: [region 0: StateA]", msg);
+    // the context is kept small, the errors are returned by value
+    assert!(std::mem::size_of::<FsmError>() <= 128);
     
     fsm.dispatch(EventClick { time: 123 })?;
 
@@ -103,7 +119,7 @@ fn test_fsm() -> FsmResult<()> {
     assert_eq!(1, state_b.counter);
 
     let ret = fsm.dispatch(EventEnter { shift: true });
-    assert_eq!(Err(FsmError::NoTransition), ret);
+    assert!(matches!(ret, Err(FsmError::NoTransition(_))));
     
     fsm.dispatch(EventEnter { shift: false })?;
     let state_b: &StateB = fsm.get_state();
@@ -0,0 +1,98 @@
+extern crate finny;
+
+use std::{thread, time::Duration};
+
+use finny::{FsmCurrentState, FsmError, FsmEventQueueVec, FsmFactory, FsmResult, FsmThread, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::null::InspectNull, timers::std::TimersStd};
+
+#[derive(Default)]
+pub struct CounterContext {
+    increments: usize,
+    ticks: usize
+}
+
+#[derive(Default)]
+pub struct StateIdle;
+#[derive(Default)]
+pub struct StateCounting;
+
+#[derive(Clone)]
+pub struct EventStart;
+#[derive(Clone)]
+pub struct EventIncrement;
+#[derive(Clone)]
+pub struct EventTick;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<CounterMachine, CounterContext>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventStart>()
+        .transition_to::<StateCounting>();
+
+    fsm.state::<StateCounting>()
+        .on_entry_start_timer(|_ctx, timer| {
+            timer.timeout = Duration::from_millis(10);
+        }, |_ctx, _state| {
+            Some(EventTick.into())
+        })
+        .with_timer_ty::<TickTimer>()
+        .on_event::<EventTick>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.ticks += 1;
+        });
+
+    fsm.state::<StateCounting>()
+        .on_event::<EventIncrement>()
+        .internal_transition()
+        .action(|_ev, ctx, _state| {
+            ctx.increments += 1;
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_handle() -> FsmResult<()> {
+    let mut fsm = CounterMachine::new_with(CounterContext::default(), FsmEventQueueVec::new(), InspectNull::new(), TimersStd::new())?;
+    fsm.start()?;
+
+    let owner = FsmThread::spawn(fsm);
+    let handle = owner.handle();
+
+    // the machine isn't counting yet
+    let res = handle.send_and_wait(EventIncrement);
+    assert!(matches!(res, Err(FsmError::NoTransition(_))));
+
+    handle.send_and_wait(EventStart)?;
+
+    let workers: Vec<_> = (0..4).map(|_| {
+        let handle = handle.clone();
+        thread::spawn(move || {
+            for _ in 0..25 {
+                handle.send(EventIncrement).unwrap();
+            }
+        })
+    }).collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let increments = handle.query(|backend| backend.increments)?;
+    assert_eq!(100, increments);
+
+    let state = handle.query(|backend| backend.get_current_states()[0])?;
+    assert_eq!(FsmCurrentState::State(CounterMachineCurrentState::StateCounting), state);
+
+    // the timer is dispatched by the machine's thread
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(1, handle.query(|backend| backend.ticks)?);
+
+    owner.stop()?;
+    assert_eq!(Err(FsmError::Disconnected), handle.send(EventIncrement));
+    assert_eq!(Err(FsmError::Disconnected), handle.query(|backend| backend.ticks));
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+extern crate finny;
+
+use finny::{FsmCurrentState, FsmFactory, FsmResult, History, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct StateIdle;
+
+#[derive(Clone)]
+pub struct EventEnter;
+#[derive(Clone)]
+pub struct EventLeave;
+#[derive(Clone)]
+pub struct EventNext;
+#[derive(Clone)]
+pub struct EventInner;
+
+#[finny_fsm]
+fn build_shallow_fsm(mut fsm: FsmBuilder<ShallowMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventEnter>()
+        .transition_to::<Worker>();
+
+    fsm.sub_machine::<Worker>()
+        .with_history(History::Shallow)
+        .on_event::<EventLeave>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[finny_fsm]
+fn build_deep_fsm(mut fsm: FsmBuilder<DeepMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventEnter>()
+        .transition_to::<Worker>();
+
+    fsm.sub_machine::<Worker>()
+        .with_history(History::Deep)
+        .on_event::<EventLeave>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct WorkerStep {
+    entries: usize
+}
+
+#[finny_fsm]
+fn build_worker_fsm(mut fsm: FsmBuilder<Worker, ()>) -> BuiltFsm {
+    fsm.initial_state::<WorkerStep>();
+
+    fsm.state::<WorkerStep>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        })
+        .on_event::<EventNext>()
+        .transition_to::<Inner>();
+
+    fsm.sub_machine::<Inner>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct InnerA {
+    entries: usize
+}
+#[derive(Default)]
+pub struct InnerB {
+    entries: usize
+}
+
+#[finny_fsm]
+fn build_inner_fsm(mut fsm: FsmBuilder<Inner, ()>) -> BuiltFsm {
+    fsm.initial_state::<InnerA>();
+
+    fsm.state::<InnerA>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        })
+        .on_event::<EventInner>()
+        .transition_to::<InnerB>();
+
+    fsm.state::<InnerB>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        });
+
+    fsm.build()
+}
+
+fn inner_event() -> WorkerEvents {
+    let ev: InnerEvents = EventInner.into();
+    ev.into()
+}
+
+#[test]
+fn test_shallow_history() -> FsmResult<()> {
+    let mut fsm = ShallowMachine::new(())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventEnter)?;
+    let ev: WorkerEvents = EventNext.into();
+    fsm.dispatch(ev)?;
+    fsm.dispatch(inner_event())?;
+
+    fsm.dispatch(EventLeave)?;
+    assert_eq!(FsmCurrentState::State(ShallowMachineCurrentState::StateIdle), fsm.get_current_states()[0]);
+
+    fsm.dispatch(EventEnter)?;
+    assert_eq!(FsmCurrentState::State(ShallowMachineCurrentState::Worker), fsm.get_current_states()[0]);
+
+    // the worker's own state is restored, the nested machine starts again
+    let worker: &Worker = fsm.get_state();
+    assert_eq!(FsmCurrentState::State(WorkerCurrentState::Inner), worker.get_current_states()[0]);
+    let step: &WorkerStep = worker.get_state();
+    assert_eq!(1, step.entries);
+
+    let inner: &Inner = worker.get_state();
+    assert_eq!(FsmCurrentState::State(InnerCurrentState::InnerA), inner.get_current_states()[0]);
+    let inner_a: &InnerA = inner.get_state();
+    assert_eq!(2, inner_a.entries);
+
+    Ok(())
+}
+
+#[test]
+fn test_deep_history() -> FsmResult<()> {
+    let mut fsm = DeepMachine::new(())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventEnter)?;
+    let ev: WorkerEvents = EventNext.into();
+    fsm.dispatch(ev)?;
+    fsm.dispatch(inner_event())?;
+
+    fsm.dispatch(EventLeave)?;
+    fsm.dispatch(EventEnter)?;
+
+    // the whole path is restored and entered again
+    let worker: &Worker = fsm.get_state();
+    assert_eq!(FsmCurrentState::State(WorkerCurrentState::Inner), worker.get_current_states()[0]);
+
+    let inner: &Inner = worker.get_state();
+    assert_eq!(FsmCurrentState::State(InnerCurrentState::InnerB), inner.get_current_states()[0]);
+    let inner_a: &InnerA = inner.get_state();
+    assert_eq!(1, inner_a.entries);
+    let inner_b: &InnerB = inner.get_state();
+    assert_eq!(2, inner_b.entries);
+
+    // stopping the machine clears the history
+    fsm.stop()?;
+    fsm.start()?;
+    fsm.dispatch(EventEnter)?;
+    let worker: &Worker = fsm.get_state();
+    assert_eq!(FsmCurrentState::State(WorkerCurrentState::WorkerStep), worker.get_current_states()[0]);
+
+    Ok(())
+}
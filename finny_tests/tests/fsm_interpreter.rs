@@ -0,0 +1,239 @@
+extern crate finny_meta;
+
+use finny_meta::interpreter::{FsmInterpreter, FsmInterpreterError, FsmInterpreterResult, FsmRegistry, FsmDynEvent};
+
+const WORKFLOW: &str = r#"{
+    "schema_version": 1,
+    "fsm_id": "Workflow",
+    "context_id": "WorkflowContext",
+    "regions": {
+        "0": {
+            "region_id": 0,
+            "states": {
+                "Stopped": "Stopped",
+                "Draft": { "State": { "state_id": "Draft" } },
+                "Review": { "State": { "state_id": "Review", "on_entry": "notify_reviewers", "on_exit": "log_exit" } },
+                "Decision": { "Choice": "Decision" },
+                "Published": { "State": { "state_id": "Published", "is_final": true } }
+            },
+            "transitions": [
+                {
+                    "transition_id": "Start",
+                    "event": "Start",
+                    "transition": { "NormalTransition": { "from_state": "Stopped", "to_state": "Draft", "entry_point": null } }
+                },
+                {
+                    "transition_id": "Edit",
+                    "event": { "Event": "Edit" },
+                    "transition": { "InternalTransition": { "state_id": "Draft" } },
+                    "action": "count_edit"
+                },
+                {
+                    "transition_id": "Submit",
+                    "event": { "Event": "Submit" },
+                    "transition": { "NormalTransition": { "from_state": "Draft", "to_state": "Review", "entry_point": null } },
+                    "guard": "has_edits"
+                },
+                {
+                    "transition_id": "Vote",
+                    "event": { "Event": "Vote" },
+                    "transition": { "NormalTransition": { "from_state": "Review", "to_state": "Decision", "entry_point": null } },
+                    "action": "count_vote"
+                },
+                {
+                    "transition_id": "Approve",
+                    "event": "Completion",
+                    "transition": { "NormalTransition": { "from_state": "Decision", "to_state": "Published", "entry_point": null } },
+                    "guard": "enough_votes"
+                },
+                {
+                    "transition_id": "WaitForVotes",
+                    "event": "Completion",
+                    "transition": { "NormalTransition": { "from_state": "Decision", "to_state": "Review", "entry_point": null } }
+                }
+            ]
+        }
+    }
+}"#;
+
+#[derive(Default)]
+pub struct WorkflowContext {
+    edits: usize,
+    votes: u64,
+    log: Vec<String>
+}
+
+fn registry() -> FsmRegistry<WorkflowContext> {
+    let mut registry = FsmRegistry::new();
+    registry
+        .guard("has_edits", |_ev, ctx: &WorkflowContext| ctx.edits > 0)
+        .guard("enough_votes", |_ev, ctx: &WorkflowContext| ctx.votes >= 2)
+        .action("count_edit", |_ev, ctx| {
+            ctx.edits += 1;
+        })
+        .action("count_vote", |ev, ctx| {
+            ctx.votes += ev.data.as_u64().unwrap_or(1);
+        })
+        .action("notify_reviewers", |ev, ctx| {
+            ctx.log.push(format!("review after {}", ev.name));
+        })
+        .action("log_exit", |ev, ctx| {
+            ctx.log.push(format!("exit review on {}", ev.name));
+        });
+    registry
+}
+
+#[test]
+fn test_interpreter() -> FsmInterpreterResult<()> {
+    let mut fsm = FsmInterpreter::from_json(WORKFLOW, registry(), WorkflowContext::default())?;
+    assert_eq!(&["Stopped"], fsm.get_current_states());
+
+    fsm.start()?;
+    assert_eq!(&["Draft"], fsm.get_current_states());
+
+    // the guard blocks the submission
+    let res = fsm.dispatch("Submit");
+    assert!(matches!(res, Err(FsmInterpreterError::NoTransition { .. })));
+
+    fsm.dispatch("Edit")?;
+    fsm.dispatch("Submit")?;
+    assert_eq!(&["Review"], fsm.get_current_states());
+
+    // not enough votes, the choice returns back into the review
+    fsm.dispatch("Vote")?;
+    assert_eq!(&["Review"], fsm.get_current_states());
+    assert_eq!(false, fsm.is_completed());
+
+    fsm.dispatch(FsmDynEvent::with_data("Vote", 3.into()))?;
+    assert_eq!(&["Published"], fsm.get_current_states());
+    assert_eq!(true, fsm.is_completed());
+    assert_eq!(4, fsm.votes);
+
+    assert_eq!(vec![
+        "review after Submit",
+        "exit review on Vote",
+        "review after Fsm::Completion",
+        "exit review on Vote"
+    ], fsm.log);
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct QueueContext {
+    pings: usize
+}
+
+#[test]
+fn test_interpreter_regions_and_queue() -> FsmInterpreterResult<()> {
+    let json = r#"{
+        "schema_version": 1,
+        "fsm_id": "Pinger",
+        "context_id": "QueueContext",
+        "regions": {
+            "0": {
+                "region_id": 0,
+                "states": { "Stopped": "Stopped", "A": { "State": { "state_id": "A" } }, "B": { "State": { "state_id": "B" } } },
+                "transitions": [
+                    { "transition_id": "T1", "event": "Start", "transition": { "NormalTransition": { "from_state": "Stopped", "to_state": "A", "entry_point": null } } },
+                    { "transition_id": "T2", "event": { "Event": "Go" }, "transition": { "NormalTransition": { "from_state": "A", "to_state": "B", "entry_point": null } }, "action": "send_ping" }
+                ]
+            },
+            "1": {
+                "region_id": 1,
+                "states": { "Stopped": "Stopped", "Listener": { "State": { "state_id": "Listener" } } },
+                "transitions": [
+                    { "transition_id": "T3", "event": "Start", "transition": { "NormalTransition": { "from_state": "Stopped", "to_state": "Listener", "entry_point": null } } },
+                    { "transition_id": "T4", "event": { "Event": "Ping" }, "transition": { "SelfTransition": { "state_id": "Listener" } }, "action": "count_ping" }
+                ]
+            }
+        }
+    }"#;
+
+    let mut registry = FsmRegistry::new();
+    registry
+        .action("send_ping", |_ev, ctx: &mut finny_meta::interpreter::FsmDynContext<QueueContext>| {
+            ctx.enqueue("Ping");
+        })
+        .action("count_ping", |_ev, ctx| {
+            ctx.pings += 1;
+        });
+
+    let mut fsm = FsmInterpreter::from_json(json, registry, QueueContext::default())?;
+    fsm.start()?;
+    assert_eq!(&["A", "Listener"], fsm.get_current_states());
+
+    fsm.dispatch("Go")?;
+    assert_eq!(&["B", "Listener"], fsm.get_current_states());
+    assert_eq!(1, fsm.pings);
+
+    fsm.stop()?;
+    assert_eq!(&["Stopped", "Stopped"], fsm.get_current_states());
+
+    Ok(())
+}
+
+#[test]
+fn test_interpreter_validation() {
+    let res = FsmInterpreter::from_json(WORKFLOW, FsmRegistry::new(), WorkflowContext::default());
+    assert!(matches!(res, Err(FsmInterpreterError::NotRegistered(_))));
+
+    let json = WORKFLOW.replace(r#""to_state": "Published""#, r#""to_state": "Archived""#);
+    let res = FsmInterpreter::from_json(&json, registry(), WorkflowContext::default());
+    assert!(matches!(res, Err(FsmInterpreterError::InvalidDescription(_))));
+
+    let json = WORKFLOW.replace(r#""schema_version": 1"#, r#""schema_version": 99"#);
+    let res = FsmInterpreter::from_json(&json, registry(), WorkflowContext::default());
+    assert!(matches!(res, Err(FsmInterpreterError::SchemaVersion(99))));
+}
+
+#[derive(Default)]
+pub struct UnhandledContext {
+    unhandled: Vec<String>
+}
+
+#[test]
+fn test_interpreter_unhandled() -> FsmInterpreterResult<()> {
+    let json = r#"{
+        "schema_version": 1,
+        "fsm_id": "Strict",
+        "context_id": "UnhandledContext",
+        "regions": {
+            "0": {
+                "region_id": 0,
+                "states": {
+                    "Stopped": "Stopped",
+                    "A": { "State": { "state_id": "A", "unhandled": { "handler": "A::on_unhandled" } } },
+                    "B": { "State": { "state_id": "B" } }
+                },
+                "transitions": [
+                    { "transition_id": "T1", "event": "Start", "transition": { "NormalTransition": { "from_state": "Stopped", "to_state": "A", "entry_point": null } } },
+                    { "transition_id": "T2", "event": { "Event": "Go" }, "transition": { "NormalTransition": { "from_state": "A", "to_state": "B", "entry_point": null } } }
+                ]
+            }
+        },
+        "unhandled": { "ignored_events": ["Noise"] }
+    }"#;
+
+    let mut registry = FsmRegistry::new();
+    registry.action("A::on_unhandled", |ev, ctx: &mut finny_meta::interpreter::FsmDynContext<UnhandledContext>| {
+        ctx.unhandled.push(ev.name.clone());
+    });
+
+    let mut fsm = FsmInterpreter::from_json(json, registry, UnhandledContext::default())?;
+    fsm.start()?;
+    fsm.dispatch("Other")?;
+    assert_eq!(vec!["Other"], fsm.unhandled);
+
+    // only the machine-wide policy applies in this state
+    fsm.dispatch("Go")?;
+    fsm.dispatch("Noise")?;
+    let res = fsm.dispatch("Other");
+    assert!(matches!(res, Err(FsmInterpreterError::NoTransition { .. })));
+    assert_eq!(vec!["Other"], fsm.unhandled);
+
+    let res = FsmInterpreter::from_json(json, FsmRegistry::new(), UnhandledContext::default());
+    assert!(matches!(res, Err(FsmInterpreterError::NotRegistered(_))));
+
+    Ok(())
+}
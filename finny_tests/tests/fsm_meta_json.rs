@@ -0,0 +1,189 @@
+#![cfg(feature = "meta_json")]
+
+extern crate finny;
+extern crate finny_meta;
+
+use finny::{decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+use finny_meta::{FINNY_META_SCHEMA_VERSION, FinnyEvent, FinnyFsm, FinnyStateKind, FinnyTransition, FinnyTransitionKind, interpreter::{FsmInterpreter, FsmRegistry}};
+
+#[derive(Default)]
+pub struct DoorContext {
+    entries: usize
+}
+
+#[derive(Default)]
+pub struct StateClosed;
+#[derive(Default)]
+pub struct StateOpen;
+#[derive(Default)]
+pub struct StateLocked;
+
+#[derive(Clone)]
+pub struct EventOpen;
+#[derive(Clone)]
+pub struct EventClose;
+#[derive(Clone)]
+pub struct EventLock;
+#[derive(Clone)]
+pub struct EventKnock;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<DoorMachine, DoorContext>) -> BuiltFsm {
+    fsm.meta_json();
+    fsm.initial_state::<StateClosed>();
+
+    fsm.state::<StateClosed>()
+        .on_entry(|_state, ctx| {
+            ctx.entries += 1;
+        })
+        .ignore_event::<EventKnock>()
+        .on_event::<EventOpen>()
+        .transition_to::<StateOpen>()
+        .guard(|_ev, _ctx, _states| true);
+
+    fsm.state::<StateClosed>()
+        .on_event::<EventLock>()
+        .transition_to::<StateLocked>();
+
+    fsm.state::<StateOpen>()
+        .defer_event::<EventLock>()
+        .on_event::<EventClose>()
+        .transition_to::<StateClosed>()
+        .action(|_ev, _ctx, _from, _to| { });
+
+    fsm.state::<StateLocked>();
+
+    fsm.build()
+}
+
+fn read_meta_file(file_name: &str) -> FinnyFsm {
+    let dir = option_env!("FINNY_META_DIR").unwrap_or(env!("OUT_DIR"));
+    let json = std::fs::read_to_string(std::path::Path::new(dir).join(file_name)).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+fn read_meta() -> FinnyFsm {
+    read_meta_file(DoorMachine::META_JSON_FILE)
+}
+
+// a machine with the same name in another module
+mod garage {
+    use finny::{decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+    #[derive(Default)]
+    pub struct StateDown;
+    #[derive(Default)]
+    pub struct StateUp;
+
+    #[derive(Clone)]
+    pub struct EventRaise;
+
+    #[finny_fsm]
+    fn build_fsm(mut fsm: FsmBuilder<DoorMachine, ()>) -> BuiltFsm {
+        fsm.meta_json();
+        fsm.initial_state::<StateDown>();
+
+        fsm.state::<StateDown>()
+            .on_event::<EventRaise>()
+            .transition_to::<StateUp>();
+
+        fsm.state::<StateUp>();
+
+        fsm.build()
+    }
+}
+
+#[test]
+fn test_meta_json_same_names() {
+    assert!(DoorMachine::META_JSON_FILE.starts_with("door_machine_"));
+    assert_ne!(DoorMachine::META_JSON_FILE, garage::DoorMachine::META_JSON_FILE);
+
+    let garage = read_meta_file(garage::DoorMachine::META_JSON_FILE);
+    assert!(garage.regions[&0].states.contains_key("StateUp"));
+    assert!(read_meta().regions[&0].states.contains_key("StateOpen"));
+}
+
+#[test]
+fn test_meta_json() {
+    let meta = read_meta();
+    assert_eq!(FINNY_META_SCHEMA_VERSION, meta.schema_version);
+    assert_eq!("DoorMachine", meta.fsm_id);
+
+    let region = &meta.regions[&0];
+    match region.states.get("StateOpen") {
+        Some(FinnyStateKind::State(s)) => assert_eq!(vec!["EventLock"], s.deferred_events),
+        _ => panic!("Unexpected state")
+    }
+    match region.states.get("StateClosed") {
+        Some(FinnyStateKind::State(s)) => {
+            assert_eq!(Some("StateClosed::on_entry"), s.on_entry.as_deref());
+            assert_eq!(None, s.on_exit);
+            assert_eq!(vec!["EventKnock"], s.unhandled.ignored_events);
+        },
+        _ => panic!("Unexpected state")
+    }
+
+    let open = find_transition(&meta, "EventOpen");
+    assert_eq!(Some(format!("{}::guard", open.transition_id)), open.guard);
+    assert_eq!(None, open.action);
+    let close = find_transition(&meta, "EventClose");
+    assert_eq!(None, close.guard);
+    assert_eq!(Some(format!("{}::action", close.transition_id)), close.action);
+
+    let transitions: Vec<_> = region.transitions.iter().map(|t| match (&t.event, &t.transition) {
+        (FinnyEvent::Start, FinnyTransitionKind::NormalTransition(t)) => format!("Start: {} -> {}", t.from_state, t.to_state),
+        (FinnyEvent::Event(ev), FinnyTransitionKind::NormalTransition(t)) => format!("{}: {} -> {}", ev, t.from_state, t.to_state),
+        _ => panic!("Unexpected transition")
+    }).collect();
+    assert_eq!(4, transitions.len());
+    assert_eq!("Start: Stopped -> StateClosed", transitions[0]);
+    assert!(transitions.contains(&"EventOpen: StateClosed -> StateOpen".to_string()));
+    assert!(transitions.contains(&"EventClose: StateOpen -> StateClosed".to_string()));
+}
+
+fn find_transition<'a>(meta: &'a FinnyFsm, event: &str) -> &'a FinnyTransition {
+    meta.regions[&0].transitions.iter().find(|t| matches!(t.event, FinnyEvent::Event(ref ev) if ev == event)).unwrap()
+}
+
+#[derive(Default)]
+pub struct InterpretedContext {
+    entries: usize,
+    closes: usize
+}
+
+#[test]
+fn test_meta_json_interpreted() -> Result<(), Box<dyn std::error::Error>> {
+    let meta = read_meta();
+    let open = find_transition(&meta, "EventOpen").transition_id.clone();
+    let close = find_transition(&meta, "EventClose").transition_id.clone();
+
+    let mut registry = FsmRegistry::new();
+    registry
+        .guard(&format!("{}::guard", open), |_ev, _ctx: &InterpretedContext| true)
+        .action(&format!("{}::action", close), |_ev, ctx| {
+            ctx.closes += 1;
+        })
+        .action("StateClosed::on_entry", |_ev, ctx| {
+            ctx.entries += 1;
+        });
+
+    let mut fsm = FsmInterpreter::new(meta, registry, InterpretedContext::default())?;
+    fsm.start()?;
+    assert_eq!(1, fsm.entries);
+
+    // ignored by the state
+    fsm.dispatch("EventKnock")?;
+    fsm.dispatch("EventOpen")?;
+    assert_eq!(&["StateOpen"], fsm.get_current_states());
+
+    // deferred until the door is closed
+    fsm.dispatch("EventLock")?;
+    assert_eq!(1, fsm.get_deferred_events().count());
+    fsm.dispatch("EventClose")?;
+    assert_eq!(&["StateLocked"], fsm.get_current_states());
+    assert_eq!(0, fsm.get_deferred_events().count());
+    assert_eq!(1, fsm.closes);
+    assert_eq!(2, fsm.entries);
+
+    Ok(())
+}
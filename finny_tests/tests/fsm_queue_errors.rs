@@ -0,0 +1,148 @@
+extern crate finny;
+
+use std::sync::{Arc, Mutex};
+
+use finny::{FsmBackend, FsmBackendImpl, FsmCurrentState, FsmError, FsmEvent, FsmEventQueue, FsmEventQueueSender, FsmEventQueueVec, FsmFactory, FsmQueueErrorPolicy, FsmQueueErrorReport, FsmResult, FsmTimersNull, Inspect, InspectEvent, InspectFsmEvent, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+/// Records the reported errors.
+#[derive(Clone, Default)]
+pub struct InspectErrors {
+    errors: Arc<Mutex<Vec<String>>>
+}
+
+impl Inspect for InspectErrors {
+    fn new_event<F: FsmBackend>(&self, _event: &FsmEvent<<F as FsmBackend>::Events, <F as FsmBackend>::Timers>, _fsm: &FsmBackendImpl<F>) -> Self {
+        self.clone()
+    }
+
+    fn for_transition<T>(&self) -> Self {
+        self.clone()
+    }
+
+    fn for_sub_machine<FSub: FsmBackend>(&self) -> Self {
+        self.clone()
+    }
+
+    fn for_timer<F>(&self, _timer_id: <F as FsmBackend>::Timers) -> Self where F: FsmBackend {
+        self.clone()
+    }
+
+    fn on_guard<T>(&self, _guard_result: bool) { }
+
+    fn on_state_enter<S>(&self) { }
+
+    fn on_state_exit<S>(&self) { }
+
+    fn on_action<S>(&self) { }
+
+    fn on_timer_trigger<T>(&self) { }
+
+    fn event_done<F: FsmBackend>(self, _fsm: &FsmBackendImpl<F>) { }
+
+    fn on_error<E>(&self, msg: &str, error: &E) where E: core::fmt::Debug {
+        self.errors.lock().unwrap().push(format!("{}: {:?}", msg, error));
+    }
+
+    fn info(&self, _msg: &str) { }
+}
+
+impl InspectEvent for InspectErrors {
+    fn on_event<S: std::any::Any + std::fmt::Debug + Clone>(&self, _event: &InspectFsmEvent<S>) { }
+}
+
+#[derive(Default)]
+pub struct StateA;
+#[derive(Default)]
+pub struct StateB;
+
+#[derive(Clone)]
+pub struct EventTrigger;
+#[derive(Clone)]
+pub struct EventUnhandled;
+#[derive(Clone)]
+pub struct EventNext;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateA>();
+
+    // enqueue two events that can't be handled in state A, followed by a valid one
+    fsm.state::<StateA>()
+        .on_event::<EventTrigger>()
+        .internal_transition()
+        .action(|_ev, ctx, _| {
+            ctx.queue.enqueue(EventUnhandled).unwrap();
+            ctx.queue.enqueue(EventUnhandled).unwrap();
+            ctx.queue.enqueue(EventNext).unwrap();
+        });
+
+    fsm.state::<StateA>()
+        .on_event::<EventNext>()
+        .transition_to::<StateB>();
+
+    fsm.state::<StateB>()
+        .on_event::<EventUnhandled>()
+        .internal_transition();
+
+    fsm.build()
+}
+
+#[test]
+fn test_queue_stop_on_first_error() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(())?;
+    fsm.queue_error_policy = FsmQueueErrorPolicy::StopOnFirstError;
+
+    fsm.start()?;
+    match fsm.dispatch(EventTrigger) {
+        Err(FsmError::QueueErrors(FsmQueueErrorReport { errors })) => {
+            assert_eq!(1, errors.len());
+            assert!(matches!(&errors[0], FsmError::NoTransition(ctx) if ctx.event == "EventUnhandled"));
+        },
+        r => panic!("Expected a report of the first queue error, got {:?}", r)
+    }
+
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateA), fsm.get_current_states()[0]);
+    assert_eq!(2, fsm.queue.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_queue_collect_errors() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(())?;
+    fsm.queue_error_policy = FsmQueueErrorPolicy::CollectErrors;
+
+    fsm.start()?;
+    match fsm.dispatch(EventTrigger) {
+        Err(FsmError::QueueErrors(FsmQueueErrorReport { errors })) => {
+            assert_eq!(2, errors.len());
+            assert!(errors.iter().all(|e| matches!(e, FsmError::NoTransition(ctx) if ctx.event == "EventUnhandled")));
+        },
+        r => panic!("Expected a report of the queue errors, got {:?}", r)
+    }
+
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateB), fsm.get_current_states()[0]);
+    assert_eq!(0, fsm.queue.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_queue_inspect_errors() -> FsmResult<()> {
+    let inspect = InspectErrors::default();
+    let mut fsm = StateMachine::new_with((), FsmEventQueueVec::new(), inspect.clone(), FsmTimersNull)?;
+    assert_eq!(FsmQueueErrorPolicy::Inspect, fsm.queue_error_policy);
+
+    fsm.start()?;
+    fsm.dispatch(EventTrigger)?;
+
+    // both of the unhandled events were reported, the dispatch itself succeeded
+    let errors = inspect.errors.lock().unwrap();
+    assert_eq!(2, errors.len());
+    assert!(errors.iter().all(|e| e.starts_with("Failed to dispatch a queued event: NoTransition") && e.contains("EventUnhandled")), "{:?}", errors);
+
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateB), fsm.get_current_states()[0]);
+    assert_eq!(0, fsm.queue.len());
+
+    Ok(())
+}
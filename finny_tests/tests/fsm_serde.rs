@@ -0,0 +1,147 @@
+#![cfg(feature = "serde")]
+
+extern crate finny;
+
+use std::{thread::sleep, time::Duration};
+
+use finny::{FsmCurrentState, FsmError, FsmFactory, FsmResult, FsmSnapshot, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct WorkflowContext {
+    steps: usize
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct StateIdle;
+#[derive(Default, Serialize, Deserialize)]
+pub struct StateWaiting {
+    timeouts: usize
+}
+
+#[derive(Clone)]
+pub struct EventStart;
+#[derive(Clone)]
+pub struct EventWait;
+#[derive(Clone)]
+pub struct EventTimeout;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<Workflow, WorkflowContext>) -> BuiltFsm {
+    fsm.serde_snapshots();
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventStart>()
+        .transition_to::<Job>()
+        .action(|_ev, ctx, _, _| {
+            ctx.steps += 1;
+        });
+
+    fsm.sub_machine::<Job>()
+        .on_event::<EventWait>()
+        .transition_to::<StateWaiting>();
+
+    fsm.state::<StateWaiting>()
+        .on_entry_start_timer(|_ctx, timer| {
+            timer.timeout = Duration::from_millis(50);
+        }, |_ctx, _state| {
+            Some(EventTimeout.into())
+        })
+        .with_timer_ty::<WaitingTimer>()
+        .on_event::<EventTimeout>()
+        .internal_transition()
+        .action(|_ev, _ctx, state| {
+            state.timeouts += 1;
+        });
+
+    fsm.build()
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct JobA;
+#[derive(Default, Serialize, Deserialize)]
+pub struct JobB {
+    entries: usize
+}
+
+#[derive(Clone)]
+pub struct EventNext;
+
+#[finny_fsm]
+fn build_job_fsm(mut fsm: FsmBuilder<Job, ()>) -> BuiltFsm {
+    fsm.serde_snapshots();
+    fsm.initial_state::<JobA>();
+
+    fsm.state::<JobA>()
+        .on_event::<EventNext>()
+        .transition_to::<JobB>();
+
+    fsm.state::<JobB>()
+        .on_entry(|state, _ctx| {
+            state.entries += 1;
+        });
+
+    fsm.build()
+}
+
+#[test]
+fn test_snapshot_restore() -> FsmResult<()> {
+    let mut fsm = Workflow::new(WorkflowContext::default())?;
+    fsm.start()?;
+    fsm.dispatch(EventStart)?;
+    let ev: JobEvents = EventNext.into();
+    fsm.dispatch(ev)?;
+
+    let json = serde_json::to_string(&fsm.snapshot()).unwrap();
+    drop(fsm);
+
+    let snapshot: FsmSnapshot<_> = serde_json::from_str(&json).unwrap();
+    let restored = Workflow::restore(snapshot)?;
+    assert_eq!(1, restored.steps);
+    assert_eq!(FsmCurrentState::State(WorkflowCurrentState::Job), restored.get_current_states()[0]);
+
+    let job: &Job = restored.get_state();
+    assert_eq!(FsmCurrentState::State(JobCurrentState::JobB), job.get_current_states()[0]);
+    let job_b: &JobB = job.get_state();
+    assert_eq!(1, job_b.entries);
+
+    Ok(())
+}
+
+#[test]
+fn test_restore_timers() -> FsmResult<()> {
+    let mut fsm = Workflow::new(WorkflowContext::default())?;
+    fsm.start()?;
+    fsm.dispatch(EventStart)?;
+    fsm.dispatch(EventWait)?;
+
+    let json = serde_json::to_string(&fsm.snapshot()).unwrap();
+    drop(fsm);
+
+    // the running timer is created again by the restored machine
+    let snapshot: FsmSnapshot<_> = serde_json::from_str(&json).unwrap();
+    let mut restored = Workflow::restore(snapshot)?;
+    sleep(Duration::from_millis(100));
+    restored.dispatch_timer_events()?;
+
+    let state: &StateWaiting = restored.get_state();
+    assert_eq!(1, state.timeouts);
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_version() -> FsmResult<()> {
+    let mut fsm = Workflow::new(WorkflowContext::default())?;
+    fsm.start()?;
+
+    let mut json: serde_json::Value = serde_json::to_value(&fsm.snapshot()).unwrap();
+    json["version"] = 999.into();
+
+    let snapshot: FsmSnapshot<_> = serde_json::from_value(json).unwrap();
+    let restored = Workflow::restore(snapshot);
+    assert_eq!(Some(FsmError::SnapshotVersion(999)), restored.err());
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+extern crate finny;
+
+use finny::{FSM_STATE_PATH_MAX_STATES, FsmFactory, FsmResult, FsmStatePathItem, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct StateParked;
+#[derive(Default)]
+pub struct StateLightsOff;
+#[derive(Default)]
+pub struct StateLightsOn;
+
+#[derive(Clone)]
+pub struct EventDrive;
+#[derive(Clone)]
+pub struct EventPark;
+#[derive(Clone)]
+pub struct EventLights;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<CarMachine, ()>) -> BuiltFsm {
+    fsm.initial_states::<(StateParked, StateLightsOff)>();
+
+    fsm.state::<StateParked>()
+        .on_event::<EventDrive>()
+        .transition_to::<GearboxMachine>();
+
+    fsm.sub_machine::<GearboxMachine>()
+        .on_event::<EventPark>()
+        .transition_to::<StateParked>();
+
+    fsm.state::<StateLightsOff>()
+        .on_event::<EventLights>()
+        .transition_to::<StateLightsOn>();
+
+    fsm.state::<StateLightsOn>()
+        .on_event::<EventLights>()
+        .transition_to::<StateLightsOff>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct StateFirstGear;
+#[derive(Default)]
+pub struct StateSecondGear;
+
+#[derive(Clone)]
+pub struct EventShiftUp;
+
+#[finny_fsm]
+fn build_gearbox_fsm(mut fsm: FsmBuilder<GearboxMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateFirstGear>();
+
+    fsm.state::<StateFirstGear>()
+        .on_event::<EventShiftUp>()
+        .transition_to::<StateSecondGear>();
+
+    fsm.state::<StateSecondGear>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_state_path() -> FsmResult<()> {
+    let mut fsm = CarMachine::new(())?;
+    assert!(fsm.current_state_path().states.is_empty());
+    assert_eq!("Fsm::Stopped", format!("{}", fsm.current_state_path()));
+
+    fsm.start()?;
+    assert_eq!("CarMachine::StateParked, CarMachine::StateLightsOff", format!("{}", fsm.current_state_path()));
+
+    fsm.dispatch(EventLights)?;
+    fsm.dispatch(EventDrive)?;
+    fsm.dispatch(GearboxMachineEvents::EventShiftUp(EventShiftUp))?;
+
+    let path = fsm.current_state_path();
+    assert_eq!(&[
+        FsmStatePathItem { fsm: "CarMachine", region: 0, state: "GearboxMachine", depth: 0 },
+        FsmStatePathItem { fsm: "GearboxMachine", region: 0, state: "StateSecondGear", depth: 1 },
+        FsmStatePathItem { fsm: "CarMachine", region: 1, state: "StateLightsOn", depth: 0 }
+    ], path.states.as_slice());
+    assert_eq!(vec!["StateSecondGear", "StateLightsOn"], path.leaves().map(|s| s.state).collect::<Vec<_>>());
+    assert!(path.contains("GearboxMachine", "StateSecondGear"));
+    assert_eq!("CarMachine::GearboxMachine -> GearboxMachine::StateSecondGear, CarMachine::StateLightsOn", format!("{}", path));
+    assert!(!path.truncated);
+
+    // the submachine isn't part of the path anymore
+    fsm.dispatch(EventPark)?;
+    assert_eq!("CarMachine::StateParked, CarMachine::StateLightsOn", format!("{}", fsm.current_state_path()));
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct Sensor1;
+#[derive(Default)]
+pub struct Sensor2;
+#[derive(Default)]
+pub struct Sensor3;
+#[derive(Default)]
+pub struct Sensor4;
+#[derive(Default)]
+pub struct Sensor5;
+#[derive(Default)]
+pub struct Sensor6;
+#[derive(Default)]
+pub struct Sensor7;
+#[derive(Default)]
+pub struct Sensor8;
+#[derive(Default)]
+pub struct Sensor9;
+#[derive(Default)]
+pub struct Sensor10;
+#[derive(Default)]
+pub struct Sensor11;
+#[derive(Default)]
+pub struct Sensor12;
+#[derive(Default)]
+pub struct Sensor13;
+#[derive(Default)]
+pub struct Sensor14;
+#[derive(Default)]
+pub struct Sensor15;
+#[derive(Default)]
+pub struct Sensor16;
+#[derive(Default)]
+pub struct Sensor17;
+
+// more regions than fit into the path
+#[finny_fsm]
+fn build_sensors_fsm(mut fsm: FsmBuilder<SensorsMachine, ()>) -> BuiltFsm {
+    fsm.initial_states::<(Sensor1, Sensor2, Sensor3, Sensor4, Sensor5, Sensor6, Sensor7, Sensor8, Sensor9, Sensor10, Sensor11, Sensor12, Sensor13, Sensor14, Sensor15, Sensor16, Sensor17)>();
+
+    fsm.state::<Sensor1>();
+    fsm.state::<Sensor2>();
+    fsm.state::<Sensor3>();
+    fsm.state::<Sensor4>();
+    fsm.state::<Sensor5>();
+    fsm.state::<Sensor6>();
+    fsm.state::<Sensor7>();
+    fsm.state::<Sensor8>();
+    fsm.state::<Sensor9>();
+    fsm.state::<Sensor10>();
+    fsm.state::<Sensor11>();
+    fsm.state::<Sensor12>();
+    fsm.state::<Sensor13>();
+    fsm.state::<Sensor14>();
+    fsm.state::<Sensor15>();
+    fsm.state::<Sensor16>();
+    fsm.state::<Sensor17>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_state_path_truncated() -> FsmResult<()> {
+    let mut fsm = SensorsMachine::new(())?;
+    fsm.start()?;
+
+    let path = fsm.current_state_path();
+    assert_eq!(FSM_STATE_PATH_MAX_STATES, path.states.len());
+    assert!(path.truncated);
+    assert!(!path.contains("SensorsMachine", "Sensor17"));
+    assert!(format!("{}", path).ends_with("SensorsMachine::Sensor16, …"));
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+extern crate finny;
+
+use std::{thread::sleep, time::Duration};
+
+use finny::{FsmCurrentState, FsmEventQueue, FsmEventQueueSender, FsmEventQueueVec, FsmFactory, FsmResult, FsmTimers, decl::{BuiltFsm, FsmBuilder}, finny_fsm, inspect::null::InspectNull, timers::std::TimersStd};
+
+#[derive(Default)]
+pub struct StopContext {
+    exit_a: usize,
+    exit_sub: usize,
+    exit_x: usize
+}
+
+#[derive(Default)]
+pub struct StateA;
+#[derive(Default)]
+pub struct StateX;
+#[derive(Clone, Debug)]
+pub struct Event;
+#[derive(Clone, Debug)]
+pub struct EventTimer;
+#[derive(Clone, Debug)]
+pub struct EventLater;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StopMachine, StopContext>) -> BuiltFsm {
+    fsm.initial_states::<(StateA, StateX)>();
+
+    fsm.state::<StateA>()
+        .on_exit(|_, ctx| {
+            ctx.exit_a += 1;
+        })
+        .on_event::<Event>()
+        .transition_to::<StopSubMachine>();
+
+    fsm.state::<StateA>()
+        .on_entry_start_timer(|_ctx, settings| {
+            settings.timeout = Duration::from_millis(50);
+            settings.renew = true;
+        }, |_ctx, _state| {
+            Some( EventTimer.into() )
+        })
+        .with_timer_ty::<StopTimer>();
+
+    fsm.state::<StateA>()
+        .on_event::<EventTimer>()
+        .internal_transition();
+
+    fsm.sub_machine::<StopSubMachine>()
+        .on_exit(|_, ctx| {
+            ctx.exit_sub += 1;
+        });
+
+    fsm.state::<StateX>()
+        .defer_event::<EventLater>()
+        .on_exit(|_, ctx| {
+            ctx.exit_x += 1;
+        });
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct SubContext {
+    exit_inner: usize
+}
+
+#[derive(Default)]
+pub struct SubStateInner;
+
+#[finny_fsm]
+fn build_sub_fsm(mut fsm: FsmBuilder<StopSubMachine, SubContext>) -> BuiltFsm {
+    fsm.initial_state::<SubStateInner>();
+    fsm.state::<SubStateInner>()
+        .on_exit(|_, ctx| {
+            ctx.exit_inner += 1;
+        });
+    fsm.build()
+}
+
+#[test]
+fn test_stop() -> FsmResult<()> {
+    let mut fsm = StopMachine::new_with_deferred(StopContext::default(), FsmEventQueueVec::new(), FsmEventQueueVec::new(), InspectNull::new(), TimersStd::new())?;
+
+    // stopping a stopped machine is a no-op
+    fsm.stop()?;
+    assert_eq!(0, fsm.exit_a);
+
+    fsm.start()?;
+    fsm.stop()?;
+
+    assert_eq!([FsmCurrentState::Stopped, FsmCurrentState::Stopped], fsm.get_current_states());
+    assert_eq!(1, fsm.exit_a);
+    assert_eq!(1, fsm.exit_x);
+
+    // the timer of the state was cancelled
+    sleep(Duration::from_millis(100));
+    assert_eq!(None, fsm.timers.get_triggered_timer());
+
+    // restart and stop while the submachine is active
+    fsm.start()?;
+    fsm.dispatch(Event)?;
+    assert_eq!(FsmCurrentState::State(StopMachineCurrentState::StopSubMachine), fsm.get_current_states()[0]);
+
+    fsm.stop()?;
+
+    assert_eq!([FsmCurrentState::Stopped, FsmCurrentState::Stopped], fsm.get_current_states());
+    assert_eq!(2, fsm.exit_a);
+    assert_eq!(1, fsm.exit_sub);
+    assert_eq!(2, fsm.exit_x);
+
+    let sub: &StopSubMachine = fsm.get_state();
+    assert_eq!([FsmCurrentState::Stopped], sub.get_current_states());
+    assert_eq!(1, sub.exit_inner);
+
+    Ok(())
+}
+
+#[test]
+fn test_stop_drops_events() -> FsmResult<()> {
+    let mut fsm = StopMachine::new_with_deferred(StopContext::default(), FsmEventQueueVec::new(), FsmEventQueueVec::new(), InspectNull::new(), TimersStd::new())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventLater)?;
+    fsm.queue.enqueue(Event)?;
+    assert_eq!(1, fsm.deferred.len());
+    assert_eq!(1, fsm.queue.len());
+
+    fsm.stop()?;
+    assert_eq!(0, fsm.deferred.len());
+    assert_eq!(0, fsm.queue.len());
+
+    // the dropped events aren't dispatched after the restart
+    fsm.start()?;
+    fsm.dispatch(EventTimer)?;
+    assert_eq!([FsmCurrentState::State(StopMachineCurrentState::StateA), FsmCurrentState::State(StopMachineCurrentState::StateX)], fsm.get_current_states());
+    assert_eq!(0, fsm.deferred.len());
+
+    Ok(())
+}
@@ -155,7 +155,7 @@ fn test_sub() -> FsmResult<()> {
     assert_eq!(2, state.value);
 
     let res = fsm.dispatch(EventSub { n: 0 });
-    assert_eq!(Err(FsmError::NoTransition), res);
+    assert!(matches!(res, Err(FsmError::NoTransition(_))));
     assert_eq!(1, fsm.sub_enter);
     assert_eq!(0, fsm.sub_exit);
     assert_eq!(0, fsm.sub_action);
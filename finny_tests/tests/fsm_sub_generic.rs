@@ -135,7 +135,7 @@ fn test_sub_generics() -> FsmResult<()> {
     assert_eq!(2, state.value);
 
     let res = fsm.dispatch(EventSub { n: 0 });
-    assert_eq!(Err(FsmError::NoTransition), res);
+    assert!(matches!(res, Err(FsmError::NoTransition(_))));
 
     fsm.dispatch(EventSub { n: 1 })?;
 
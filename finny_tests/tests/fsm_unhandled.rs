@@ -0,0 +1,215 @@
+extern crate finny;
+
+use finny::{FsmCurrentState, FsmError, FsmFactory, FsmResult, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct UnhandledContext {
+    unhandled: Vec<String>
+}
+
+#[derive(Default)]
+pub struct StateA;
+#[derive(Default)]
+pub struct StateB;
+#[derive(Default)]
+pub struct StateC;
+
+#[derive(Clone)]
+pub struct EventGo;
+#[derive(Clone)]
+pub struct EventBack;
+#[derive(Clone)]
+pub struct EventNoise;
+#[derive(Clone)]
+pub struct EventGlobal;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<StateMachine, UnhandledContext>) -> BuiltFsm {
+    fsm.initial_state::<StateA>();
+    fsm.ignore_event::<EventGlobal>();
+
+    fsm.state::<StateA>()
+        .ignore_event::<EventNoise>()
+        .on_event::<EventGo>()
+        .transition_to::<StateB>();
+
+    fsm.state::<StateB>()
+        .on_unhandled(|ev, ctx| {
+            ctx.unhandled.push(ev.as_ref().to_string());
+        })
+        .on_event::<EventBack>()
+        .transition_to::<StateC>();
+
+    fsm.state::<StateC>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_unhandled() -> FsmResult<()> {
+    let mut fsm = StateMachine::new(UnhandledContext::default())?;
+    fsm.start()?;
+
+    // ignored by the state
+    fsm.dispatch(EventNoise)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateA), fsm.get_current_states()[0]);
+
+    // ignored by the machine
+    fsm.dispatch(EventGlobal)?;
+
+    fsm.dispatch(EventGo)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateB), fsm.get_current_states()[0]);
+
+    // the state's handler takes precedence over the machine's policy
+    fsm.dispatch(EventNoise)?;
+    fsm.dispatch(EventGlobal)?;
+    fsm.dispatch(EventGo)?;
+    assert_eq!(vec!["EventNoise", "EventGlobal", "EventGo"], fsm.unhandled);
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateB), fsm.get_current_states()[0]);
+
+    fsm.dispatch(EventBack)?;
+    assert_eq!(FsmCurrentState::State(StateMachineCurrentState::StateC), fsm.get_current_states()[0]);
+
+    fsm.dispatch(EventGlobal)?;
+    let res = fsm.dispatch(EventNoise);
+    assert!(matches!(res, Err(FsmError::NoTransition(_))));
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct StateX;
+#[derive(Default)]
+pub struct StateY;
+
+#[finny_fsm]
+fn build_fsm_ignore_all(mut fsm: FsmBuilder<IgnoreAllMachine, ()>) -> BuiltFsm {
+    fsm.initial_states::<(StateX, StateY)>();
+    fsm.ignore_all_unhandled();
+
+    fsm.state::<StateX>()
+        .on_event::<EventGo>()
+        .self_transition();
+
+    fsm.state::<StateY>()
+        .on_event::<EventBack>()
+        .self_transition();
+
+    fsm.state::<StateY>()
+        .on_event::<EventNoise>()
+        .self_transition()
+        .guard(|_, _, _| false);
+
+    fsm.build()
+}
+
+#[test]
+fn test_ignore_all_unhandled() -> FsmResult<()> {
+    let mut fsm = IgnoreAllMachine::new(())?;
+    fsm.start()?;
+
+    fsm.dispatch(EventGo)?;
+    fsm.dispatch(EventBack)?;
+
+    // none of the regions can handle it
+    fsm.dispatch(EventNoise)?;
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct StateInner;
+#[derive(Default)]
+pub struct StateOther;
+
+#[derive(Clone)]
+pub struct EventInner;
+#[derive(Clone)]
+pub struct EventOther;
+
+#[finny_fsm]
+fn build_child_fsm(mut fsm: FsmBuilder<ChildMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateInner>();
+
+    fsm.state::<StateInner>()
+        .on_event::<EventInner>()
+        .transition_to::<StateOther>();
+
+    fsm.state::<StateOther>()
+        .on_event::<EventOther>()
+        .transition_to::<StateInner>();
+
+    fsm.build()
+}
+
+#[finny_fsm]
+fn build_handling_parent_fsm(mut fsm: FsmBuilder<HandlingParentMachine, UnhandledContext>) -> BuiltFsm {
+    fsm.initial_state::<ChildMachine>();
+
+    fsm.sub_machine::<ChildMachine>()
+        .on_unhandled(|ev, ctx| {
+            ctx.unhandled.push(ev.as_ref().to_string());
+        })
+        .on_event::<EventGo>()
+        .transition_to::<StateA>();
+
+    fsm.state::<StateA>();
+
+    fsm.build()
+}
+
+#[finny_fsm]
+fn build_ignoring_parent_fsm(mut fsm: FsmBuilder<IgnoringParentMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<ChildMachine>();
+    fsm.ignore_all_unhandled();
+
+    fsm.sub_machine::<ChildMachine>()
+        .on_event::<EventGo>()
+        .transition_to::<StateA>();
+
+    fsm.state::<StateA>();
+
+    fsm.build()
+}
+
+#[finny_fsm]
+fn build_strict_parent_fsm(mut fsm: FsmBuilder<StrictParentMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<ChildMachine>();
+
+    fsm.sub_machine::<ChildMachine>()
+        .on_event::<EventGo>()
+        .transition_to::<StateA>();
+
+    fsm.state::<StateA>();
+
+    fsm.build()
+}
+
+#[test]
+fn test_unhandled_by_submachine() -> FsmResult<()> {
+    // the handler of the submachine's state
+    let mut fsm = HandlingParentMachine::new(UnhandledContext::default())?;
+    fsm.start()?;
+    fsm.dispatch(HandlingParentMachineEvents::ChildMachine(ChildMachineEvents::EventOther(EventOther)))?;
+    assert_eq!(vec!["ChildMachineEvents"], fsm.unhandled);
+    fsm.dispatch(HandlingParentMachineEvents::ChildMachine(ChildMachineEvents::EventInner(EventInner)))?;
+    assert_eq!(1, fsm.unhandled.len());
+
+    // the machine's policy
+    let mut fsm = IgnoringParentMachine::new(())?;
+    fsm.start()?;
+    fsm.dispatch(IgnoringParentMachineEvents::ChildMachine(ChildMachineEvents::EventOther(EventOther)))?;
+
+    // the error of the submachine is returned
+    let mut fsm = StrictParentMachine::new(())?;
+    fsm.start()?;
+    match fsm.dispatch(StrictParentMachineEvents::ChildMachine(ChildMachineEvents::EventOther(EventOther))) {
+        Err(FsmError::NoTransition(ctx)) => {
+            assert_eq!(std::any::type_name::<ChildMachine>(), ctx.fsm);
+            assert_eq!("EventOther", ctx.event);
+        },
+        r => panic!("Expected NoTransition, got {:?}", r)
+    }
+
+    Ok(())
+}
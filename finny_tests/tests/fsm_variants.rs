@@ -0,0 +1,80 @@
+extern crate finny;
+
+use finny::{AllVariants, FsmFactory, FsmResult, FsmStaticName, decl::{BuiltFsm, FsmBuilder}, finny_fsm};
+
+#[derive(Default)]
+pub struct StateIdle;
+
+#[derive(Clone)]
+pub struct EventRun;
+#[derive(Clone)]
+pub struct EventHalt;
+
+#[finny_fsm]
+fn build_fsm(mut fsm: FsmBuilder<PumpMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateIdle>();
+
+    fsm.state::<StateIdle>()
+        .on_event::<EventRun>()
+        .transition_to::<MotorMachine>();
+
+    fsm.sub_machine::<MotorMachine>()
+        .on_event::<EventHalt>()
+        .transition_to::<StateIdle>();
+
+    fsm.build()
+}
+
+#[derive(Default)]
+pub struct StateSpinning;
+
+#[derive(Clone)]
+pub struct EventSpeedUp;
+
+#[finny_fsm]
+fn build_motor_fsm(mut fsm: FsmBuilder<MotorMachine, ()>) -> BuiltFsm {
+    fsm.initial_state::<StateSpinning>();
+
+    fsm.state::<StateSpinning>()
+        .on_event::<EventSpeedUp>()
+        .self_transition();
+
+    fsm.build()
+}
+
+#[test]
+fn test_state_variants() {
+    assert_eq!(2, PumpMachineCurrentState::STATE_COUNT);
+
+    let names: Vec<_> = PumpMachineCurrentState::iter().map(|s| s.name()).collect();
+    assert_eq!(vec!["MotorMachine", "StateIdle"], names);
+    assert_eq!(&PumpMachineCurrentState::ALL[..], &PumpMachineCurrentState::iter().collect::<Vec<_>>()[..]);
+
+    const IDLE: &str = PumpMachineCurrentState::StateIdle.name();
+    assert_eq!("StateIdle", IDLE);
+}
+
+#[test]
+fn test_event_variants() -> FsmResult<()> {
+    assert_eq!(3, PumpMachineEvents::EVENT_COUNT);
+
+    let names: Vec<_> = PumpMachineEventKind::iter().map(|e| e.name()).collect();
+    assert_eq!(vec!["EventHalt", "EventRun", "MotorMachineEvents"], names);
+
+    let event: PumpMachineEvents = EventRun.into();
+    assert_eq!(PumpMachineEventKind::EventRun, event.kind());
+    assert_eq!(event.static_name(), event.kind().static_name());
+
+    let event: PumpMachineEvents = MotorMachineEvents::EventSpeedUp(EventSpeedUp).into();
+    assert_eq!(PumpMachineEventKind::MotorMachine, event.kind());
+
+    let mut fsm = PumpMachine::new(())?;
+    fsm.start()?;
+    let states: Vec<_> = fsm.get_current_states().iter().map(|s| match s {
+        finny::FsmCurrentState::State(s) => s.name(),
+        finny::FsmCurrentState::Stopped | finny::FsmCurrentState::InTransition(_) => "Stopped"
+    }).collect();
+    assert_eq!(vec!["StateIdle"], states);
+
+    Ok(())
+}